@@ -6,6 +6,10 @@ use alloc::collections::BTreeMap;
 use near_lite_client::{
 	CryptoHash, LightClientBlockView, NearLiteClientTrait, TrustedCheckpoint, ValidatorStakeView,
 };
+use near_lite_relayer::{
+	blockchain_connector::{BlockchainConnector, NearNetwork, NearRpc},
+	replay::ReplayConnector,
+};
 use near_primitives_wasm::HostFunctions;
 
 pub struct NearHostFunctions;
@@ -49,3 +53,19 @@ impl NearLiteClientTrait for LightClient {
 		self.head.inner_lite.height
 	}
 }
+
+/// Recordings of testnet replayed by `testnet`
+pub const TESTNET_RECORDINGS: &str =
+	concat!(env!("CARGO_MANIFEST_DIR"), "/../test-fixtures/recordings/testnet");
+
+/// Testnet, as recorded in `TESTNET_RECORDINGS`.
+///
+/// With `NEAR_RECORD=1`, connects to testnet instead and records the exchanges to
+/// `TESTNET_RECORDINGS`, which should be emptied beforehand.
+pub fn testnet() -> Box<dyn NearRpc> {
+	if std::env::var_os("NEAR_RECORD").is_some() {
+		Box::new(BlockchainConnector::new(NearNetwork::Testnet).record_to(TESTNET_RECORDINGS))
+	} else {
+		Box::new(ReplayConnector::open(TESTNET_RECORDINGS).expect("invalid testnet recordings"))
+	}
+}
@@ -1,29 +1,20 @@
-use std::{
-	thread::{self},
-	time::Duration,
-};
-
-use integration_tests::{LightClient, NearHostFunctions};
-use near_lite_relayer::{
-	blockchain_connector::{BlockchainConnector, NearNetwork},
-	state::LightClientState,
-};
-
-use borsh::{BorshDeserialize, BorshSerialize};
+use integration_tests::{testnet, LightClient, NearHostFunctions};
+use near_lite_relayer::state::LightClientState;
+
 use near_lite_client::prelude::*;
 
 /// ## Both Relayer and Lite Client
 ///
 /// Note: we're testing happy path only here
 ///
-/// Connects to Testnet using the relayer logic from `near-lite-relayer`
+/// Replays Testnet using the relayer logic from `near-lite-relayer`
 /// Serializes the data, and feeds it into the light client
 /// Validates that the block is correct
-#[tokio::test]
-async fn both_relayer_and_lite_client() {
+#[test]
+fn both_relayer_and_lite_client() {
 	env_logger::init();
 
-	let blockchain_connector = BlockchainConnector::new(NearNetwork::Testnet);
+	let blockchain_connector = testnet();
 
 	let (_, almost_latest_height) = blockchain_connector
 		.get_almost_latest_finalized_block_hash_and_height()
@@ -43,83 +34,62 @@ async fn both_relayer_and_lite_client() {
 	let light_client_block_view =
 		blockchain_connector.get_light_client_block_view(block_hash_past_epoch).unwrap();
 
-	let serialized_block_view = light_client_block_view.try_to_vec().unwrap();
-	let block_view_for_lite_client =
-		BorshDeserialize::try_from_slice(&serialized_block_view).unwrap();
+	let block_view_for_lite_client = LightClientBlockView::from(light_client_block_view.clone());
 
 	// TODO: assert that we're on a past epoch
-	let _lite_client_relayer = LightClientState::new_from_checkpoint(light_client_block_view);
+	let mut lite_client_relayer = LightClientState::new_from_checkpoint(light_client_block_view);
 
 	let trusted_checkpoint = TrustedCheckpoint(block_view_for_lite_client);
 	let mut lite_client = LightClient::new_from_checkpoint(trusted_checkpoint, 10);
 
-	let (tx, mut rx) = tokio::sync::mpsc::channel(1);
-
-	tokio::task::spawn(async move {
-		let result = tokio::task::spawn_blocking(move || {
-			let sleep_time = Duration::from_secs(3);
-			let mut counter = 0;
-			loop {
-				let current_block_height = lite_client.current_block_height();
-				log::info!("current block height={}", current_block_height);
-				thread::sleep(sleep_time);
-
-				let current_block_hash = blockchain_connector
-					.get_block_hash_from_block_number(current_block_height)
-					.unwrap();
-
-				let near_light_client_block_view =
-					blockchain_connector.get_light_client_block_view(current_block_hash).unwrap();
-
-				log::info!(
-					"fetched block to validate with height={:?}",
-					near_light_client_block_view.inner_lite.height
-				);
-
-				let light_client_block_view = LightClientBlockView::try_from_slice(
-					near_light_client_block_view.try_to_vec().unwrap().as_ref(),
-				)
-				.unwrap();
-				if light_client_block_view.inner_lite.height <= lite_client.current_block_height() {
-					log::info!("block has not yet been updated");
-					continue;
-				}
-
-				log::info!("validating block height={}", light_client_block_view.inner_lite.height);
-
-				let head = &lite_client.head;
-				let epoch_block_producers = &lite_client.epoch_block_producers;
-				assert!(validate_head::<NearHostFunctions>(
-					head,
-					&light_client_block_view,
-					epoch_block_producers
-				)
-				.is_ok());
-
-				lite_client.update_head(head.clone());
-
-				log::info!(
-					"validated block height={} and head is on height={}",
-					light_client_block_view.inner_lite.height,
-					lite_client.current_block_height()
-				);
-				counter += 1;
-				if counter == 3 {
-					break;
-				}
-			}
-		})
-		.await;
-		if let Ok(_) = result {
-			tx.send(()).await.unwrap();
-		}
-	});
-
-	// we do three loops, each of 3 seconds (total of 9 secs)
-	// therefore the receiver should have a response before out threshold, which in this case is
-	// _epsilon_ higher than those 9 secs
-	let waiting_time = 9 + 3;
-	tokio::time::timeout(tokio::time::Duration::from_secs(waiting_time), rx.recv())
-		.await
-		.unwrap();
+	// the checkpoint is in a past epoch: the following blocks are final already
+	for _ in 0..3 {
+		let current_block_height = lite_client.current_block_height();
+		log::info!("current block height={}", current_block_height);
+
+		let current_block_hash = blockchain_connector
+			.get_block_hash_from_block_number(current_block_height)
+			.unwrap();
+
+		let near_light_client_block_view =
+			blockchain_connector.get_light_client_block_view(current_block_hash).unwrap();
+
+		log::info!(
+			"fetched block to validate with height={:?}",
+			near_light_client_block_view.inner_lite.height
+		);
+
+		let light_client_block_view =
+			LightClientBlockView::from(near_light_client_block_view.clone());
+		assert!(light_client_block_view.inner_lite.height > lite_client.current_block_height());
+
+		log::info!("validating block height={}", light_client_block_view.inner_lite.height);
+
+		let head = &lite_client.head;
+		let epoch_block_producers = &lite_client.epoch_block_producers;
+		assert!(validate_head::<NearHostFunctions>(
+			head,
+			&light_client_block_view,
+			epoch_block_producers
+		)
+		.is_ok());
+
+		lite_client.update_head(light_client_block_view.clone());
+		// the head moved to the validated header
+		assert!(lite_client.current_block_height() > current_block_height);
+		assert_eq!(lite_client.current_block_height(), light_client_block_view.inner_lite.height);
+		// the relayer accepts the same header
+		lite_client_relayer.validate_head(&near_light_client_block_view).unwrap();
+		assert_eq!(lite_client_relayer.head_height(), lite_client.current_block_height());
+		assert_eq!(
+			lite_client_relayer.head_hash().0,
+			lite_client.head.current_block_hash::<NearHostFunctions>().0
+		);
+
+		log::info!(
+			"validated block height={} and head is on height={}",
+			light_client_block_view.inner_lite.height,
+			lite_client.current_block_height()
+		);
+	}
 }
@@ -1,22 +1,21 @@
-use integration_tests::{LightClient, NearHostFunctions};
-use near_lite_relayer::blockchain_connector::{BlockchainConnector, NearNetwork};
+use integration_tests::{testnet, LightClient, NearHostFunctions};
 
-use borsh::{BorshDeserialize, BorshSerialize};
 use near_lite_client::{
-	validate_transaction, CryptoHash, MerklePath, NearLiteClientTrait, OutcomeProof,
-	TrustedCheckpoint,
+	validate_transaction, CryptoHash, LightClientBlockView, MerklePath, NearLiteClientTrait,
+	OutcomeProof, TrustedCheckpoint,
 };
+use near_lite_relayer::blockchain_connector::ProofRequest;
 
 /// ## Both Relayer and Lite Client - testing tx validation
 ///
 /// Finds one transaction from the last 300 blocks
 /// Validates that the transaction is part of a valid block that the lite client has already
 /// validated.
-#[tokio::test]
-async fn both_relayer_and_lite_client_validate_tx() {
+#[test]
+fn both_relayer_and_lite_client_validate_tx() {
 	env_logger::init();
 
-	let blockchain_connector = BlockchainConnector::new(NearNetwork::Testnet);
+	let blockchain_connector = testnet();
 
 	let (almost_last_block_hash, almost_latest_height) = blockchain_connector
 		.get_almost_latest_finalized_block_hash_and_height()
@@ -26,9 +25,7 @@ async fn both_relayer_and_lite_client_validate_tx() {
 		.get_light_client_block_view(almost_last_block_hash)
 		.unwrap();
 
-	let serialized_block_view = light_client_block_view.try_to_vec().unwrap();
-	let block_view_for_lite_client =
-		BorshDeserialize::try_from_slice(&serialized_block_view).unwrap();
+	let block_view_for_lite_client = LightClientBlockView::from(light_client_block_view);
 
 	let trusted_checkpoint = TrustedCheckpoint(block_view_for_lite_client);
 	let _ = LightClient::new_from_checkpoint(trusted_checkpoint, 10);
@@ -43,29 +40,18 @@ async fn both_relayer_and_lite_client_validate_tx() {
 			if !tx_info.is_empty() {
 				let (tx_hash, sender_id) = tx_info[0].clone();
 				let tx_light_client_proof = blockchain_connector
-					.get_light_client_proof_transaction(almost_last_block_hash, tx_hash, sender_id)
+					.light_client_proof(
+						almost_last_block_hash,
+						&ProofRequest::Transaction { tx_hash, sender_id },
+					)
 					.unwrap();
 
-				let expected_block_outcome_root = CryptoHash::try_from_slice(
-					tx_light_client_proof
-						.block_header_lite
-						.inner_lite
-						.outcome_root
-						.try_to_vec()
-						.unwrap()
-						.as_ref(),
-				)
-				.unwrap();
-
-				let outcome_root_proof = MerklePath::try_from_slice(
-					tx_light_client_proof.outcome_root_proof.try_to_vec().unwrap().as_ref(),
-				)
-				.unwrap();
-
-				let outcome_proof = OutcomeProof::try_from_slice(
-					tx_light_client_proof.outcome_proof.try_to_vec().unwrap().as_ref(),
-				)
-				.unwrap();
+				let expected_block_outcome_root = CryptoHash::from(
+					tx_light_client_proof.block_header_lite.inner_lite.outcome_root,
+				);
+				let outcome_root_proof: MerklePath =
+					tx_light_client_proof.outcome_root_proof.into_iter().map(Into::into).collect();
+				let outcome_proof = OutcomeProof::from(tx_light_client_proof.outcome_proof);
 
 				validate_transaction::<NearHostFunctions>(
 					&outcome_proof,
@@ -0,0 +1,731 @@
+//! # NEAR chain simulator
+//!
+//! Deterministic fake NEAR chain producing the `LightClientBlockView`s validated by
+//! `near-lite-client`, to write tests without hand-editing recorded RPC responses.
+//!
+//! - the block producers are real ed25519 keys derived from their account ids, with configurable
+//!   stakes, and can be rotated at any epoch
+//! - the epoch ids, `next_bp_hash` and `next_bps` follow the rotations
+//! - `approvals_after_next` are signed over the approval message of the next block
+//! - outcomes can be included in the blocks, with their outcome roots and merkle paths
+//!
+//! ```ignore
+//! let validators = vec![Validator::new("alice", 100), Validator::new("bob", 50)];
+//! let mut chain = ChainSimulator::new(validators);
+//! // the light client block of a block needs the next one
+//! chain.produce_blocks(2 * chain.epoch_length() + 1);
+//! let head = chain.light_client_block(chain.last_block_of_epoch(0)).unwrap();
+//! let block = chain.light_client_block(chain.last_block_of_epoch(1)).unwrap();
+//! // alice's approval, with more than a third of the stake, left out
+//! let tampering = Tampering { missing_approvals: [0].into(), ..Default::default() };
+//! let height = chain.last_block_of_epoch(1);
+//! let unfinalized_block = chain.tampered_light_client_block(height, &tampering).unwrap();
+//! ```
+//!
+//! The epoch of a block is `(height - genesis_height) / epoch_length`. As on NEAR, the id of an
+//! epoch is the hash of the last block of the epoch two epochs before, except for the first two
+//! epochs, whose ids are derived from their number.
+
+pub mod merkle;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use borsh::BorshSerialize;
+use near_primitives_wasm::{
+	ApprovalInner, Balance, BlockHeaderInnerLiteView, BlockHeaderInnerLiteViewFinal, CryptoHash,
+	ExecutionOutcomeView, HostFunctions, LightClientBlockView, MerklePath, OutcomeProof, PublicKey,
+	Signature, ValidatorStakeView, ValidatorStakeViewV1,
+};
+use sha2::{Digest, Sha256};
+use sp_core::{ed25519, Pair};
+
+use crate::merkle::merklize;
+
+pub const DEFAULT_EPOCH_LENGTH: u64 = 10;
+pub const DEFAULT_GENESIS_HEIGHT: u64 = 1;
+/// timestamp of the genesis block, in nanoseconds; the blocks follow each other every second
+pub const GENESIS_TIMESTAMP: u64 = 1_600_000_000_000_000_000;
+
+/// `HostFunctions` to validate the simulated blocks with `near-lite-client`
+pub struct SimulatorHostFunctions;
+
+impl HostFunctions for SimulatorHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		Sha256::digest(data).into()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Validator {
+	pub account_id: String,
+	pub stake: Balance,
+}
+
+impl Validator {
+	pub fn new(account_id: impl Into<String>, stake: Balance) -> Self {
+		Self { account_id: account_id.into(), stake }
+	}
+
+	/// key of the validator, derived from its account id
+	pub fn key_pair(&self) -> ed25519::Pair {
+		ed25519::Pair::from_seed(&Sha256::digest(self.account_id.as_bytes()).into())
+	}
+
+	pub fn public_key(&self) -> PublicKey {
+		PublicKey::from_raw(self.key_pair().public().as_ref())
+	}
+
+	pub fn stake_view(&self) -> ValidatorStakeView {
+		ValidatorStakeView::V1(ValidatorStakeViewV1 {
+			account_id: self.account_id.clone(),
+			public_key: self.public_key(),
+			stake: self.stake,
+		})
+	}
+}
+
+/// Deviations of a light client block from the honest one. The approvals are signed over the
+/// tampered block, so that only the tampered property is invalid.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tampering {
+	/// epoch id of the block instead of its own
+	pub epoch_id: Option<CryptoHash>,
+	/// inner rest hash of the block instead of its own: a different block at the same height,
+	/// which equivocating block producers approve
+	pub inner_rest_hash: Option<CryptoHash>,
+	/// indexes of the block producers whose approval is left out
+	pub missing_approvals: BTreeSet<usize>,
+	/// indexes of the block producers whose approval is signed by a key outside of the epoch
+	pub forged_approvals: BTreeSet<usize>,
+}
+
+/// Proof of an outcome included in a simulated block, as served by
+/// `EXPERIMENTAL_light_client_proof`
+#[derive(Debug, Clone)]
+pub struct SimulatedOutcomeProof {
+	pub block_height: u64,
+	pub outcome_proof: OutcomeProof,
+	/// path of the outcome root of the shard to the outcome root of the block
+	pub outcome_root_proof: MerklePath,
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedBlock {
+	prev_block_hash: CryptoHash,
+	inner_lite: BlockHeaderInnerLiteView,
+	inner_rest_hash: CryptoHash,
+	hash: CryptoHash,
+	/// outcomes of every shard, with their ids
+	shards: Vec<Vec<(CryptoHash, ExecutionOutcomeView)>>,
+}
+
+impl SimulatedBlock {
+	fn inner_hash(&self) -> CryptoHash {
+		inner_hash(&self.inner_lite, self.inner_rest_hash)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainSimulator {
+	genesis_height: u64,
+	epoch_length: u64,
+	/// block producers from an epoch on
+	validator_sets: BTreeMap<u64, Vec<Validator>>,
+	blocks: Vec<SimulatedBlock>,
+}
+
+impl ChainSimulator {
+	/// chain without blocks yet, produced by `validators` until they are rotated
+	pub fn new(validators: Vec<Validator>) -> Self {
+		Self {
+			genesis_height: DEFAULT_GENESIS_HEIGHT,
+			epoch_length: DEFAULT_EPOCH_LENGTH,
+			validator_sets: [(0, validators)].into_iter().collect(),
+			blocks: Vec::new(),
+		}
+	}
+
+	pub fn with_genesis_height(mut self, genesis_height: u64) -> Self {
+		assert!(self.blocks.is_empty(), "the genesis block is already produced");
+		self.genesis_height = genesis_height;
+		self
+	}
+
+	pub fn with_epoch_length(mut self, epoch_length: u64) -> Self {
+		assert!(self.blocks.is_empty(), "the genesis block is already produced");
+		assert!(epoch_length > 0, "empty epochs");
+		self.epoch_length = epoch_length;
+		self
+	}
+
+	/// Rotates the block producers from `epoch` on. The block producers of an epoch are committed
+	/// to by the blocks of the previous epoch, so `epoch` must be after the epoch following the
+	/// head.
+	pub fn set_validators(&mut self, epoch: u64, validators: Vec<Validator>) {
+		if let Some(head_height) = self.head_height() {
+			assert!(
+				epoch > self.epoch_of(head_height) + 1,
+				"the block producers of epoch {} are already committed to",
+				epoch
+			);
+		}
+		self.validator_sets.insert(epoch, validators);
+	}
+
+	pub fn genesis_height(&self) -> u64 {
+		self.genesis_height
+	}
+
+	pub fn epoch_length(&self) -> u64 {
+		self.epoch_length
+	}
+
+	/// height of the latest block, `None` before the genesis block
+	pub fn head_height(&self) -> Option<u64> {
+		self.blocks.last().map(|block| block.inner_lite.height)
+	}
+
+	pub fn epoch_of(&self, height: u64) -> u64 {
+		(height - self.genesis_height) / self.epoch_length
+	}
+
+	pub fn last_block_of_epoch(&self, epoch: u64) -> u64 {
+		self.genesis_height + (epoch + 1) * self.epoch_length - 1
+	}
+
+	/// Id of `epoch`. Only known once the last block of the epoch two epochs before is produced.
+	pub fn epoch_id(&self, epoch: u64) -> Option<CryptoHash> {
+		if epoch < 2 {
+			Some(CryptoHash::hash_bytes(format!("epoch {}", epoch).as_bytes()))
+		} else {
+			self.block_hash(self.last_block_of_epoch(epoch - 2))
+		}
+	}
+
+	pub fn validators(&self, epoch: u64) -> &[Validator] {
+		self.validator_sets
+			.range(..=epoch)
+			.next_back()
+			.map(|(_, validators)| validators.as_slice())
+			.unwrap_or_default()
+	}
+
+	pub fn block_producers(&self, epoch: u64) -> Vec<ValidatorStakeView> {
+		self.validators(epoch).iter().map(Validator::stake_view).collect()
+	}
+
+	pub fn block_hash(&self, height: u64) -> Option<CryptoHash> {
+		self.block(height).map(|block| block.hash)
+	}
+
+	fn block(&self, height: u64) -> Option<&SimulatedBlock> {
+		let index = height.checked_sub(self.genesis_height)?;
+		self.blocks.get(usize::try_from(index).ok()?)
+	}
+
+	/// produces a block without outcomes and returns its height
+	pub fn produce_block(&mut self) -> u64 {
+		self.produce_block_with_outcomes(Vec::new())
+	}
+
+	/// produces `count` blocks and returns the height of the last one
+	pub fn produce_blocks(&mut self, count: u64) -> Option<u64> {
+		(0..count).map(|_| self.produce_block()).last()
+	}
+
+	/// Produces a block with the outcomes of every shard, and their ids, and returns its height.
+	pub fn produce_block_with_outcomes(
+		&mut self,
+		shards: Vec<Vec<(CryptoHash, ExecutionOutcomeView)>>,
+	) -> u64 {
+		let height = self.head_height().map_or(self.genesis_height, |height| height + 1);
+		let epoch = self.epoch_of(height);
+		let prev_block_hash = self.blocks.last().map(|block| block.hash).unwrap_or_default();
+		let block_hashes = self.blocks.iter().map(|block| block.hash).collect::<Vec<_>>();
+
+		let inner_lite = BlockHeaderInnerLiteView {
+			height,
+			epoch_id: self.epoch_id(epoch).expect("the previous epochs are produced"),
+			next_epoch_id: self.epoch_id(epoch + 1).expect("the previous epochs are produced"),
+			prev_state_root: CryptoHash::hash_bytes(format!("state {}", height).as_bytes()),
+			outcome_root: merklize(&shard_outcome_roots(&shards)).0,
+			timestamp: GENESIS_TIMESTAMP + (height - self.genesis_height) * 1_000_000_000,
+			timestamp_nanosec: GENESIS_TIMESTAMP + (height - self.genesis_height) * 1_000_000_000,
+			next_bp_hash: CryptoHash::hash_borsh(&self.block_producers(epoch + 1)),
+			// the blocks before this one
+			block_merkle_root: merklize(&block_hashes).0,
+		};
+		let inner_rest_hash = CryptoHash::hash_bytes(format!("inner rest {}", height).as_bytes());
+		let hash = block_hash(&inner_lite, inner_rest_hash, prev_block_hash);
+		self.blocks.push(SimulatedBlock {
+			prev_block_hash,
+			inner_lite,
+			inner_rest_hash,
+			hash,
+			shards,
+		});
+		height
+	}
+
+	/// Light client block of the block at `height`, approved by all its block producers. The next
+	/// block must already be produced.
+	///
+	/// `next_bps`, the block producers of the next epoch, is always set.
+	pub fn light_client_block(&self, height: u64) -> Option<LightClientBlockView> {
+		self.tampered_light_client_block(height, &Tampering::default())
+	}
+
+	pub fn tampered_light_client_block(
+		&self,
+		height: u64,
+		tampering: &Tampering,
+	) -> Option<LightClientBlockView> {
+		let block = self.block(height)?;
+		let next_block = self.block(height + 1)?;
+		let epoch = self.epoch_of(height);
+
+		let mut inner_lite = block.inner_lite.clone();
+		if let Some(epoch_id) = tampering.epoch_id {
+			inner_lite.epoch_id = epoch_id;
+		}
+		let mut block_view = LightClientBlockView {
+			prev_block_hash: block.prev_block_hash,
+			next_block_inner_hash: next_block.inner_hash(),
+			inner_lite,
+			inner_rest_hash: tampering.inner_rest_hash.unwrap_or(block.inner_rest_hash),
+			next_bps: Some(self.block_producers(epoch + 1)),
+			approvals_after_next: Vec::new(),
+		};
+
+		let approval_message = approval_message(&block_view);
+		let forger = Validator::new("forger", 0).key_pair();
+		block_view.approvals_after_next = self
+			.validators(epoch)
+			.iter()
+			.enumerate()
+			.map(|(index, validator)| {
+				if tampering.missing_approvals.contains(&index) {
+					None
+				} else if tampering.forged_approvals.contains(&index) {
+					Some(Signature::Ed25519(forger.sign(&approval_message)))
+				} else {
+					Some(Signature::Ed25519(validator.key_pair().sign(&approval_message)))
+				}
+			})
+			.collect();
+		Some(block_view)
+	}
+
+	/// proof of the outcome `id`, included in any block
+	pub fn outcome_proof(&self, id: CryptoHash) -> Option<SimulatedOutcomeProof> {
+		self.blocks.iter().find_map(|block| {
+			let (shard_index, outcome_index) =
+				block.shards.iter().enumerate().find_map(|(shard_index, outcomes)| {
+					outcomes
+						.iter()
+						.position(|(outcome_id, _)| *outcome_id == id)
+						.map(|outcome_index| (shard_index, outcome_index))
+				})?;
+
+			let outcomes = &block.shards[shard_index];
+			let outcome_hashes = outcomes
+				.iter()
+				.map(|(id, outcome)| outcome_hash(*id, outcome))
+				.collect::<Vec<_>>();
+			let (_, mut outcome_paths) = merklize(&outcome_hashes);
+			let (_, mut shard_paths) = merklize(&shard_outcome_roots(&block.shards));
+			Some(SimulatedOutcomeProof {
+				block_height: block.inner_lite.height,
+				outcome_proof: OutcomeProof {
+					proof: outcome_paths.swap_remove(outcome_index),
+					block_hash: block.hash,
+					id,
+					outcome: outcomes[outcome_index].1.clone(),
+				},
+				outcome_root_proof: shard_paths.swap_remove(shard_index),
+			})
+		})
+	}
+
+	/// Path of the block at `height` to the `block_merkle_root` of the block at `head_height`,
+	/// which covers the blocks before it.
+	pub fn block_proof(&self, height: u64, head_height: u64) -> Option<MerklePath> {
+		if height >= head_height || self.block(head_height).is_none() {
+			return None;
+		}
+		let index = usize::try_from(height.checked_sub(self.genesis_height)?).ok()?;
+		let head_index = usize::try_from(head_height - self.genesis_height).ok()?;
+		let block_hashes =
+			self.blocks[..head_index].iter().map(|block| block.hash).collect::<Vec<_>>();
+		let (_, mut paths) = merklize(&block_hashes);
+		Some(paths.swap_remove(index))
+	}
+}
+
+/// Successful outcome of a transaction or a receipt without return value, to include in a block.
+pub fn success_outcome(
+	executor_id: impl Into<String>,
+	logs: Vec<String>,
+	receipt_ids: Vec<CryptoHash>,
+) -> ExecutionOutcomeView {
+	ExecutionOutcomeView {
+		logs,
+		receipt_ids,
+		gas_burnt: 2_428_395_018_008,
+		tokens_burnt: 242_839_501_800_800_000_000,
+		executor_id: executor_id.into(),
+		// borsh of `ExecutionStatus::SuccessValue(vec![])`
+		status: vec![2, 0, 0, 0, 0],
+	}
+}
+
+/// Hash of an outcome in the outcome tree of its shard: `sha256(borsh([id, ...merkelization
+/// hashes]))`, the merkelization hashes being the hash of the outcome without its logs and the
+/// hash of every log.
+pub fn outcome_hash(id: CryptoHash, outcome: &ExecutionOutcomeView) -> CryptoHash {
+	let outcome_without_logs = [
+		outcome.receipt_ids.try_to_vec().unwrap(),
+		outcome.gas_burnt.try_to_vec().unwrap(),
+		outcome.tokens_burnt.try_to_vec().unwrap(),
+		outcome.executor_id.try_to_vec().unwrap(),
+		outcome.status.clone(),
+	]
+	.concat();
+	let hashes = [id, CryptoHash::hash_bytes(&outcome_without_logs)]
+		.into_iter()
+		.chain(outcome.logs.iter().map(|log| CryptoHash::hash_bytes(log.as_bytes())))
+		.collect::<Vec<_>>();
+	CryptoHash::hash_borsh(&hashes)
+}
+
+/// leaves of the outcome tree of a block: the hash of the outcome root of every shard
+fn shard_outcome_roots(shards: &[Vec<(CryptoHash, ExecutionOutcomeView)>]) -> Vec<CryptoHash> {
+	shards
+		.iter()
+		.map(|outcomes| {
+			let outcome_hashes = outcomes
+				.iter()
+				.map(|(id, outcome)| outcome_hash(*id, outcome))
+				.collect::<Vec<_>>();
+			CryptoHash::hash_borsh(&merklize(&outcome_hashes).0)
+		})
+		.collect()
+}
+
+fn inner_hash(inner_lite: &BlockHeaderInnerLiteView, inner_rest_hash: CryptoHash) -> CryptoHash {
+	let inner_lite_hash =
+		CryptoHash::hash_borsh(&BlockHeaderInnerLiteViewFinal::from(inner_lite.clone()));
+	CryptoHash::hash_bytes(&[inner_lite_hash.as_ref(), inner_rest_hash.as_ref()].concat())
+}
+
+fn block_hash(
+	inner_lite: &BlockHeaderInnerLiteView,
+	inner_rest_hash: CryptoHash,
+	prev_block_hash: CryptoHash,
+) -> CryptoHash {
+	let inner_hash = inner_hash(inner_lite, inner_rest_hash);
+	CryptoHash::hash_bytes(&[inner_hash.as_ref(), prev_block_hash.as_ref()].concat())
+}
+
+/// message signed by the block producers in `approvals_after_next`: the endorsement of the next
+/// block at the height after it
+pub fn approval_message(block_view: &LightClientBlockView) -> Vec<u8> {
+	let current_block_hash =
+		block_hash(&block_view.inner_lite, block_view.inner_rest_hash, block_view.prev_block_hash);
+	let next_block_hash = CryptoHash::hash_bytes(
+		&[block_view.next_block_inner_hash.as_ref(), current_block_hash.as_ref()].concat(),
+	);
+	[
+		ApprovalInner::Endorsement(next_block_hash).try_to_vec().unwrap(),
+		(block_view.inner_lite.height + 2).to_le_bytes().to_vec(),
+	]
+	.concat()
+}
+
+#[cfg(test)]
+mod tests {
+	use near_lite_client::{
+		validate_head, validate_light_block_with_report, validate_transaction, ApprovalStatus,
+		NearLiteClientError,
+	};
+
+	use super::*;
+
+	type H = SimulatorHostFunctions;
+
+	fn validators() -> Vec<Validator> {
+		vec![
+			Validator::new("alice.near", 400),
+			Validator::new("bob.near", 300),
+			Validator::new("carol.near", 200),
+			Validator::new("dave.near", 100),
+		]
+	}
+
+	/// light client following the chain from the last block of an epoch
+	struct LiteClient {
+		head: LightClientBlockView,
+		epoch_block_producers: BTreeMap<CryptoHash, Vec<ValidatorStakeView>>,
+	}
+
+	impl LiteClient {
+		fn new(checkpoint: LightClientBlockView) -> Self {
+			let epoch_block_producers =
+				[(checkpoint.inner_lite.next_epoch_id, checkpoint.next_bps.clone().unwrap())]
+					.into_iter()
+					.collect();
+			Self { head: checkpoint, epoch_block_producers }
+		}
+
+		fn validate(&self, block_view: &LightClientBlockView) -> Result<(), NearLiteClientError> {
+			validate_head::<H>(&self.head, block_view, &self.epoch_block_producers)
+		}
+
+		fn update_head(&mut self, block_view: LightClientBlockView) {
+			self.validate(&block_view).unwrap();
+			if let Some(next_bps) = &block_view.next_bps {
+				self.epoch_block_producers
+					.insert(block_view.inner_lite.next_epoch_id, next_bps.clone());
+			}
+			self.head = block_view;
+		}
+	}
+
+	fn chain() -> ChainSimulator {
+		let mut chain = ChainSimulator::new(validators()).with_epoch_length(5);
+		chain.set_validators(
+			2,
+			vec![Validator::new("erin.near", 500), Validator::new("bob.near", 10)],
+		);
+		chain.produce_blocks(5 * chain.epoch_length());
+		chain
+	}
+
+	#[test]
+	fn test_follow_the_chain() {
+		let chain = chain();
+		assert_eq!(chain.head_height(), Some(25));
+		assert_eq!(chain.epoch_of(10), 1);
+		assert_eq!(chain.epoch_id(2), chain.block_hash(chain.last_block_of_epoch(0)));
+
+		let block_view = chain.light_client_block(10).unwrap();
+		assert_eq!(block_view.current_block_hash::<H>(), chain.block_hash(10).unwrap());
+		assert_eq!(block_view.inner_lite.next_epoch_id, chain.epoch_id(2).unwrap());
+		// the next block
+		assert_eq!(
+			CryptoHash::hash_bytes(
+				&[
+					block_view.next_block_inner_hash.as_ref(),
+					chain.block_hash(10).unwrap().as_ref()
+				]
+				.concat()
+			),
+			chain.block_hash(11).unwrap()
+		);
+		assert!(chain.light_client_block(25).is_none());
+
+		let mut lite_client =
+			LiteClient::new(chain.light_client_block(chain.last_block_of_epoch(0)).unwrap());
+		// the validators rotate in the epoch 2
+		for epoch in 1..4 {
+			let block_view = chain.light_client_block(chain.last_block_of_epoch(epoch)).unwrap();
+			assert_eq!(block_view.approvals_after_next.len(), chain.validators(epoch).len());
+			lite_client.update_head(block_view);
+		}
+		assert_eq!(lite_client.head.inner_lite.height, 20);
+		assert_eq!(lite_client.epoch_block_producers.len(), 4);
+	}
+
+	#[test]
+	fn test_adversarial_blocks() {
+		let chain = chain();
+		let lite_client =
+			LiteClient::new(chain.light_client_block(chain.last_block_of_epoch(0)).unwrap());
+		let height = chain.last_block_of_epoch(1);
+		let tampered =
+			|tampering: Tampering| chain.tampered_light_client_block(height, &tampering).unwrap();
+
+		// dave.near has less than a third of the stake
+		let block_view =
+			tampered(Tampering { missing_approvals: [3].into(), ..Default::default() });
+		assert!(lite_client.validate(&block_view).is_ok());
+
+		let block_view =
+			tampered(Tampering { missing_approvals: [1, 3].into(), ..Default::default() });
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::InvalidLiteBlock(_))
+		));
+
+		let block_view = tampered(Tampering { forged_approvals: [2].into(), ..Default::default() });
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::SignatureVerification(_))
+		));
+
+		let block_view = tampered(Tampering {
+			epoch_id: Some(chain.epoch_id(2).unwrap()),
+			..Default::default()
+		});
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::InvalidLiteBlock(_))
+		));
+
+		// signed over the approval message of another block
+		let mut block_view = chain.light_client_block(height).unwrap();
+		block_view.approvals_after_next =
+			chain.light_client_block(height - 1).unwrap().approvals_after_next;
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::SignatureVerification(_))
+		));
+
+		let mut block_view = chain.light_client_block(height).unwrap();
+		block_view.next_bps = None;
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::InvalidLiteBlock(_))
+		));
+
+		let mut block_view = chain.light_client_block(height).unwrap();
+		block_view.next_bps = Some(chain.block_producers(1));
+		assert!(matches!(
+			lite_client.validate(&block_view),
+			Err(NearLiteClientError::InvalidLiteBlock(_))
+		));
+
+		// a validly signed block conflicting with the canonical one
+		let block_view = tampered(Tampering {
+			inner_rest_hash: Some(CryptoHash::hash_bytes(b"equivocation")),
+			..Default::default()
+		});
+		assert!(lite_client.validate(&block_view).is_ok());
+		assert_ne!(block_view.current_block_hash::<H>(), chain.block_hash(height).unwrap());
+	}
+
+	#[test]
+	fn test_validation_report() {
+		let chain = chain();
+		let lite_client =
+			LiteClient::new(chain.light_client_block(chain.last_block_of_epoch(0)).unwrap());
+		let height = chain.last_block_of_epoch(1);
+		let tampered =
+			|tampering: Tampering| chain.tampered_light_client_block(height, &tampering).unwrap();
+		let report = |block_view: &LightClientBlockView| {
+			validate_light_block_with_report::<H>(
+				&lite_client.head,
+				block_view,
+				&lite_client.epoch_block_producers,
+			)
+			.unwrap()
+		};
+
+		let block_view = chain.light_client_block(height).unwrap();
+		let honest = report(&block_view);
+		assert!(honest.is_valid());
+		assert_eq!(honest.block_hash, chain.block_hash(height).unwrap());
+		assert_eq!(honest.approvals.len(), 4);
+		assert!(honest
+			.approvals
+			.iter()
+			.all(|approval| approval.status == ApprovalStatus::Signed));
+		assert_eq!((honest.approved_stake, honest.total_stake), (1000, 1000));
+
+		let block_view = tampered(Tampering { forged_approvals: [2].into(), ..Default::default() });
+		let forged = report(&block_view);
+		assert_eq!(forged.approvals[2].account_id.as_str(), "carol.near");
+		assert_eq!(forged.approvals[2].status, ApprovalStatus::Invalid);
+		assert!(matches!(forged.signatures, Err(NearLiteClientError::SignatureVerification(_))));
+		for (name, outcome) in forged.checks() {
+			assert_eq!(outcome.is_ok(), name != "signatures", "{}", name);
+		}
+		assert!(matches!(forged.into_result(), Err(NearLiteClientError::SignatureVerification(_))));
+
+		let block_view =
+			tampered(Tampering { missing_approvals: [1, 3].into(), ..Default::default() });
+		let missing = report(&block_view);
+		assert_eq!(missing.approvals[1].status, ApprovalStatus::Missing);
+		assert_eq!(missing.approvals[3].status, ApprovalStatus::Missing);
+		assert_eq!(
+			(missing.approved_stake, missing.total_stake, missing.threshold),
+			(600, 1000, 666)
+		);
+		assert!(missing.signatures.is_ok());
+		assert!(matches!(missing.stake, Err(NearLiteClientError::InvalidLiteBlock(_))));
+		assert_eq!(
+			missing.into_result().map_err(|err| format!("{:?}", err)),
+			lite_client.validate(&block_view).map_err(|err| format!("{:?}", err))
+		);
+
+		// the block producers of the epoch 2 aren't known to a lite client at the epoch 0
+		let block_view = chain.light_client_block(chain.last_block_of_epoch(2)).unwrap();
+		let unknown = report(&block_view);
+		assert!(unknown.approvals.is_empty());
+		assert!(matches!(unknown.signatures, Err(NearLiteClientError::InvalidLiteBlock(_))));
+		assert!(lite_client.validate(&block_view).is_err());
+	}
+
+	#[test]
+	fn test_outcome_proof() {
+		let id = |name: &str| CryptoHash::hash_bytes(name.as_bytes());
+		let outcome = |executor_id: &str| {
+			success_outcome(
+				executor_id,
+				vec![format!("log of {}", executor_id)],
+				vec![id("receipt")],
+			)
+		};
+		let mut chain = ChainSimulator::new(validators());
+		chain.produce_blocks(3);
+		let height = chain.produce_block_with_outcomes(vec![
+			vec![(id("tx1"), outcome("alice.near")), (id("tx2"), outcome("bob.near"))],
+			vec![],
+			vec![
+				(id("tx3"), outcome("carol.near")),
+				(id("tx4"), outcome("dave.near")),
+				(id("tx5"), outcome("erin.near")),
+			],
+		]);
+		chain.produce_blocks(2);
+		let outcome_root = chain.light_client_block(height).unwrap().inner_lite.outcome_root;
+
+		for name in ["tx1", "tx2", "tx3", "tx4", "tx5"] {
+			let proof = chain.outcome_proof(id(name)).unwrap();
+			assert_eq!(proof.block_height, height);
+			assert_eq!(proof.outcome_proof.block_hash, chain.block_hash(height).unwrap());
+			validate_transaction::<H>(&proof.outcome_proof, proof.outcome_root_proof, outcome_root)
+				.unwrap();
+		}
+		assert!(chain.outcome_proof(id("receipt")).is_none());
+
+		let mut proof = chain.outcome_proof(id("tx4")).unwrap();
+		proof.outcome_proof.outcome.logs.clear();
+		assert!(validate_transaction::<H>(
+			&proof.outcome_proof,
+			proof.outcome_root_proof,
+			outcome_root
+		)
+		.is_err());
+
+		let head = chain.light_client_block(height + 1).unwrap();
+		let block_proof = chain.block_proof(height, height + 1).unwrap();
+		assert_eq!(
+			merkle::root_from_path(&block_proof, chain.block_hash(height).unwrap()),
+			head.inner_lite.block_merkle_root
+		);
+		assert!(chain.block_proof(height, height).is_none());
+	}
+
+	#[test]
+	fn test_deterministic() {
+		let light_client_block = |chain: ChainSimulator| {
+			chain
+				.light_client_block(chain.last_block_of_epoch(3))
+				.unwrap()
+				.try_to_vec()
+				.unwrap()
+		};
+		assert_eq!(light_client_block(chain()), light_client_block(chain()));
+	}
+}
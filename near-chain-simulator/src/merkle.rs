@@ -0,0 +1,72 @@
+//! Merkle trees built the way NEAR builds them: a missing right sibling promotes its left sibling
+//! to the next level unchanged, and the leaves are given already hashed.
+
+use near_primitives_wasm::{CryptoHash, Direction, MerklePath, MerklePathItem};
+
+pub fn combine_hash(left: &CryptoHash, right: &CryptoHash) -> CryptoHash {
+	CryptoHash::hash_bytes(&[left.as_ref(), right.as_ref()].concat())
+}
+
+/// Root of the tree of `leaves` and the path of every leaf to it; the root of no leaves is the
+/// default hash.
+pub fn merklize(leaves: &[CryptoHash]) -> (CryptoHash, Vec<MerklePath>) {
+	let mut paths = vec![MerklePath::new(); leaves.len()];
+	if leaves.is_empty() {
+		return (CryptoHash::default(), paths);
+	}
+
+	let mut level = leaves.to_vec();
+	// index of every leaf in the current level
+	let mut indexes = (0..leaves.len()).collect::<Vec<_>>();
+	while level.len() > 1 {
+		for (path, index) in paths.iter_mut().zip(indexes.iter_mut()) {
+			let sibling = if *index % 2 == 0 {
+				level.get(*index + 1).map(|hash| (*hash, Direction::Right))
+			} else {
+				Some((level[*index - 1], Direction::Left))
+			};
+			if let Some((hash, direction)) = sibling {
+				path.push(MerklePathItem { hash, direction });
+			}
+			*index /= 2;
+		}
+		level = level
+			.chunks(2)
+			.map(|pair| match pair {
+				[left, right] => combine_hash(left, right),
+				[single] => *single,
+				_ => unreachable!(),
+			})
+			.collect();
+	}
+	(level[0], paths)
+}
+
+/// root of the tree of the leaf `item` with the path `path`
+pub fn root_from_path(path: &MerklePath, item: CryptoHash) -> CryptoHash {
+	path.iter().fold(item, |hash, item| match item.direction {
+		Direction::Left => combine_hash(&item.hash, &hash),
+		Direction::Right => combine_hash(&hash, &item.hash),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_merklize() {
+		let leaves = (0..7u8).map(|i| CryptoHash::hash_bytes(&[i])).collect::<Vec<_>>();
+		for len in 1..leaves.len() {
+			let (root, paths) = merklize(&leaves[..len]);
+			for (leaf, path) in leaves.iter().zip(&paths) {
+				assert_eq!(root_from_path(path, *leaf), root);
+			}
+		}
+
+		let (root, _) = merklize(&leaves[..3]);
+		assert_eq!(root, combine_hash(&combine_hash(&leaves[0], &leaves[1]), &leaves[2]));
+		assert_eq!(merklize(&leaves[..1]).0, leaves[0]);
+		assert_eq!(merklize(&[]).0, CryptoHash::default());
+	}
+}
@@ -0,0 +1,445 @@
+use borsh::BorshDeserialize;
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+use cosmwasm_std::{
+	to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Storage,
+};
+use near_lite_client::{
+	validate_contract_state, validate_head, CommitmentProofBytes, ShardStateRootProof,
+};
+use near_primitives_wasm::{HostFunctions, LightClientBlockView};
+
+use crate::{
+	error::ContractError,
+	host_functions::CosmWasmHostFunctions,
+	msg::{ExecuteMsg, InstantiateMsg, LatestHeightResponse, QueryMsg, Status, StatusResponse},
+	state::{
+		is_frozen, load_block_producers, load_head, load_header, save_block_producers, save_head,
+		Config, CONFIG, FROZEN_HEIGHT, LATEST_HEIGHT,
+	},
+};
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+	deps: DepsMut,
+	_env: Env,
+	_info: MessageInfo,
+	msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+	let checkpoint = LightClientBlockView::try_from_slice(msg.checkpoint.as_slice())?;
+	let next_bps = checkpoint.next_bps.as_ref().ok_or(ContractError::MissingNextBlockProducers)?;
+
+	save_block_producers(deps.storage, &checkpoint.inner_lite.next_epoch_id, next_bps)?;
+	save_head(deps.storage, &checkpoint)?;
+	CONFIG.save(
+		deps.storage,
+		&Config { ibc_account_id: msg.ibc_account_id, ibc_storage_prefix: msg.ibc_storage_prefix },
+	)?;
+
+	Ok(Response::new()
+		.add_attribute("action", "instantiate")
+		.add_attribute("height", checkpoint.inner_lite.height.to_string()))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+	deps: DepsMut,
+	_env: Env,
+	_info: MessageInfo,
+	msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+	match msg {
+		ExecuteMsg::UpdateClient { header } =>
+			execute_update_client::<CosmWasmHostFunctions>(deps, &header),
+		ExecuteMsg::VerifyMembership { height, path, shard_state_root_proof, proof, value } =>
+			execute_verify_membership::<CosmWasmHostFunctions>(
+				deps.as_ref(),
+				height,
+				&path,
+				&shard_state_root_proof,
+				proof,
+				value,
+			),
+		ExecuteMsg::CheckMisbehaviour { trusted_height, header_1, header_2 } =>
+			execute_check_misbehaviour::<CosmWasmHostFunctions>(
+				deps,
+				trusted_height,
+				&header_1,
+				&header_2,
+			),
+	}
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+	match msg {
+		QueryMsg::Status {} => {
+			let status = if is_frozen(deps.storage)? { Status::Frozen } else { Status::Active };
+			to_binary(&StatusResponse { status })
+		},
+		QueryMsg::LatestHeight {} =>
+			to_binary(&LatestHeightResponse { height: LATEST_HEIGHT.load(deps.storage)? }),
+	}
+}
+
+pub fn execute_update_client<H: HostFunctions>(
+	deps: DepsMut,
+	header: &Binary,
+) -> Result<Response, ContractError> {
+	if is_frozen(deps.storage)? {
+		return Err(ContractError::ClientFrozen);
+	}
+	let head = load_head(deps.storage)?;
+	let header = LightClientBlockView::try_from_slice(header.as_slice())?;
+	validate_header::<H>(deps.storage, &head, &header)?;
+
+	if let Some(next_bps) = &header.next_bps {
+		save_block_producers(deps.storage, &header.inner_lite.next_epoch_id, next_bps)?;
+	}
+	save_head(deps.storage, &header)?;
+
+	Ok(Response::new()
+		.add_attribute("action", "update_client")
+		.add_attribute("height", header.inner_lite.height.to_string()))
+}
+
+pub fn execute_verify_membership<H: HostFunctions>(
+	deps: Deps,
+	height: u64,
+	path: &str,
+	shard_state_root_proof: &Binary,
+	proof: Binary,
+	value: Option<Binary>,
+) -> Result<Response, ContractError> {
+	if is_frozen(deps.storage)? {
+		return Err(ContractError::ClientFrozen);
+	}
+	let header = load_header(deps.storage, height)?;
+	let shard_state_root_proof =
+		ShardStateRootProof::try_from_slice(shard_state_root_proof.as_slice())?;
+	let storage = CONFIG.load(deps.storage)?.ibc_storage();
+
+	validate_contract_state::<H>(
+		&header,
+		&storage,
+		path.as_bytes(),
+		&shard_state_root_proof,
+		&CommitmentProofBytes(proof.0),
+		value.map(|value| value.0),
+	)?;
+
+	Ok(Response::new().add_attribute("action", "verify_membership"))
+}
+
+pub fn execute_check_misbehaviour<H: HostFunctions>(
+	deps: DepsMut,
+	trusted_height: u64,
+	header_1: &Binary,
+	header_2: &Binary,
+) -> Result<Response, ContractError> {
+	if is_frozen(deps.storage)? {
+		return Err(ContractError::ClientFrozen);
+	}
+	let trusted_header = load_header(deps.storage, trusted_height)?;
+	let header_1 = LightClientBlockView::try_from_slice(header_1.as_slice())?;
+	let header_2 = LightClientBlockView::try_from_slice(header_2.as_slice())?;
+	validate_header::<H>(deps.storage, &trusted_header, &header_1)?;
+	validate_header::<H>(deps.storage, &trusted_header, &header_2)?;
+
+	let height = header_1.inner_lite.height;
+	if height != header_2.inner_lite.height {
+		return Err(ContractError::NoMisbehaviour("headers have different heights".into()));
+	}
+	if header_1.current_block_hash::<H>() == header_2.current_block_hash::<H>() {
+		return Err(ContractError::NoMisbehaviour("headers are the same block".into()));
+	}
+	FROZEN_HEIGHT.save(deps.storage, &height)?;
+
+	Ok(Response::new()
+		.add_attribute("action", "check_misbehaviour")
+		.add_attribute("frozen_height", height.to_string()))
+}
+
+/// Validates `header` against `trusted_header` with the block producers stored for its epoch
+fn validate_header<H: HostFunctions>(
+	storage: &dyn Storage,
+	trusted_header: &LightClientBlockView,
+	header: &LightClientBlockView,
+) -> Result<(), ContractError> {
+	let epoch_id = header.inner_lite.epoch_id;
+	let block_producers =
+		load_block_producers(storage, &epoch_id)?.ok_or(ContractError::UnknownEpoch(epoch_id))?;
+	validate_head::<H>(
+		trusted_header,
+		header,
+		&[(epoch_id, block_producers)].into_iter().collect(),
+	)?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use borsh::BorshSerialize;
+	use cosmwasm_std::{
+		from_binary,
+		testing::{mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage},
+		OwnedDeps,
+	};
+	use near_lite_client::test_utils::single_leaf_trie;
+	use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+	use near_primitives_wasm::CryptoHash;
+
+	use super::*;
+
+	const CHECKPOINT: &str = include_str!("../../test-fixtures/light_client_block_86441383.json");
+	const HEADER: &str = include_str!("../../test-fixtures/light_client_block_86455909.json");
+	const NEXT_HEADER: &str = include_str!("../../test-fixtures/light_client_block_86456070.json");
+
+	/// Skips the signature verification, so that headers can be tampered with
+	struct SkipSignatureVerification;
+	impl HostFunctions for SkipSignatureVerification {
+		fn sha256(data: &[u8]) -> [u8; 32] {
+			CosmWasmHostFunctions::sha256(data)
+		}
+
+		fn verify(
+			_signature: near_primitives_wasm::Signature,
+			_data: impl AsRef<[u8]>,
+			_public_key: near_primitives_wasm::PublicKey,
+		) -> bool {
+			true
+		}
+	}
+
+	#[derive(serde::Deserialize)]
+	struct ResultFromRpc {
+		result: NearLightClientBlockView,
+	}
+
+	fn block_view(rpc_response: &str) -> LightClientBlockView {
+		let block_view = serde_json::from_str::<ResultFromRpc>(rpc_response).unwrap().result;
+		LightClientBlockView::from(block_view)
+	}
+
+	fn encode(block_view: &LightClientBlockView) -> Binary {
+		Binary(block_view.try_to_vec().unwrap())
+	}
+
+	fn setup() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+		let mut deps = mock_dependencies();
+		let msg = InstantiateMsg {
+			checkpoint: encode(&block_view(CHECKPOINT)),
+			ibc_account_id: "ibc.near".into(),
+			ibc_storage_prefix: Binary(b"ibc/".to_vec()),
+		};
+		instantiate(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg).unwrap();
+		deps
+	}
+
+	fn status(deps: Deps) -> Status {
+		from_binary::<StatusResponse>(&query(deps, mock_env(), QueryMsg::Status {}).unwrap())
+			.unwrap()
+			.status
+	}
+
+	fn latest_height(deps: Deps) -> u64 {
+		from_binary::<LatestHeightResponse>(
+			&query(deps, mock_env(), QueryMsg::LatestHeight {}).unwrap(),
+		)
+		.unwrap()
+		.height
+	}
+
+	fn update_client(
+		deps: DepsMut,
+		header: &LightClientBlockView,
+	) -> Result<Response, ContractError> {
+		execute(
+			deps,
+			mock_env(),
+			mock_info("relayer", &[]),
+			ExecuteMsg::UpdateClient { header: encode(header) },
+		)
+	}
+
+	#[test]
+	fn test_instantiate() {
+		let deps = setup();
+		assert_eq!(status(deps.as_ref()), Status::Active);
+		assert_eq!(latest_height(deps.as_ref()), 86441383);
+
+		let mut checkpoint = block_view(CHECKPOINT);
+		checkpoint.next_bps = None;
+		let msg = InstantiateMsg {
+			checkpoint: encode(&checkpoint),
+			ibc_account_id: "ibc.near".into(),
+			ibc_storage_prefix: Binary::default(),
+		};
+		assert!(matches!(
+			instantiate(mock_dependencies().as_mut(), mock_env(), mock_info("relayer", &[]), msg),
+			Err(ContractError::MissingNextBlockProducers)
+		));
+	}
+
+	#[test]
+	fn test_update_client() {
+		let mut deps = setup();
+
+		update_client(deps.as_mut(), &block_view(HEADER)).unwrap();
+		assert_eq!(latest_height(deps.as_ref()), 86455909);
+		update_client(deps.as_mut(), &block_view(NEXT_HEADER)).unwrap();
+		assert_eq!(latest_height(deps.as_ref()), 86456070);
+
+		// the client can't go backwards
+		assert!(update_client(deps.as_mut(), &block_view(HEADER)).is_err());
+		assert_eq!(latest_height(deps.as_ref()), 86456070);
+	}
+
+	#[test]
+	fn test_update_client_tampered_header() {
+		let mut deps = setup();
+		let mut header = block_view(HEADER);
+		header.inner_lite.prev_state_root = CryptoHash([1; 32]);
+
+		// approvals are over the hash of the block, which no longer matches
+		assert!(matches!(update_client(deps.as_mut(), &header), Err(ContractError::LiteClient(_))));
+		assert_eq!(latest_height(deps.as_ref()), 86441383);
+	}
+
+	#[test]
+	fn test_check_misbehaviour() {
+		let mut deps = setup();
+		let header = block_view(HEADER);
+		let mut forged_header = header.clone();
+		forged_header.inner_rest_hash = CryptoHash([1; 32]);
+
+		// same block twice is not a misbehaviour
+		assert!(matches!(
+			execute(
+				deps.as_mut(),
+				mock_env(),
+				mock_info("relayer", &[]),
+				ExecuteMsg::CheckMisbehaviour {
+					trusted_height: 86441383,
+					header_1: encode(&header),
+					header_2: encode(&header),
+				},
+			),
+			Err(ContractError::NoMisbehaviour(_))
+		));
+		// the forged header is not signed by the block producers
+		assert!(matches!(
+			execute(
+				deps.as_mut(),
+				mock_env(),
+				mock_info("relayer", &[]),
+				ExecuteMsg::CheckMisbehaviour {
+					trusted_height: 86441383,
+					header_1: encode(&header),
+					header_2: encode(&forged_header),
+				},
+			),
+			Err(ContractError::LiteClient(_))
+		));
+		assert_eq!(status(deps.as_ref()), Status::Active);
+
+		// both headers signed: conflicting blocks at the same height
+		execute_check_misbehaviour::<SkipSignatureVerification>(
+			deps.as_mut(),
+			86441383,
+			&encode(&header),
+			&encode(&forged_header),
+		)
+		.unwrap();
+		assert_eq!(status(deps.as_ref()), Status::Frozen);
+		assert!(matches!(
+			update_client(deps.as_mut(), &block_view(HEADER)),
+			Err(ContractError::ClientFrozen)
+		));
+	}
+
+	#[test]
+	fn test_verify_membership() {
+		let path = "commitments/ports/transfer/channels/channel-0/sequences/1";
+		let value = b"packet commitment".to_vec();
+
+		// single leaf trie holding `value` under the trie key of `path`
+		let trie_key = [&b"\x09ibc.near,ibc/"[..], path.as_bytes()].concat();
+		let (state_root, leaf) = single_leaf_trie(&trie_key, &value);
+		let proof = Binary(vec![leaf].try_to_vec().unwrap());
+		let shard_state_root_proof =
+			Binary(ShardStateRootProof { state_root, path: vec![] }.try_to_vec().unwrap());
+
+		let mut deps = setup();
+		let mut header = block_view(HEADER);
+		header.inner_lite.prev_state_root =
+			CryptoHash(CosmWasmHostFunctions::sha256(&state_root.0));
+		execute_update_client::<SkipSignatureVerification>(deps.as_mut(), &encode(&header))
+			.unwrap();
+
+		let verify_membership = |deps: DepsMut, height, path: &str, value: Option<Vec<u8>>| {
+			execute(
+				deps,
+				mock_env(),
+				mock_info("relayer", &[]),
+				ExecuteMsg::VerifyMembership {
+					height,
+					path: path.into(),
+					shard_state_root_proof: shard_state_root_proof.clone(),
+					proof: proof.clone(),
+					value: value.map(Binary),
+				},
+			)
+		};
+
+		assert!(verify_membership(deps.as_mut(), 86455909, path, Some(value.clone())).is_ok());
+		assert!(verify_membership(deps.as_mut(), 86455909, path, Some(b"other".to_vec())).is_err());
+		assert!(verify_membership(deps.as_mut(), 86455909, path, None).is_err());
+		assert!(verify_membership(
+			deps.as_mut(),
+			86455909,
+			"commitments/ports/transfer/channels/channel-0/sequences/2",
+			None
+		)
+		.is_ok());
+		// the state root belongs to another header
+		assert!(matches!(
+			verify_membership(deps.as_mut(), 86441383, path, Some(value.clone())),
+			Err(ContractError::LiteClient(_))
+		));
+		assert!(matches!(
+			verify_membership(deps.as_mut(), 1, path, Some(value)),
+			Err(ContractError::UnknownHeight(1))
+		));
+	}
+
+	#[test]
+	fn test_secp256k1_signatures() {
+		use near_primitives_wasm::{PublicKey, Signature};
+
+		let secret_key = libsecp256k1::SecretKey::parse(&[7; 32]).unwrap();
+		let public_key = PublicKey::Secp256k1(
+			libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize()[1..]
+				.try_into()
+				.unwrap(),
+		);
+		let message = CryptoHash::hash_bytes(b"message");
+		let (signature, recovery_id) =
+			libsecp256k1::sign(&libsecp256k1::Message::parse(&message.0), &secret_key);
+		let signature = Signature::Secp256k1(
+			[&signature.serialize()[..], &[recovery_id.serialize()]]
+				.concat()
+				.try_into()
+				.unwrap(),
+		);
+
+		// the same as with the host functions of substrate
+		assert!(signature.verify(message, public_key.clone()));
+		assert!(CosmWasmHostFunctions::verify(signature.clone(), message, public_key.clone()));
+		assert!(!CosmWasmHostFunctions::verify(
+			signature,
+			CryptoHash::hash_bytes(b"another message"),
+			public_key
+		));
+	}
+}
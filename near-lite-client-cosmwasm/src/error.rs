@@ -0,0 +1,55 @@
+use std::fmt;
+
+use borsh::maybestd::io::Error as BorshError;
+use cosmwasm_std::StdError;
+use near_lite_client::NearLiteClientError;
+use near_primitives_wasm::CryptoHash;
+
+#[derive(Debug)]
+pub enum ContractError {
+	Std(StdError),
+	Borsh(BorshError),
+	LiteClient(NearLiteClientError),
+	ClientFrozen,
+	MissingNextBlockProducers,
+	UnknownEpoch(CryptoHash),
+	UnknownHeight(u64),
+	NoMisbehaviour(String),
+}
+
+impl fmt::Display for ContractError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Std(err) => write!(f, "{}", err),
+			Self::Borsh(err) => write!(f, "borsh decoding error: {}", err),
+			Self::LiteClient(err) => write!(f, "lite client error: {:?}", err),
+			Self::ClientFrozen => write!(f, "client is frozen"),
+			Self::MissingNextBlockProducers => {
+				write!(f, "checkpoint does not contain the next block producers")
+			},
+			Self::UnknownEpoch(epoch_id) => {
+				write!(f, "block producers of epoch {:?} are unknown", epoch_id)
+			},
+			Self::UnknownHeight(height) => write!(f, "no header stored at height {}", height),
+			Self::NoMisbehaviour(reason) => write!(f, "no misbehaviour: {}", reason),
+		}
+	}
+}
+
+impl From<StdError> for ContractError {
+	fn from(err: StdError) -> Self {
+		Self::Std(err)
+	}
+}
+
+impl From<BorshError> for ContractError {
+	fn from(err: BorshError) -> Self {
+		Self::Borsh(err)
+	}
+}
+
+impl From<NearLiteClientError> for ContractError {
+	fn from(err: NearLiteClientError) -> Self {
+		Self::LiteClient(err)
+	}
+}
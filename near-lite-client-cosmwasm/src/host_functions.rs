@@ -0,0 +1,28 @@
+use near_primitives_wasm::HostFunctions;
+
+/// Host functions of the contract. Substrate's host functions are not available in a CosmWasm
+/// contract, and the ones of CosmWasm need `Deps`, so everything is computed inside the
+/// contract.
+pub struct CosmWasmHostFunctions;
+
+impl HostFunctions for CosmWasmHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		use sha2::Digest;
+		sha2::Sha256::digest(data).into()
+	}
+
+	fn ed25519_verify(signature: &[u8; 64], data: &[u8], public_key: &[u8; 32]) -> bool {
+		let signature = ed25519_zebra::Signature::from(*signature);
+		ed25519_zebra::VerificationKey::try_from(*public_key)
+			.and_then(|verification_key| verification_key.verify(&signature, data))
+			.is_ok()
+	}
+
+	fn secp256k1_ecdsa_recover(signature: &[u8; 65], message: &[u8; 32]) -> Option<[u8; 64]> {
+		let recovery_id = libsecp256k1::RecoveryId::parse(signature[64]).ok()?;
+		let signature = libsecp256k1::Signature::parse_standard_slice(&signature[..64]).ok()?;
+		let message = libsecp256k1::Message::parse(message);
+		let public_key = libsecp256k1::recover(&message, &signature, &recovery_id).ok()?;
+		public_key.serialize()[1..].try_into().ok()
+	}
+}
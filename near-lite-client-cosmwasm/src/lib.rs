@@ -0,0 +1,20 @@
+//! # Near lite client CosmWasm contract
+//!
+//! Runs the NEAR lite client as a CosmWasm contract, in the style of the light clients of
+//! ibc-go's `08-wasm` module:
+//! - `instantiate` takes the trusted checkpoint the client starts from
+//! - `execute` updates the client (`UpdateClient`), verifies the state of the IBC contract on NEAR
+//!   (`VerifyMembership`) and freezes the client when it is given conflicting headers
+//!   (`CheckMisbehaviour`)
+//! - `query` returns the `Status` and the `LatestHeight` of the client
+//!
+//! Headers are validated with `near_lite_client::validate_head`, the block producers of every
+//! epoch the client learns about are kept in the contract's storage.
+
+pub mod contract;
+mod error;
+pub mod host_functions;
+pub mod msg;
+pub mod state;
+
+pub use error::ContractError;
@@ -0,0 +1,63 @@
+//! Messages of the contract. NEAR types are passed around as borsh encoded `Binary`s, the same
+//! encoding the relayer uses when submitting them to the Substrate pallet.
+
+use cosmwasm_std::Binary;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+	/// borsh encoded `LightClientBlockView` of the trusted checkpoint, it must carry the block
+	/// producers of the next epoch
+	pub checkpoint: Binary,
+	/// account of the IBC contract whose storage is proven by `VerifyMembership`
+	pub ibc_account_id: String,
+	/// prefix the IBC contract prepends to every ICS-24 path
+	pub ibc_storage_prefix: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+	/// Moves the head of the client to `header`, a borsh encoded `LightClientBlockView`
+	UpdateClient { header: Binary },
+	/// Verifies that `value` is stored under `path` in the IBC contract storage at `height`.
+	/// When `value` is `None` the absence of `path` is verified instead.
+	VerifyMembership {
+		height: u64,
+		/// ICS-24 path, e.g. `commitments/ports/transfer/channels/channel-0/sequences/1`
+		path: String,
+		/// borsh encoded `ShardStateRootProof`
+		shard_state_root_proof: Binary,
+		/// borsh encoded list of the trie nodes returned by the `view_state` RPC query
+		proof: Binary,
+		value: Option<Binary>,
+	},
+	/// Freezes the client if both headers are valid, have the same height but differ. The
+	/// headers are validated against the header stored at `trusted_height`.
+	CheckMisbehaviour { trusted_height: u64, header_1: Binary, header_2: Binary },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+	Status {},
+	LatestHeight {},
+}
+
+/// Client statuses, named after the ones of ibc-go's `exported.Status`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+pub enum Status {
+	Active,
+	Frozen,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatusResponse {
+	pub status: Status,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LatestHeightResponse {
+	pub height: u64,
+}
@@ -0,0 +1,84 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use cosmwasm_std::{Binary, StdResult, Storage};
+use cw_storage_plus::{Item, Map};
+use near_lite_client::IbcContractStorage;
+use near_primitives_wasm::{CryptoHash, LightClientBlockView, ValidatorStakeView};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+	pub ibc_account_id: String,
+	pub ibc_storage_prefix: Binary,
+}
+
+impl Config {
+	pub fn ibc_storage(&self) -> IbcContractStorage {
+		IbcContractStorage {
+			account_id: self.ibc_account_id.clone(),
+			storage_prefix: self.ibc_storage_prefix.to_vec(),
+		}
+	}
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+pub const LATEST_HEIGHT: Item<u64> = Item::new("latest_height");
+/// height of the first conflicting headers, set once the client is frozen
+pub const FROZEN_HEIGHT: Item<u64> = Item::new("frozen_height");
+/// borsh encoded `LightClientBlockView`s the client has been updated to, by height
+const HEADERS: Map<u64, Binary> = Map::new("headers");
+/// borsh encoded block producers, by epoch id
+const EPOCH_BLOCK_PRODUCERS: Map<&[u8], Binary> = Map::new("epoch_block_producers");
+
+pub fn is_frozen(storage: &dyn Storage) -> StdResult<bool> {
+	Ok(FROZEN_HEIGHT.may_load(storage)?.is_some())
+}
+
+pub fn load_header(
+	storage: &dyn Storage,
+	height: u64,
+) -> Result<LightClientBlockView, ContractError> {
+	let header = HEADERS.may_load(storage, height)?.ok_or(ContractError::UnknownHeight(height))?;
+	Ok(LightClientBlockView::try_from_slice(header.as_slice())?)
+}
+
+pub fn load_head(storage: &dyn Storage) -> Result<LightClientBlockView, ContractError> {
+	load_header(storage, LATEST_HEIGHT.load(storage)?)
+}
+
+/// Stores `header` and makes it the head of the client
+pub fn save_head(
+	storage: &mut dyn Storage,
+	header: &LightClientBlockView,
+) -> Result<(), ContractError> {
+	let height = header.inner_lite.height;
+	HEADERS.save(storage, height, &Binary(header.try_to_vec()?))?;
+	LATEST_HEIGHT.save(storage, &height)?;
+	Ok(())
+}
+
+pub fn load_block_producers(
+	storage: &dyn Storage,
+	epoch_id: &CryptoHash,
+) -> Result<Option<Vec<ValidatorStakeView>>, ContractError> {
+	EPOCH_BLOCK_PRODUCERS
+		.may_load(storage, epoch_id.as_ref())?
+		.map(|block_producers| Vec::try_from_slice(block_producers.as_slice()))
+		.transpose()
+		.map_err(Into::into)
+}
+
+pub fn save_block_producers(
+	storage: &mut dyn Storage,
+	epoch_id: &CryptoHash,
+	block_producers: &[ValidatorStakeView],
+) -> Result<(), ContractError> {
+	EPOCH_BLOCK_PRODUCERS.save(
+		storage,
+		epoch_id.as_ref(),
+		&Binary(block_producers.try_to_vec()?),
+	)?;
+	Ok(())
+}
@@ -0,0 +1,16 @@
+use std::{env, path::Path};
+
+/// Generates the C header into `OUT_DIR`. The checked-in `include/near_lite_client.h` is only
+/// rewritten when `NEAR_LC_REGENERATE_HEADER` is set, so that builds don't write to the sources.
+fn main() {
+	println!("cargo:rerun-if-changed=src");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+	println!("cargo:rerun-if-env-changed=NEAR_LC_REGENERATE_HEADER");
+
+	let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+	let bindings = cbindgen::generate(&crate_dir).expect("unable to generate the C header");
+	bindings.write_to_file(Path::new(&env::var("OUT_DIR").unwrap()).join("near_lite_client.h"));
+	if env::var_os("NEAR_LC_REGENERATE_HEADER").is_some() {
+		bindings.write_to_file(Path::new(&crate_dir).join("include/near_lite_client.h"));
+	}
+}
@@ -0,0 +1,65 @@
+#ifndef NEAR_LITE_CLIENT_H
+#define NEAR_LITE_CLIENT_H
+
+/* Generated by cbindgen from near-lite-client-ffi, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of the validation functions
+typedef enum NearLcStatus {
+  // the header or the transaction is valid
+  NEAR_LC_STATUS_OK = 0,
+  // one of the buffers is a null pointer
+  NEAR_LC_STATUS_NULL_POINTER = 1,
+  // one of the buffers is not the borsh encoding of the expected type
+  NEAR_LC_STATUS_INVALID_ENCODING = 2,
+  // the header doesn't follow the head (height, epoch, stake or next block producers), or the
+  // block producers of its epoch are unknown
+  NEAR_LC_STATUS_INVALID_LITE_BLOCK = 3,
+  // an approval of the header is not signed by its block producer
+  NEAR_LC_STATUS_SIGNATURE_VERIFICATION = 4,
+  // the outcome of the transaction is not part of the expected block outcome root
+  NEAR_LC_STATUS_TRANSACTION_VALIDATION = 5,
+  // a merkle proof is malformed
+  NEAR_LC_STATUS_PROOF_VERIFICATION = 6,
+  // unexpected failure of the verifier, e.g. a value that doesn't encode or convert
+  NEAR_LC_STATUS_INTERNAL = 7,
+} NearLcStatus;
+
+// Validates `block_view` against the current `head` of the client.
+//
+// - `head` and `block_view`: borsh encoded `LightClientBlockView`s
+// - `epoch_block_producers`: borsh encoded `BTreeMap<CryptoHash, Vec<ValidatorStakeView>>`, the
+//   block producers of the epochs known by the client
+//
+// # Safety
+//
+// Every pointer must either be null or point to a buffer of at least the given length.
+enum NearLcStatus near_lc_validate_head(const uint8_t *head,
+                                        size_t head_len,
+                                        const uint8_t *block_view,
+                                        size_t block_view_len,
+                                        const uint8_t *epoch_block_producers,
+                                        size_t epoch_block_producers_len);
+
+// Validates that the outcome of a transaction is part of a block.
+//
+// - `outcome_proof`: borsh encoded `OutcomeProof`
+// - `outcome_root_proof`: borsh encoded `MerklePath` of the shard outcome root
+// - `expected_block_outcome_root`: the 32 bytes of the `outcome_root` of a validated block
+//
+// # Safety
+//
+// Every pointer must either be null or point to a buffer of at least the given length.
+enum NearLcStatus near_lc_validate_transaction(const uint8_t *outcome_proof,
+                                               size_t outcome_proof_len,
+                                               const uint8_t *outcome_root_proof,
+                                               size_t outcome_root_proof_len,
+                                               const uint8_t *expected_block_outcome_root,
+                                               size_t expected_block_outcome_root_len);
+
+#endif /* NEAR_LITE_CLIENT_H */
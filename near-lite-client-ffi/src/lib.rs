@@ -0,0 +1,336 @@
+//! # Near lite client C ABI
+//!
+//! Exposes the header and transaction validation of `near-lite-client` to other languages, so
+//! that they can link the same verifier instead of reimplementing the hashing and the
+//! validation rules. The header `include/near_lite_client.h` is generated by cbindgen; rebuild
+//! with `NEAR_LC_REGENERATE_HEADER=1` to update it after changing the exported API.
+//!
+//! Every argument is a borsh encoded buffer, passed as a pointer and a length, and every
+//! function returns a `NearLcStatus`. The library never takes ownership of the buffers.
+
+use std::{
+	collections::BTreeMap,
+	panic::{self, AssertUnwindSafe},
+	slice,
+};
+
+use borsh::BorshDeserialize;
+use near_lite_client::{
+	validate_head, validate_transaction, CryptoHash, LightClientBlockView, MerklePath,
+	NearLiteClientError, OutcomeProof, ValidatorStakeView,
+};
+use near_primitives_wasm::HostFunctions;
+
+/// Result of the validation functions
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NearLcStatus {
+	/// the header or the transaction is valid
+	Ok = 0,
+	/// one of the buffers is a null pointer
+	NullPointer = 1,
+	/// one of the buffers is not the borsh encoding of the expected type
+	InvalidEncoding = 2,
+	/// the header doesn't follow the head (height, epoch, stake or next block producers), or the
+	/// block producers of its epoch are unknown
+	InvalidLiteBlock = 3,
+	/// an approval of the header is not signed by its block producer
+	SignatureVerification = 4,
+	/// the outcome of the transaction is not part of the expected block outcome root
+	TransactionValidation = 5,
+	/// a merkle proof is malformed
+	ProofVerification = 6,
+	/// unexpected failure of the verifier, e.g. a value that doesn't encode or convert
+	Internal = 7,
+}
+
+impl From<NearLiteClientError> for NearLcStatus {
+	fn from(err: NearLiteClientError) -> Self {
+		match err {
+			NearLiteClientError::InvalidLiteBlock(_) => Self::InvalidLiteBlock,
+			NearLiteClientError::SignatureVerification(_) => Self::SignatureVerification,
+			NearLiteClientError::TransactionValidation(_) => Self::TransactionValidation,
+			NearLiteClientError::ProofVerificationError(_) | NearLiteClientError::Commitment(_) =>
+				Self::ProofVerification,
+			NearLiteClientError::Borsh(_) | NearLiteClientError::Conversion(_) => Self::Internal,
+		}
+	}
+}
+
+struct NativeHostFunctions;
+
+impl HostFunctions for NativeHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		use sha2::Digest;
+		sha2::Sha256::digest(data).into()
+	}
+}
+
+/// Validates `block_view` against the current `head` of the client.
+///
+/// - `head` and `block_view`: borsh encoded `LightClientBlockView`s
+/// - `epoch_block_producers`: borsh encoded `BTreeMap<CryptoHash, Vec<ValidatorStakeView>>`, the
+///   block producers of the epochs known by the client
+///
+/// # Safety
+///
+/// Every pointer must either be null or point to a buffer of at least the given length.
+#[no_mangle]
+pub unsafe extern "C" fn near_lc_validate_head(
+	head: *const u8,
+	head_len: usize,
+	block_view: *const u8,
+	block_view_len: usize,
+	epoch_block_producers: *const u8,
+	epoch_block_producers_len: usize,
+) -> NearLcStatus {
+	call(|| {
+		let head = decode::<LightClientBlockView>(head, head_len)?;
+		let block_view = decode::<LightClientBlockView>(block_view, block_view_len)?;
+		let epoch_block_producers = decode::<BTreeMap<CryptoHash, Vec<ValidatorStakeView>>>(
+			epoch_block_producers,
+			epoch_block_producers_len,
+		)?;
+		validate_head::<NativeHostFunctions>(&head, &block_view, &epoch_block_producers)?;
+		Ok(())
+	})
+}
+
+/// Validates that the outcome of a transaction is part of a block.
+///
+/// - `outcome_proof`: borsh encoded `OutcomeProof`
+/// - `outcome_root_proof`: borsh encoded `MerklePath` of the shard outcome root
+/// - `expected_block_outcome_root`: the 32 bytes of the `outcome_root` of a validated block
+///
+/// # Safety
+///
+/// Every pointer must either be null or point to a buffer of at least the given length.
+#[no_mangle]
+pub unsafe extern "C" fn near_lc_validate_transaction(
+	outcome_proof: *const u8,
+	outcome_proof_len: usize,
+	outcome_root_proof: *const u8,
+	outcome_root_proof_len: usize,
+	expected_block_outcome_root: *const u8,
+	expected_block_outcome_root_len: usize,
+) -> NearLcStatus {
+	call(|| {
+		let outcome_proof = decode::<OutcomeProof>(outcome_proof, outcome_proof_len)?;
+		let outcome_root_proof = decode::<MerklePath>(outcome_root_proof, outcome_root_proof_len)?;
+		let expected_block_outcome_root =
+			decode::<CryptoHash>(expected_block_outcome_root, expected_block_outcome_root_len)?;
+		validate_transaction::<NativeHostFunctions>(
+			&outcome_proof,
+			outcome_root_proof,
+			expected_block_outcome_root,
+		)?;
+		Ok(())
+	})
+}
+
+/// Runs `f`, making sure that no panic unwinds into the caller
+fn call(f: impl FnOnce() -> Result<(), NearLcStatus>) -> NearLcStatus {
+	match panic::catch_unwind(AssertUnwindSafe(f)) {
+		Ok(Ok(())) => NearLcStatus::Ok,
+		Ok(Err(status)) => status,
+		Err(_) => NearLcStatus::Internal,
+	}
+}
+
+unsafe fn decode<T: BorshDeserialize>(data: *const u8, len: usize) -> Result<T, NearLcStatus> {
+	if data.is_null() {
+		return Err(NearLcStatus::NullPointer);
+	}
+	T::try_from_slice(slice::from_raw_parts(data, len)).map_err(|_| NearLcStatus::InvalidEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+	use borsh::BorshSerialize;
+	use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+	use near_primitives_wasm::{Direction, MerklePathItem};
+
+	use super::*;
+
+	const CHECKPOINT: &str = include_str!("../../test-fixtures/light_client_block_86441383.json");
+	const HEADER: &str = include_str!("../../test-fixtures/light_client_block_86455909.json");
+
+	#[derive(serde::Deserialize)]
+	struct ResultFromRpc {
+		result: NearLightClientBlockView,
+	}
+
+	fn block_view(rpc_response: &str) -> LightClientBlockView {
+		let block_view = serde_json::from_str::<ResultFromRpc>(rpc_response).unwrap().result;
+		LightClientBlockView::from(block_view)
+	}
+
+	fn hash(encoded: &str) -> CryptoHash {
+		CryptoHash::try_from(bs58::decode(encoded).into_vec().unwrap().as_ref()).unwrap()
+	}
+
+	fn validate_head_ffi(
+		head: &[u8],
+		block_view: &[u8],
+		epoch_block_producers: &[u8],
+	) -> NearLcStatus {
+		unsafe {
+			near_lc_validate_head(
+				head.as_ptr(),
+				head.len(),
+				block_view.as_ptr(),
+				block_view.len(),
+				epoch_block_producers.as_ptr(),
+				epoch_block_producers.len(),
+			)
+		}
+	}
+
+	fn validate_transaction_ffi(
+		outcome_proof: &[u8],
+		outcome_root_proof: &[u8],
+		expected_block_outcome_root: &[u8],
+	) -> NearLcStatus {
+		unsafe {
+			near_lc_validate_transaction(
+				outcome_proof.as_ptr(),
+				outcome_proof.len(),
+				outcome_root_proof.as_ptr(),
+				outcome_root_proof.len(),
+				expected_block_outcome_root.as_ptr(),
+				expected_block_outcome_root.len(),
+			)
+		}
+	}
+
+	#[test]
+	fn test_validate_head() {
+		let checkpoint = block_view(CHECKPOINT);
+		let header = block_view(HEADER);
+		let epoch_block_producers: BTreeMap<_, _> =
+			[(checkpoint.inner_lite.next_epoch_id, checkpoint.next_bps.clone().unwrap())]
+				.into_iter()
+				.collect();
+		let head = checkpoint.try_to_vec().unwrap();
+		let epoch_block_producers = epoch_block_producers.try_to_vec().unwrap();
+
+		assert_eq!(
+			validate_head_ffi(&head, &header.try_to_vec().unwrap(), &epoch_block_producers),
+			NearLcStatus::Ok
+		);
+		// going backwards
+		assert_eq!(
+			validate_head_ffi(&header.try_to_vec().unwrap(), &head, &epoch_block_producers),
+			NearLcStatus::InvalidLiteBlock
+		);
+
+		let mut tampered_header = header.clone();
+		tampered_header.inner_lite.prev_state_root = CryptoHash([1; 32]);
+		assert_eq!(
+			validate_head_ffi(
+				&head,
+				&tampered_header.try_to_vec().unwrap(),
+				&epoch_block_producers
+			),
+			NearLcStatus::SignatureVerification
+		);
+
+		// the block producers of the header's epoch are unknown
+		assert_eq!(
+			validate_head_ffi(
+				&head,
+				&header.try_to_vec().unwrap(),
+				&BTreeMap::<CryptoHash, Vec<ValidatorStakeView>>::new().try_to_vec().unwrap()
+			),
+			NearLcStatus::InvalidLiteBlock
+		);
+
+		assert_eq!(
+			validate_head_ffi(&head, &[1, 2, 3], &epoch_block_producers),
+			NearLcStatus::InvalidEncoding
+		);
+		assert_eq!(
+			unsafe {
+				near_lc_validate_head(
+					head.as_ptr(),
+					head.len(),
+					std::ptr::null(),
+					0,
+					epoch_block_producers.as_ptr(),
+					epoch_block_producers.len(),
+				)
+			},
+			NearLcStatus::NullPointer
+		);
+	}
+
+	#[test]
+	fn test_validate_transaction() {
+		let outcome_proof = (
+			// proof
+			vec![
+				MerklePathItem {
+					hash: hash("B1Kx1mFhCpjkhon9iYJ5BMdmBT8drgesumGZoohWhAkL"),
+					direction: Direction::Right,
+				},
+				MerklePathItem {
+					hash: hash("3tTqGEkN2QHr1HQdctpdCoJ6eJeL6sSBw4m5aabgGWBT"),
+					direction: Direction::Right,
+				},
+				MerklePathItem {
+					hash: hash("FR6wWrpjkV31NHr6BvRjJmxmL4Y5qqmrLRHT42sidMv5"),
+					direction: Direction::Right,
+				},
+			],
+			// block_hash
+			CryptoHash([0; 32]),
+			// id
+			hash("8HoqDvJGYrSjaejXpv2PsK8c5NUvqhU3EcUFkgq18jx9"),
+			// outcome
+			(
+				Vec::<String>::new(),
+				vec![hash("8hxkU4avDWFDCsZckig7oN2ypnYvLyb1qmZ3SA1t8iZK")],
+				2428395018008u64,
+				242839501800800000000u128,
+				String::from("relay.aurora"),
+				vec![
+					3u8, 114, 128, 19, 177, 40, 127, 16, 184, 156, 69, 215, 55, 142, 98, 142, 27,
+					111, 246, 232, 85, 207, 169, 209, 101, 242, 113, 144, 111, 227, 117, 100, 30,
+				],
+			),
+		)
+			.try_to_vec()
+			.unwrap();
+		let outcome_root_proof = vec![
+			MerklePathItem {
+				hash: hash("3hbd1r5BK33WsN6Qit7qJCjFeVZfDFBZL3TnJt2S2T4T"),
+				direction: Direction::Left,
+			},
+			MerklePathItem {
+				hash: hash("4A9zZ1umpi36rXiuaKYJZgAjhUH9WoTrnSBXtA3wMdV2"),
+				direction: Direction::Left,
+			},
+		]
+		.try_to_vec()
+		.unwrap();
+		let expected_block_outcome_root = hash("AZYywqmo6vXvhPdVyuotmoEDgNb2tQzh2A1kV5f4Mxmq").0;
+
+		assert_eq!(
+			validate_transaction_ffi(
+				&outcome_proof,
+				&outcome_root_proof,
+				&expected_block_outcome_root
+			),
+			NearLcStatus::Ok
+		);
+		assert_eq!(
+			validate_transaction_ffi(&outcome_proof, &outcome_root_proof, &[0; 32]),
+			NearLcStatus::TransactionValidation
+		);
+		// the root is a 32 bytes hash
+		assert_eq!(
+			validate_transaction_ffi(&outcome_proof, &outcome_root_proof, &[0; 31]),
+			NearLcStatus::InvalidEncoding
+		);
+	}
+}
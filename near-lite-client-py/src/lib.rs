@@ -0,0 +1,217 @@
+//! # Near lite client Python bindings
+//!
+//! Exposes the lite client to Python (`import near_lc`) in order to check headers and proofs
+//! offline, e.g. from the RPC responses gathered while investigating an incident.
+//!
+//! NEAR types are passed around as borsh encoded `bytes`, hashes as their 32 raw bytes. The
+//! `parse_*` functions turn RPC JSON responses into those encodings.
+//!
+//! The module is built with maturin: `maturin develop --extras test && pytest tests`.
+
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_lite_client::{
+	validate_head as lite_client_validate_head,
+	validate_transaction as lite_client_validate_transaction, CryptoHash, LightClientBlockView,
+	MerklePath, OutcomeProof, ValidatorStakeView,
+};
+use near_merkle_proofs::state_proof::{
+	verify_state_proof as merkle_verify_state_proof, RawTrieNodeWithSize,
+};
+use near_primitives::{
+	merkle::MerklePathItem,
+	views::{
+		ExecutionOutcomeWithIdView, LightClientBlockLiteView,
+		LightClientBlockView as NearLightClientBlockView,
+	},
+};
+use near_primitives_wasm::HostFunctions;
+use pyo3::{
+	create_exception,
+	exceptions::{PyException, PyValueError},
+	prelude::*,
+	types::{PyBytes, PyDict},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+create_exception!(near_lc, ValidationError, PyException);
+
+struct NativeHostFunctions;
+
+impl HostFunctions for NativeHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		use sha2::Digest;
+		sha2::Sha256::digest(data).into()
+	}
+}
+
+/// `result` of the `light_client_proof` RPC method
+#[derive(Debug, Deserialize)]
+struct LightClientProof {
+	outcome_proof: ExecutionOutcomeWithIdView,
+	outcome_root_proof: Vec<MerklePathItem>,
+	block_header_lite: LightClientBlockLiteView,
+}
+
+/// Parses a `next_light_client_block` RPC response (or its `result`) into a borsh encoded
+/// `LightClientBlockView`.
+#[pyfunction]
+fn parse_light_client_block_view(py: Python, rpc_response: &str) -> PyResult<PyObject> {
+	let block_view = from_rpc_json::<NearLightClientBlockView>(rpc_response)?;
+	Ok(PyBytes::new(py, &encode(&LightClientBlockView::from(block_view))?).into())
+}
+
+/// Parses a `light_client_proof` RPC response (or its `result`) into the arguments of
+/// `validate_transaction`: the borsh encoded `outcome_proof` and `outcome_root_proof`, and the
+/// `outcome_root` of the block the proof has been requested for. This block must be validated
+/// separately.
+#[pyfunction]
+fn parse_light_client_proof(py: Python, rpc_response: &str) -> PyResult<PyObject> {
+	let proof = from_rpc_json::<LightClientProof>(rpc_response)?;
+	let outcome_proof = OutcomeProof::from(proof.outcome_proof);
+	let outcome_root_proof: MerklePath =
+		proof.outcome_root_proof.into_iter().map(Into::into).collect();
+	let dict = PyDict::new(py);
+	dict.set_item("outcome_proof", PyBytes::new(py, &encode(&outcome_proof)?))?;
+	dict.set_item("outcome_root_proof", PyBytes::new(py, &encode(&outcome_root_proof)?))?;
+	dict.set_item(
+		"outcome_root",
+		PyBytes::new(py, proof.block_header_lite.inner_lite.outcome_root.as_ref()),
+	)?;
+	Ok(dict.into())
+}
+
+/// Decodes a borsh encoded `LightClientBlockView` into a dict. `next_bps` stays borsh encoded,
+/// as expected by the `epoch_block_producers` argument of `validate_head`.
+#[pyfunction]
+fn decode_light_client_block_view(py: Python, block_view: &[u8]) -> PyResult<PyObject> {
+	let block_view = decode::<LightClientBlockView>(block_view)?;
+	let inner_lite = &block_view.inner_lite;
+	let next_bps = block_view.next_bps.as_ref().map(encode).transpose()?;
+
+	let dict = PyDict::new(py);
+	dict.set_item("hash", hash_to_py(py, block_view.current_block_hash::<NativeHostFunctions>()))?;
+	dict.set_item("prev_block_hash", hash_to_py(py, block_view.prev_block_hash))?;
+	dict.set_item("next_block_inner_hash", hash_to_py(py, block_view.next_block_inner_hash))?;
+	dict.set_item("inner_rest_hash", hash_to_py(py, block_view.inner_rest_hash))?;
+	dict.set_item("height", inner_lite.height)?;
+	dict.set_item("epoch_id", hash_to_py(py, inner_lite.epoch_id))?;
+	dict.set_item("next_epoch_id", hash_to_py(py, inner_lite.next_epoch_id))?;
+	dict.set_item("prev_state_root", hash_to_py(py, inner_lite.prev_state_root))?;
+	dict.set_item("outcome_root", hash_to_py(py, inner_lite.outcome_root))?;
+	dict.set_item("timestamp", inner_lite.timestamp)?;
+	dict.set_item("timestamp_nanosec", inner_lite.timestamp_nanosec)?;
+	dict.set_item("next_bp_hash", hash_to_py(py, inner_lite.next_bp_hash))?;
+	dict.set_item("block_merkle_root", hash_to_py(py, inner_lite.block_merkle_root))?;
+	dict.set_item("next_bps", next_bps.map(|next_bps| PyBytes::new(py, &next_bps)))?;
+	Ok(dict.into())
+}
+
+/// Hash of a borsh encoded `LightClientBlockView`
+#[pyfunction]
+fn block_hash(py: Python, block_view: &[u8]) -> PyResult<PyObject> {
+	let block_view = decode::<LightClientBlockView>(block_view)?;
+	Ok(hash_to_py(py, block_view.current_block_hash::<NativeHostFunctions>()))
+}
+
+/// Validates `block_view` against `head`, raising a `ValidationError` when it is not valid.
+/// `epoch_block_producers` maps epoch ids to their borsh encoded block producers.
+#[pyfunction]
+fn validate_head(head: &[u8], block_view: &[u8], epoch_block_producers: &PyDict) -> PyResult<()> {
+	let head = decode::<LightClientBlockView>(head)?;
+	let block_view = decode::<LightClientBlockView>(block_view)?;
+	let epoch_block_producers = epoch_block_producers
+		.iter()
+		.map(|(epoch_id, block_producers)| {
+			Ok((
+				hash_from_py(epoch_id.extract()?)?,
+				decode::<Vec<ValidatorStakeView>>(block_producers.extract()?)?,
+			))
+		})
+		.collect::<PyResult<BTreeMap<_, _>>>()?;
+	if !epoch_block_producers.contains_key(&block_view.inner_lite.epoch_id) {
+		return Err(ValidationError::new_err("block producers of the block's epoch are unknown"));
+	}
+
+	lite_client_validate_head::<NativeHostFunctions>(&head, &block_view, &epoch_block_producers)
+		.map_err(|err| ValidationError::new_err(format!("{:?}", err)))
+}
+
+/// Validates that the outcome of a transaction is part of the block with the given outcome
+/// root, raising a `ValidationError` when it is not.
+#[pyfunction]
+fn validate_transaction(
+	outcome_proof: &[u8],
+	outcome_root_proof: &[u8],
+	expected_block_outcome_root: &[u8],
+) -> PyResult<()> {
+	lite_client_validate_transaction::<NativeHostFunctions>(
+		&decode::<OutcomeProof>(outcome_proof)?,
+		decode::<MerklePath>(outcome_root_proof)?,
+		hash_from_py(expected_block_outcome_root)?,
+	)
+	.map_err(|err| ValidationError::new_err(format!("{:?}", err)))
+}
+
+/// Verifies that `value` is stored under `key` in the state trie with root `state_root`, or
+/// that `key` is not part of it when `value` is `None`. `proof` is the list of raw trie nodes
+/// returned by the `view_state` RPC query.
+#[pyfunction]
+#[pyo3(signature = (key, proof, state_root, value = None))]
+fn verify_state_proof(
+	key: &[u8],
+	proof: Vec<&[u8]>,
+	state_root: &[u8],
+	value: Option<&[u8]>,
+) -> PyResult<bool> {
+	let levels = proof
+		.into_iter()
+		.map(|node| RawTrieNodeWithSize::decode(node).map_err(PyValueError::new_err))
+		.collect::<PyResult<Vec<_>>>()?;
+	Ok(merkle_verify_state_proof::<NativeHostFunctions>(
+		key,
+		&levels,
+		value,
+		hash_from_py(state_root)?,
+	))
+}
+
+#[pymodule]
+fn near_lc(py: Python, m: &PyModule) -> PyResult<()> {
+	m.add("ValidationError", py.get_type::<ValidationError>())?;
+	m.add_function(wrap_pyfunction!(parse_light_client_block_view, m)?)?;
+	m.add_function(wrap_pyfunction!(parse_light_client_proof, m)?)?;
+	m.add_function(wrap_pyfunction!(decode_light_client_block_view, m)?)?;
+	m.add_function(wrap_pyfunction!(block_hash, m)?)?;
+	m.add_function(wrap_pyfunction!(validate_head, m)?)?;
+	m.add_function(wrap_pyfunction!(validate_transaction, m)?)?;
+	m.add_function(wrap_pyfunction!(verify_state_proof, m)?)?;
+	Ok(())
+}
+
+/// Deserializes either a full RPC response or only its `result`
+fn from_rpc_json<T: DeserializeOwned>(rpc_response: &str) -> PyResult<T> {
+	let mut value = serde_json::from_str::<serde_json::Value>(rpc_response)
+		.map_err(|err| PyValueError::new_err(err.to_string()))?;
+	if let Some(result) = value.get_mut("result") {
+		value = result.take();
+	}
+	serde_json::from_value(value).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn encode<T: BorshSerialize>(value: &T) -> PyResult<Vec<u8>> {
+	value.try_to_vec().map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn decode<T: BorshDeserialize>(bytes: &[u8]) -> PyResult<T> {
+	T::try_from_slice(bytes).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+fn hash_from_py(bytes: &[u8]) -> PyResult<CryptoHash> {
+	CryptoHash::try_from(bytes).map_err(|_| PyValueError::new_err("hashes are 32 bytes long"))
+}
+
+fn hash_to_py(py: Python, hash: CryptoHash) -> PyObject {
+	PyBytes::new(py, hash.as_ref()).into()
+}
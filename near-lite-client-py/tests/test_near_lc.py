@@ -0,0 +1,118 @@
+import json
+from pathlib import Path
+
+import pytest
+
+import near_lc
+
+FIXTURES = Path(__file__).resolve().parents[2] / "test-fixtures"
+
+# same proof used in `near-merkle-proofs`, taken from NEAR CORE
+DOGE_PROOF = [
+    bytes.fromhex(node)
+    for node in [
+        "0301000000165a1e73ea8e3686db1c5938a8d22912c2e2936fe475d933c310c452cbfb4306ff5303000000000000",
+        "011001aa33bd98698cbe05e77f027fc9bd32783d433a9a9f6213e80a523dd82e7fab108d0c9da6ee86e6957cd608f5a9bac9b9069ceaf555431744a4b09311ad62898e1f03000000000000",
+        "0302000000006f7240cc9614e62849c6e37a9b74e3e46707c42e517061622d45a1c62849d744d31002000000000000",
+        "02040000006806f86a17f0ae04d4ad43168fcdd0651c0fe99108b992f5ac398fd6bf235331400044a09c32d7c008aa4cd3f44adbd62bfe01a7811f21591aef4df91634d4530d1dda01000000000000",
+        "01880095581dfffd1f4f734b2c69c960e63193c18fae785d0c20d9a1c52c7c1374b5efe5fe9244cc68ea29ca00d2207f0033bb007be8aed9a00e27e23f1c84456bf02f7201000000000000",
+        "02050000006588ef4db6a357d6d9ca7d0c9feb69bd8e2f236ab88459da5c193b7fa95031874000e2919fa19c4fe63ae7741fc7e42ab169792efc46b715749822df86bb74c977afd300000000000000",
+        "00010000003504000000b3a1984ba0b1d8ad7f9dc881dfd9c9dc78c76c647a7692fbbfd6fcdcb9d9a1216a00000000000000",
+    ]
+]
+
+
+def b58decode(encoded):
+    alphabet = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+    number = 0
+    for char in encoded:
+        number = number * 58 + alphabet.index(char)
+    return number.to_bytes(32, "big")
+
+
+DOGE_ROOT = b58decode("hvKZryexWm5CPgcvB3VKxKp1uRQWZnSDALLNa9raXJV")
+
+
+def block_view(height):
+    return near_lc.parse_light_client_block_view(
+        (FIXTURES / f"light_client_block_{height}.json").read_text()
+    )
+
+
+@pytest.fixture
+def checkpoint():
+    return block_view(86441383)
+
+
+@pytest.fixture
+def header():
+    return block_view(86455909)
+
+
+@pytest.fixture
+def epoch_block_producers(checkpoint):
+    fields = near_lc.decode_light_client_block_view(checkpoint)
+    return {fields["next_epoch_id"]: fields["next_bps"]}
+
+
+def test_parse_light_client_block_view(checkpoint):
+    fields = near_lc.decode_light_client_block_view(checkpoint)
+    assert fields["height"] == 86441383
+    assert fields["prev_block_hash"] == b58decode("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr")
+    assert fields["next_bps"] is not None
+
+    # the `result` of the RPC response is accepted as well
+    response = json.loads((FIXTURES / "light_client_block_86441383.json").read_text())
+    assert near_lc.parse_light_client_block_view(json.dumps(response["result"])) == checkpoint
+
+    with pytest.raises(ValueError):
+        near_lc.parse_light_client_block_view("{}")
+
+
+def test_block_hash(checkpoint):
+    assert near_lc.block_hash(block_view(86380519)) == b58decode(
+        "DixB3qV9kRwPDWMKTuhBLM67QgW7bpJ6M5hrZr79kC8F"
+    )
+    assert near_lc.block_hash(checkpoint) == near_lc.decode_light_client_block_view(checkpoint)["hash"]
+
+
+def test_validate_head(checkpoint, header, epoch_block_producers):
+    assert near_lc.validate_head(checkpoint, header, epoch_block_producers) is None
+    assert near_lc.validate_head(checkpoint, block_view(86456070), epoch_block_producers) is None
+
+    # going backwards
+    with pytest.raises(near_lc.ValidationError):
+        near_lc.validate_head(header, checkpoint, epoch_block_producers)
+    # unknown epoch
+    with pytest.raises(near_lc.ValidationError):
+        near_lc.validate_head(checkpoint, header, {})
+    with pytest.raises(ValueError):
+        near_lc.validate_head(checkpoint, b"\x01\x02\x03", epoch_block_producers)
+
+
+def test_validate_transaction():
+    proof = near_lc.parse_light_client_proof(
+        (FIXTURES / "light_client_proof_8HoqDvJGYrSjaejXpv2PsK8c5NUvqhU3EcUFkgq18jx9.json").read_text()
+    )
+    assert proof["outcome_root"] == b58decode("AZYywqmo6vXvhPdVyuotmoEDgNb2tQzh2A1kV5f4Mxmq")
+
+    assert (
+        near_lc.validate_transaction(
+            proof["outcome_proof"], proof["outcome_root_proof"], proof["outcome_root"]
+        )
+        is None
+    )
+    with pytest.raises(near_lc.ValidationError):
+        near_lc.validate_transaction(proof["outcome_proof"], proof["outcome_root_proof"], bytes(32))
+    with pytest.raises(ValueError):
+        near_lc.validate_transaction(proof["outcome_proof"], proof["outcome_root_proof"], bytes(31))
+
+
+def test_verify_state_proof():
+    assert near_lc.verify_state_proof(b"doge", DOGE_PROOF, DOGE_ROOT, b"coin")
+    assert not near_lc.verify_state_proof(b"doge", DOGE_PROOF, DOGE_ROOT, b"coin_not_present")
+    assert near_lc.verify_state_proof(b"white_horse", DOGE_PROOF[:1], DOGE_ROOT)
+    assert not near_lc.verify_state_proof(b"doge", DOGE_PROOF, DOGE_ROOT)
+
+    with pytest.raises(ValueError):
+        near_lc.verify_state_proof(b"doge", [b"\x09"], DOGE_ROOT, b"coin")
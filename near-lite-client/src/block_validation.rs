@@ -5,16 +5,95 @@ use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
 use crate::{error::NearLiteClientError, LiteClientResult};
 
 use near_primitives_wasm::{
-	ApprovalInner, CryptoHash, LightClientBlockView, ValidatorStakeView,
+	AccountId, ApprovalInner, Balance, CryptoHash, LightClientBlockView, ValidatorStakeView,
 };
 
 use borsh::BorshSerialize;
 
+/// Whether a block producer approved the block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalStatus {
+	Signed,
+	Missing,
+	/// the signature is not the one of the block producer over the approval message
+	Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorApproval {
+	pub account_id: AccountId,
+	pub stake: Balance,
+	pub status: ApprovalStatus,
+}
+
+/// Outcome of the six checks of `validate_light_block`, with what they are computed from
+#[derive(Debug)]
+pub struct LightBlockReport {
+	pub block_hash: CryptoHash,
+	pub next_block_hash: CryptoHash,
+	pub approval_message: Vec<u8>,
+	/// approvals of the block producers of the epoch of the block, empty when they are unknown
+	pub approvals: Vec<ValidatorApproval>,
+	pub approved_stake: Balance,
+	pub total_stake: Balance,
+	/// the approved stake must be above it: 2/3 of the total stake
+	pub threshold: Balance,
+	/// (1) the block is ahead of the head
+	pub height: LiteClientResult<()>,
+	/// (2) the block is in the epoch of the head or in the next one
+	pub epoch: LiteClientResult<()>,
+	/// (3) the blocks of the next epoch have `next_bps`
+	pub next_bps: LiteClientResult<()>,
+	/// (4) the approvals are signed by their block producers
+	pub signatures: LiteClientResult<()>,
+	/// (5) the approvals are more than 2/3 of the stake
+	pub stake: LiteClientResult<()>,
+	/// (6) `next_bp_hash` is the hash of `next_bps`
+	pub next_bp_hash: LiteClientResult<()>,
+}
+
+impl LightBlockReport {
+	/// the checks, in the order of the spec
+	pub fn checks(&self) -> [(&'static str, &LiteClientResult<()>); 6] {
+		[
+			("height", &self.height),
+			("epoch", &self.epoch),
+			("next_bps", &self.next_bps),
+			("signatures", &self.signatures),
+			("stake", &self.stake),
+			("next_bp_hash", &self.next_bp_hash),
+		]
+	}
+
+	pub fn is_valid(&self) -> bool {
+		self.checks().iter().all(|(_, outcome)| outcome.is_ok())
+	}
+
+	/// the first failed check, as returned by `validate_light_block`
+	pub fn into_result(self) -> LiteClientResult<()> {
+		[self.height, self.epoch, self.next_bps, self.signatures, self.stake, self.next_bp_hash]
+			.into_iter()
+			.find(Result::is_err)
+			.unwrap_or(Ok(()))
+	}
+}
+
 pub fn validate_light_block<H: HostFunctions>(
 	head: &LightClientBlockView,
 	block_view: &LightClientBlockView,
 	epoch_block_producers_map: &BTreeMap<CryptoHash, Vec<ValidatorStakeView>>,
 ) -> LiteClientResult<()> {
+	validate_light_block_with_report::<H>(head, block_view, epoch_block_producers_map)?
+		.into_result()
+}
+
+/// Runs every check of `validate_light_block` instead of stopping at the first failure, and
+/// reports their outcome along with the approval of every block producer.
+pub fn validate_light_block_with_report<H: HostFunctions>(
+	head: &LightClientBlockView,
+	block_view: &LightClientBlockView,
+	epoch_block_producers_map: &BTreeMap<CryptoHash, Vec<ValidatorStakeView>>,
+) -> LiteClientResult<LightBlockReport> {
 	//The light client updates its head with the information from LightClientBlockView iff:
 
 	// 1. The height of the block is higher than the height of the current head;
@@ -28,85 +107,110 @@ pub fn validate_light_block<H: HostFunctions>(
 
 	// QUESTION: do we also want to pass the block hash received from the RPC?
 	// it's not on the spec, but it's an extra validation
-	let (_current_block_hash, _next_block_hash, approval_message) =
+	let (block_hash, next_block_hash, approval_message) =
 		reconstruct_light_client_block_view_fields::<H>(block_view)?;
+	let invalid = |message: &str| -> LiteClientResult<()> {
+		Err(NearLiteClientError::InvalidLiteBlock(String::from(message)))
+	};
 
 	// (1)
-	if block_view.inner_lite.height <= head.inner_lite.height {
-		return Err(NearLiteClientError::InvalidLiteBlock(String::from(
-			"block view height is not ahead of the head's height",
-		)));
-	}
+	let height = if block_view.inner_lite.height <= head.inner_lite.height {
+		invalid("block view height is not ahead of the head's height")
+	} else {
+		Ok(())
+	};
 
 	// (2)
-	if ![head.inner_lite.epoch_id, head.inner_lite.next_epoch_id]
+	let epoch = if ![head.inner_lite.epoch_id, head.inner_lite.next_epoch_id]
 		.contains(&block_view.inner_lite.epoch_id)
 	{
-		return Err(NearLiteClientError::InvalidLiteBlock(String::from(
-			"block view epoch id not present in the head",
-		)));
-	}
+		invalid("block view epoch id not present in the head")
+	} else {
+		Ok(())
+	};
 
 	// (3)
-	if block_view.inner_lite.epoch_id == head.inner_lite.next_epoch_id
-		&& block_view.next_bps.is_none()
+	let next_bps = if block_view.inner_lite.epoch_id == head.inner_lite.next_epoch_id &&
+		block_view.next_bps.is_none()
 	{
-		return Err(NearLiteClientError::InvalidLiteBlock(String::from(
-			"block view epoch id is not the next epoch",
-		)));
-	}
+		invalid("block view epoch id is not the next epoch")
+	} else {
+		Ok(())
+	};
 
 	//  (4) and (5)
 	let mut total_stake = 0;
 	let mut approved_stake = 0;
-
-	let epoch_block_producers = &epoch_block_producers_map[&block_view.inner_lite.epoch_id];
-
-	for (maybe_signature, block_producer) in
-		block_view.approvals_after_next.iter().zip(epoch_block_producers.iter())
-	{
-		let bp_stake_view = block_producer.clone().into_validator_stake();
-		let bp_stake = bp_stake_view.stake;
-		total_stake += bp_stake;
-
-		if maybe_signature.is_none() {
-			continue;
-		}
-
-		approved_stake += bp_stake;
-
-		let validator_public_key = bp_stake_view.public_key.clone();
-		if !maybe_signature
-			.as_ref()
-			.unwrap()
-			.verify(&approval_message, validator_public_key.clone())
-		{
-			return Err(NearLiteClientError::SignatureVerification(String::from(
-				"signature is not valid",
-			)));
-		}
-	}
+	let mut approvals = Vec::new();
+	let signatures = match epoch_block_producers_map.get(&block_view.inner_lite.epoch_id) {
+		Some(epoch_block_producers) => {
+			for (maybe_signature, block_producer) in
+				block_view.approvals_after_next.iter().zip(epoch_block_producers.iter())
+			{
+				let bp_stake_view = block_producer.clone().into_validator_stake();
+				let bp_stake = bp_stake_view.stake;
+				total_stake += bp_stake;
+
+				let status = match maybe_signature {
+					None => ApprovalStatus::Missing,
+					Some(signature) => {
+						approved_stake += bp_stake;
+						let validator_public_key = bp_stake_view.public_key.clone();
+						if H::verify(signature.clone(), &approval_message, validator_public_key) {
+							ApprovalStatus::Signed
+						} else {
+							ApprovalStatus::Invalid
+						}
+					},
+				};
+				approvals.push(ValidatorApproval {
+					account_id: bp_stake_view.account_id,
+					stake: bp_stake,
+					status,
+				});
+			}
+			if approvals.iter().any(|approval| approval.status == ApprovalStatus::Invalid) {
+				Err(NearLiteClientError::SignatureVerification(String::from(
+					"signature is not valid",
+				)))
+			} else {
+				Ok(())
+			}
+		},
+		None => invalid("unknown block producers of the block view epoch"),
+	};
 
 	let threshold = total_stake * 2 / 3;
-	if approved_stake <= threshold {
-		return Err(NearLiteClientError::InvalidLiteBlock(String::from(
-			"block is not final: stake threshold is not reached",
-		)));
-	}
+	let stake = if approved_stake <= threshold {
+		invalid("block is not final: stake threshold is not reached")
+	} else {
+		Ok(())
+	};
 
 	// # (6)
-	if block_view.next_bps.is_some() {
-		let block_view_next_bps_serialized =
-			block_view.next_bps.as_deref().unwrap().try_to_vec()?;
-		if H::sha256(&block_view_next_bps_serialized).as_slice()
-			!= block_view.inner_lite.next_bp_hash.as_ref()
-		{
-			return Err(NearLiteClientError::InvalidLiteBlock(String::from(
-				"inccorect next bp hash in block view",
-			)));
-		}
-	}
-	Ok(())
+	let next_bp_hash = match block_view.next_bps.as_deref() {
+		Some(next_bps)
+			if H::sha256(&next_bps.try_to_vec()?).as_slice() !=
+				block_view.inner_lite.next_bp_hash.as_ref() =>
+			invalid("inccorect next bp hash in block view"),
+		_ => Ok(()),
+	};
+
+	Ok(LightBlockReport {
+		block_hash,
+		next_block_hash,
+		approval_message,
+		approvals,
+		approved_stake,
+		total_stake,
+		threshold,
+		height,
+		epoch,
+		next_bps,
+		signatures,
+		stake,
+		next_bp_hash,
+	})
 }
 
 pub fn reconstruct_light_client_block_view_fields<H: HostFunctions>(
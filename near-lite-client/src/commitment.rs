@@ -0,0 +1,339 @@
+//! # IBC commitments
+//!
+//! An IBC module running as a NEAR contract keeps its packet commitments, acknowledgements
+//! and receipts in the contract's storage. This module maps the ICS-24 paths to the
+//! `ContractData` keys of the NEAR state trie and verifies their values against the state
+//! root of a block that has already been validated by the lite client.
+
+use alloc::{format, string::String, vec::Vec};
+use borsh::{BorshDeserialize, BorshSerialize};
+use core::fmt;
+
+use near_merkle_proofs::commitment::{
+	verify_membership, verify_non_membership, CommitmentPrefix, CommitmentProofBytes,
+	CommitmentRoot,
+};
+use near_primitives_wasm::{
+	AccountId, CryptoHash, HostFunctions, LightClientBlockView, MerklePath,
+};
+
+use crate::{error::NearLiteClientError, merkle_tree::compute_root_from_path, LiteClientResult};
+
+// ported from NEAR CORE (`near_primitives::trie_key`)
+const CONTRACT_DATA: u8 = 9;
+const ACCOUNT_DATA_SEPARATOR: u8 = b',';
+
+/// ICS-24 paths of the values an IBC module commits to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IbcPath {
+	ClientState { client_id: String },
+	ConsensusState { client_id: String, revision_number: u64, revision_height: u64 },
+	Connection { connection_id: String },
+	ChannelEnd { port_id: String, channel_id: String },
+	NextSequenceSend { port_id: String, channel_id: String },
+	NextSequenceRecv { port_id: String, channel_id: String },
+	NextSequenceAck { port_id: String, channel_id: String },
+	Commitment { port_id: String, channel_id: String, sequence: u64 },
+	Ack { port_id: String, channel_id: String, sequence: u64 },
+	Receipt { port_id: String, channel_id: String, sequence: u64 },
+}
+
+impl fmt::Display for IbcPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::ClientState { client_id } => write!(f, "clients/{}/clientState", client_id),
+			Self::ConsensusState { client_id, revision_number, revision_height } => write!(
+				f,
+				"clients/{}/consensusStates/{}-{}",
+				client_id, revision_number, revision_height
+			),
+			Self::Connection { connection_id } => write!(f, "connections/{}", connection_id),
+			Self::ChannelEnd { port_id, channel_id } => {
+				write!(f, "channelEnds/ports/{}/channels/{}", port_id, channel_id)
+			},
+			Self::NextSequenceSend { port_id, channel_id } => {
+				write!(f, "nextSequenceSend/ports/{}/channels/{}", port_id, channel_id)
+			},
+			Self::NextSequenceRecv { port_id, channel_id } => {
+				write!(f, "nextSequenceRecv/ports/{}/channels/{}", port_id, channel_id)
+			},
+			Self::NextSequenceAck { port_id, channel_id } => {
+				write!(f, "nextSequenceAck/ports/{}/channels/{}", port_id, channel_id)
+			},
+			Self::Commitment { port_id, channel_id, sequence } => write!(
+				f,
+				"commitments/ports/{}/channels/{}/sequences/{}",
+				port_id, channel_id, sequence
+			),
+			Self::Ack { port_id, channel_id, sequence } => {
+				write!(f, "acks/ports/{}/channels/{}/sequences/{}", port_id, channel_id, sequence)
+			},
+			Self::Receipt { port_id, channel_id, sequence } => write!(
+				f,
+				"receipts/ports/{}/channels/{}/sequences/{}",
+				port_id, channel_id, sequence
+			),
+		}
+	}
+}
+
+/// Location of the IBC store: the contract account and the prefix the contract
+/// prepends to every ICS-24 path before writing it to its storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IbcContractStorage {
+	pub account_id: AccountId,
+	pub storage_prefix: Vec<u8>,
+}
+
+impl IbcContractStorage {
+	/// `ContractData` prefix of the trie under which the IBC store lives
+	pub fn commitment_prefix(&self) -> CommitmentPrefix {
+		CommitmentPrefix(
+			[
+				&[CONTRACT_DATA][..],
+				self.account_id.as_bytes(),
+				&[ACCOUNT_DATA_SEPARATOR],
+				&self.storage_prefix,
+			]
+			.concat(),
+		)
+	}
+
+	/// Full state trie key of the given ICS-24 path
+	pub fn trie_key(&self, path: &IbcPath) -> Vec<u8> {
+		self.commitment_prefix().apply(format!("{}", path).as_bytes())
+	}
+}
+
+/// `prev_state_root` of a block is the merkle root of the state roots of all its shards.
+/// This is the proof that the state root of the shard holding the IBC contract is part of it.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct ShardStateRootProof {
+	pub state_root: CryptoHash,
+	pub path: MerklePath,
+}
+
+/// Verifies that `value` is stored under `path` in the IBC contract storage as of the state
+/// of `block_view`, a block that must have been validated beforehand (see `validate_head`).
+/// When `value` is `None` the absence of `path` is verified instead (e.g. packet receipts on
+/// timeouts).
+pub fn validate_ibc_commitment<H: HostFunctions>(
+	block_view: &LightClientBlockView,
+	storage: &IbcContractStorage,
+	path: &IbcPath,
+	shard_state_root_proof: &ShardStateRootProof,
+	state_proof: &CommitmentProofBytes,
+	value: Option<Vec<u8>>,
+) -> LiteClientResult<()> {
+	validate_contract_state::<H>(
+		block_view,
+		storage,
+		format!("{}", path).as_bytes(),
+		shard_state_root_proof,
+		state_proof,
+		value,
+	)
+}
+
+/// Same as `validate_ibc_commitment`, for a raw key of the IBC contract storage (e.g. an
+/// ICS-24 path received as a string).
+pub fn validate_contract_state<H: HostFunctions>(
+	block_view: &LightClientBlockView,
+	storage: &IbcContractStorage,
+	key: &[u8],
+	shard_state_root_proof: &ShardStateRootProof,
+	state_proof: &CommitmentProofBytes,
+	value: Option<Vec<u8>>,
+) -> LiteClientResult<()> {
+	let state_root = shard_state_root_proof.state_root;
+	let prev_state_root = compute_root_from_path::<H>(
+		&shard_state_root_proof.path,
+		CryptoHash(H::sha256(state_root.as_ref())),
+	)?;
+	if prev_state_root != block_view.inner_lite.prev_state_root {
+		return Err(NearLiteClientError::ProofVerificationError(String::from(
+			"shard state root is not part of the block's state root",
+		)));
+	}
+
+	let prefix = storage.commitment_prefix();
+	let root = CommitmentRoot(state_root);
+	match value {
+		Some(value) => verify_membership::<H>(&prefix, state_proof, &root, key, value),
+		None => verify_non_membership::<H>(&prefix, state_proof, &root, key),
+	}?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::{self, MockedHostFunctions};
+
+	fn storage() -> IbcContractStorage {
+		IbcContractStorage { account_id: "ibc.near".into(), storage_prefix: b"ibc/".to_vec() }
+	}
+
+	fn packet_commitment_path(sequence: u64) -> IbcPath {
+		IbcPath::Commitment { port_id: "transfer".into(), channel_id: "channel-0".into(), sequence }
+	}
+
+	fn single_leaf_trie(key: &[u8], value: &[u8]) -> (CryptoHash, CommitmentProofBytes) {
+		let (state_root, node) = test_utils::single_leaf_trie(key, value);
+		(state_root, CommitmentProofBytes::from(vec![node]))
+	}
+
+	fn block_with_state_root(state_root: CryptoHash) -> LightClientBlockView {
+		let mut block_view = LightClientBlockView::new_for_test();
+		// a single shard: the merkle root is the hash of its only leaf
+		block_view.inner_lite.prev_state_root =
+			CryptoHash(MockedHostFunctions::sha256(state_root.as_ref()));
+		block_view
+	}
+
+	#[test]
+	fn test_ics24_paths() {
+		let cases = [
+			(
+				IbcPath::ClientState { client_id: "07-tendermint-0".into() },
+				"clients/07-tendermint-0/clientState",
+			),
+			(
+				IbcPath::ConsensusState {
+					client_id: "07-tendermint-0".into(),
+					revision_number: 1,
+					revision_height: 42,
+				},
+				"clients/07-tendermint-0/consensusStates/1-42",
+			),
+			(
+				IbcPath::Connection { connection_id: "connection-0".into() },
+				"connections/connection-0",
+			),
+			(
+				IbcPath::ChannelEnd { port_id: "transfer".into(), channel_id: "channel-0".into() },
+				"channelEnds/ports/transfer/channels/channel-0",
+			),
+			(
+				IbcPath::NextSequenceRecv {
+					port_id: "transfer".into(),
+					channel_id: "channel-0".into(),
+				},
+				"nextSequenceRecv/ports/transfer/channels/channel-0",
+			),
+			(
+				packet_commitment_path(7),
+				"commitments/ports/transfer/channels/channel-0/sequences/7",
+			),
+			(
+				IbcPath::Ack {
+					port_id: "transfer".into(),
+					channel_id: "channel-0".into(),
+					sequence: 7,
+				},
+				"acks/ports/transfer/channels/channel-0/sequences/7",
+			),
+			(
+				IbcPath::Receipt {
+					port_id: "transfer".into(),
+					channel_id: "channel-0".into(),
+					sequence: 7,
+				},
+				"receipts/ports/transfer/channels/channel-0/sequences/7",
+			),
+		];
+		for (path, expected) in cases {
+			assert_eq!(format!("{}", path), expected);
+		}
+	}
+
+	#[test]
+	fn test_trie_key() {
+		assert_eq!(
+			storage().trie_key(&packet_commitment_path(1)),
+			b"\x09ibc.near,ibc/commitments/ports/transfer/channels/channel-0/sequences/1".to_vec()
+		);
+	}
+
+	#[test]
+	fn test_validate_ibc_commitment() {
+		let value = b"packet commitment".to_vec();
+		let (state_root, state_proof) =
+			single_leaf_trie(&storage().trie_key(&packet_commitment_path(1)), &value);
+		let block_view = block_with_state_root(state_root);
+		let shard_state_root_proof = ShardStateRootProof { state_root, path: vec![] };
+
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&storage(),
+			&packet_commitment_path(1),
+			&shard_state_root_proof,
+			&state_proof,
+			Some(value.clone()),
+		)
+		.is_ok());
+
+		// wrong value
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&storage(),
+			&packet_commitment_path(1),
+			&shard_state_root_proof,
+			&state_proof,
+			Some(b"another commitment".to_vec()),
+		)
+		.is_err());
+
+		// the commitment is stored, so it can't be proven absent
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&storage(),
+			&packet_commitment_path(1),
+			&shard_state_root_proof,
+			&state_proof,
+			None,
+		)
+		.is_err());
+
+		// a different sequence is not in the trie
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&storage(),
+			&packet_commitment_path(2),
+			&shard_state_root_proof,
+			&state_proof,
+			None,
+		)
+		.is_ok());
+
+		// same key, but stored by another contract
+		let other_storage = IbcContractStorage { account_id: "other.near".into(), ..storage() };
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&other_storage,
+			&packet_commitment_path(1),
+			&shard_state_root_proof,
+			&state_proof,
+			Some(value),
+		)
+		.is_err());
+	}
+
+	#[test]
+	fn test_validate_ibc_commitment_wrong_state_root() {
+		let value = b"packet commitment".to_vec();
+		let (state_root, state_proof) =
+			single_leaf_trie(&storage().trie_key(&packet_commitment_path(1)), &value);
+		let block_view = block_with_state_root(CryptoHash([1; 32]));
+
+		assert!(validate_ibc_commitment::<MockedHostFunctions>(
+			&block_view,
+			&storage(),
+			&packet_commitment_path(1),
+			&ShardStateRootProof { state_root, path: vec![] },
+			&state_proof,
+			Some(value),
+		)
+		.is_err());
+	}
+}
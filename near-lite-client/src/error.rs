@@ -1,11 +1,13 @@
 use alloc::string::String;
 use borsh::maybestd::io::Error as BorshError;
+use near_merkle_proofs::commitment::CommitmentError;
 use near_primitives_wasm::ConversionError;
 
 #[derive(Debug)]
 pub enum NearLiteClientError {
 	Borsh(BorshError),
 	Conversion(ConversionError),
+	Commitment(CommitmentError),
 	ProofVerificationError(String),
 	InvalidLiteBlock(String),
 	SignatureVerification(String),
@@ -28,6 +30,12 @@ impl From<ConversionError> for NearLiteClientError {
 	}
 }
 
+impl From<CommitmentError> for NearLiteClientError {
+	fn from(err: CommitmentError) -> Self {
+		Self::Commitment(err)
+	}
+}
+
 impl From<String> for NearLiteClientError {
 	fn from(err: String) -> Self {
 		Self::ProofVerificationError(err)
@@ -24,26 +24,35 @@ extern crate alloc;
 
 mod block_validation;
 mod checkpoint;
+mod commitment;
 mod error;
 mod merkle_tree;
-#[cfg(test)]
+#[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
 mod verifier;
 
+pub use block_validation::{
+	validate_light_block_with_report, ApprovalStatus, LightBlockReport, ValidatorApproval,
+};
 pub use checkpoint::TrustedCheckpoint;
+pub use commitment::{
+	validate_contract_state, validate_ibc_commitment, IbcContractStorage, IbcPath,
+	ShardStateRootProof,
+};
+pub use error::NearLiteClientError;
+pub use near_merkle_proofs::commitment::CommitmentProofBytes;
 pub use near_primitives_wasm::{
 	CryptoHash, LightClientBlockView, MerklePath, OutcomeProof, Signature, ValidatorStakeView,
 };
 pub use verifier::{validate_head, validate_transaction, validate_transactions};
 
-use crate::error::NearLiteClientError;
-
 pub type LiteClientResult<T> = Result<T, NearLiteClientError>;
 
 pub mod prelude {
 	pub use super::{
-		validate_head, validate_transaction, validate_transactions, CryptoHash,
-		LightClientBlockView, MerklePath, NearLiteClientTrait, OutcomeProof, Signature,
+		validate_head, validate_ibc_commitment, validate_transaction, validate_transactions,
+		CommitmentProofBytes, CryptoHash, IbcContractStorage, IbcPath, LightClientBlockView,
+		MerklePath, NearLiteClientTrait, OutcomeProof, ShardStateRootProof, Signature,
 		TrustedCheckpoint, ValidatorStakeView,
 	};
 }
@@ -1,13 +1,16 @@
-use near_primitives_wasm::HostFunctions;
+//! Fixtures of the tests, shared with the crates depending on this one through the `test-utils`
+//! feature
+
+use alloc::vec::Vec;
+
+use near_primitives_wasm::{CryptoHash, HostFunctions};
 
-#[cfg(test)]
 pub struct MockedHostFunctions;
 
-#[cfg(any(test))]
 impl HostFunctions for MockedHostFunctions {
 	fn sha256(data: &[u8]) -> [u8; 32] {
 		use sha2::Digest;
-		sha2::Sha256::digest(data).try_into().unwrap()
+		sha2::Sha256::digest(data).into()
 	}
 
 	fn verify(
@@ -15,6 +18,23 @@ impl HostFunctions for MockedHostFunctions {
 		data: impl AsRef<[u8]>,
 		public_key: near_primitives_wasm::PublicKey,
 	) -> bool {
-		todo!()
+		signature.verify(data, public_key)
 	}
 }
+
+/// Builds a trie holding `value` under `trie_key` in a single leaf, returning its root and its
+/// only node, the proof of the leaf
+pub fn single_leaf_trie(trie_key: &[u8], value: &[u8]) -> (CryptoHash, Vec<u8>) {
+	// leaf with an even number of nibbles: flag byte followed by the key itself
+	let encoded_key = [&[0x20][..], trie_key].concat();
+	let node = [
+		&[0u8][..],
+		&(encoded_key.len() as u32).to_le_bytes(),
+		&encoded_key,
+		&(value.len() as u32).to_le_bytes(),
+		&MockedHostFunctions::sha256(value),
+		&100u64.to_le_bytes(),
+	]
+	.concat();
+	(CryptoHash(MockedHostFunctions::sha256(&node)), node)
+}
@@ -0,0 +1,247 @@
+//! # Async connector
+//!
+//! `AsyncBlockchainConnector` is the tokio counterpart of `BlockchainConnector`: it sends the same
+//! requests, with the same failover and retries, without blocking a thread per request, so that
+//! many of them can be in flight at once, see `prefetch`.
+
+use std::time::{Duration, Instant};
+
+use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+use near_sdk::json_types::Base58CryptoHash;
+use serde_json::Value;
+
+use crate::{
+	blockchain_connector::{
+		chunk_params, decode_next_light_client_block, decode_response, light_client_proof_params,
+		next_light_client_block_params, request_body, view_state_params, BlockReference,
+		NearNetwork, ProofRequest, RpcBlockResponse, RpcChunkResponse, RpcEndpoint,
+		RpcLightClientExecutionProofResponse, RpcViewStateResponse,
+	},
+	error::RelayerError,
+	metrics::RelayerMetrics,
+	retry::{Failover, RetryPolicy},
+};
+
+/// Connects to Near RPC and submits requests asynchronously, see `BlockchainConnector` for the
+/// failover and the retries. Cloning it is cheap, and the clones share their connections.
+#[derive(Clone)]
+pub struct AsyncBlockchainConnector {
+	endpoints: Vec<RpcEndpoint>,
+	clients: Vec<reqwest::Client>,
+	retry_policy: RetryPolicy,
+	metrics: Option<RelayerMetrics>,
+}
+
+impl AsyncBlockchainConnector {
+	pub fn new(network: NearNetwork) -> Self {
+		Self::from_endpoint(RpcEndpoint::new(network.get_base_url()))
+	}
+
+	/// connects to the RPC node listening on `rpc_url`, e.g. a `MockRpcServer`
+	pub fn with_rpc_url(rpc_url: impl Into<String>) -> Self {
+		Self::from_endpoint(RpcEndpoint::new(rpc_url))
+	}
+
+	pub fn from_endpoint(endpoint: RpcEndpoint) -> Self {
+		Self::from_endpoints(vec![endpoint], RetryPolicy::default())
+	}
+
+	/// connects to `endpoints`, given in order of preference
+	pub fn from_endpoints(endpoints: Vec<RpcEndpoint>, retry_policy: RetryPolicy) -> Self {
+		assert!(!endpoints.is_empty(), "at least one endpoint is required");
+		let clients = endpoints
+			.iter()
+			.map(|endpoint| {
+				let mut client = reqwest::Client::builder();
+				if let Some(timeout_secs) = endpoint.timeout_secs {
+					client = client.timeout(Duration::from_secs(timeout_secs));
+				}
+				client.build().expect("the TLS backend is available")
+			})
+			.collect();
+		Self { endpoints, clients, retry_policy, metrics: None }
+	}
+
+	pub fn endpoints(&self) -> &[RpcEndpoint] {
+		&self.endpoints
+	}
+
+	/// counts the requests to every endpoint, their errors and their latency in `metrics`
+	pub fn with_metrics(self, metrics: RelayerMetrics) -> Self {
+		Self { metrics: Some(metrics), ..self }
+	}
+
+	/// submits a JSON-RPC request and returns its `result`, failing over to the next endpoints
+	/// and retrying as long as the errors are specific to an endpoint
+	pub async fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+		let metrics = self.metrics.as_ref();
+		let mut failover = Failover::new(&self.retry_policy, &self.endpoints, metrics, method);
+		while let Some(attempt) = failover.next_attempt() {
+			if let Some(backoff) = attempt.backoff {
+				tokio::time::sleep(backoff).await;
+			}
+			let endpoint = &self.endpoints[attempt.endpoint];
+			let client = &self.clients[attempt.endpoint];
+			let started = Instant::now();
+			let result = call_endpoint(endpoint, client, method, &params).await;
+			if let Some(result) = failover.report(result, started.elapsed()) {
+				return result;
+			}
+		}
+		Err(failover.into_error())
+	}
+
+	/// see `NearRpc::next_light_client_block`
+	pub async fn next_light_client_block(
+		&self,
+		last_known_hash: Base58CryptoHash,
+	) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		let params = next_light_client_block_params(last_known_hash);
+		decode_next_light_client_block(self.call("next_light_client_block", params).await?)
+	}
+
+	/// see `NearRpc::block`
+	pub async fn block(
+		&self,
+		block_reference: BlockReference,
+	) -> Result<RpcBlockResponse, RelayerError> {
+		Ok(serde_json::from_value(self.call("block", block_reference.to_params()).await?)?)
+	}
+
+	/// see `NearRpc::chunk`
+	pub async fn chunk(
+		&self,
+		chunk_id: Base58CryptoHash,
+	) -> Result<RpcChunkResponse, RelayerError> {
+		Ok(serde_json::from_value(self.call("chunk", chunk_params(chunk_id)).await?)?)
+	}
+
+	/// see `NearRpc::light_client_proof`
+	pub async fn light_client_proof(
+		&self,
+		light_client_head: Base58CryptoHash,
+		request: &ProofRequest,
+	) -> Result<RpcLightClientExecutionProofResponse, RelayerError> {
+		let params = light_client_proof_params(light_client_head, request)?;
+		Ok(serde_json::from_value(self.call("EXPERIMENTAL_light_client_proof", params).await?)?)
+	}
+
+	/// see `NearRpc::view_state`
+	pub async fn view_state(
+		&self,
+		block_hash: Base58CryptoHash,
+		account_id: &str,
+		key_prefix: &[u8],
+	) -> Result<RpcViewStateResponse, RelayerError> {
+		let params = view_state_params(block_hash, account_id, key_prefix);
+		Ok(serde_json::from_value(self.call("query", params).await?)?)
+	}
+}
+
+/// submits a JSON-RPC request to a single endpoint
+async fn call_endpoint(
+	endpoint: &RpcEndpoint,
+	client: &reqwest::Client,
+	method: &str,
+	params: &Value,
+) -> Result<Value, RelayerError> {
+	let url = format!("{}/", endpoint.url);
+	let request = endpoint
+		.headers
+		.iter()
+		.fold(client.post(&url), |request, (name, value)| request.header(name, value));
+	let response = request
+		.json(&request_body(method, params))
+		.send()
+		.await
+		.map_err(|err| RelayerError::Transport(err.to_string()))?;
+	let status = response.status().as_u16();
+	let body = response.text().await.map_err(|err| RelayerError::Transport(err.to_string()))?;
+	decode_response(status, &body)
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::mock_rpc::MockRpcServer;
+
+	const CHECKPOINT: &str = include_str!("../../test-fixtures/light_client_block_86441383.json");
+
+	fn hash(encoded: &str) -> Base58CryptoHash {
+		serde_json::from_value(json!(encoded)).unwrap()
+	}
+
+	#[tokio::test]
+	async fn test_async_connector() {
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond_with_recording(
+				"next_light_client_block",
+				json!(["FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"]),
+				CHECKPOINT,
+			)
+			.respond(
+				"next_light_client_block",
+				json!(["853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7"]),
+				json!({}),
+			)
+			.respond(
+				"block",
+				json!({ "block_id": 86441384 }),
+				json!({
+					"header": {
+						"height": 86441384,
+						"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+					},
+					"chunks": [],
+				}),
+			);
+		let rpc = AsyncBlockchainConnector::with_rpc_url(server.url());
+
+		let block_view = rpc
+			.next_light_client_block(hash("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"))
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(block_view.inner_lite.height, 86441383);
+		let up_to_date = rpc
+			.next_light_client_block(hash("853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7"))
+			.await
+			.unwrap();
+		assert!(up_to_date.is_none());
+
+		let block = rpc.block(BlockReference::Height(86441384)).await.unwrap();
+		assert_eq!(block.header.prev_hash, hash("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"));
+
+		// the mock answers unknown requests with a JSON-RPC error, which is not retried
+		match rpc.block(BlockReference::Height(1)).await {
+			Err(RelayerError::JsonRpc(_)) => {},
+			other => panic!("unexpected response {:?}", other.map(|block| block.header.height)),
+		}
+	}
+
+	#[tokio::test]
+	async fn test_async_failover() {
+		let server = MockRpcServer::start().unwrap();
+		server.fail_next(1, 503).respond(
+			"block",
+			json!({ "finality": "final" }),
+			json!({
+				"header": {
+					"height": 86441384,
+					"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+				},
+				"chunks": [],
+			}),
+		);
+		// nothing listens on the first endpoint, and the second one fails the first round
+		let rpc = AsyncBlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new("http://127.0.0.1:9"), RpcEndpoint::new(server.url())],
+			RetryPolicy { max_retries: 1, initial_backoff_ms: 1, max_backoff_ms: 1 },
+		);
+		let block = rpc.block(BlockReference::Final).await.unwrap();
+		assert_eq!(block.header.height, 86441384);
+	}
+}
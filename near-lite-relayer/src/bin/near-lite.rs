@@ -0,0 +1,133 @@
+//! # Offline verification and inspection
+//!
+//! `near-lite <command>`: verifies headers and proofs read from files, in the JSON of the RPC or
+//! of the proof service, or in Borsh, raw or in hex, and prints a verification report. Exits with
+//! 1 when a check fails. See `inspect` for the checks.
+
+use std::{collections::HashMap, env, error::Error, fmt, process};
+
+use near_lite_relayer::inspect::{self, Input, StateProof, TransactionProof};
+
+const USAGE: &str = "usage:
+  near-lite verify-block --head <head> --block <block> [--bps <block of the previous epoch>]
+  near-lite verify-tx <proof> [--head <head>]
+  near-lite verify-state <proof> [--head <head>]
+  near-lite block-hash <block>
+  near-lite decode <borsh-hex> --type <LightClientBlockView|LiteClientProof|LiteClientStateProof>";
+
+#[derive(Debug)]
+struct UsageError(String);
+
+impl fmt::Display for UsageError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl Error for UsageError {}
+
+fn main() {
+	let args = env::args().skip(1).collect::<Vec<_>>();
+	match run(&args) {
+		Ok(true) => {},
+		Ok(false) => process::exit(1),
+		Err(err) if err.is::<UsageError>() => {
+			eprintln!("{}\n{}", err, USAGE);
+			process::exit(2)
+		},
+		Err(err) => {
+			eprintln!("error: {}", err);
+			process::exit(1)
+		},
+	}
+}
+
+/// Positional arguments and `--name value` options of a command
+struct Args {
+	positional: Vec<String>,
+	options: HashMap<String, String>,
+}
+
+impl Args {
+	fn parse(args: &[String], known_options: &[&str]) -> Result<Self, UsageError> {
+		let mut parsed = Self { positional: Vec::new(), options: HashMap::new() };
+		let mut args = args.iter();
+		while let Some(arg) = args.next() {
+			match arg.strip_prefix("--") {
+				Some(name) if known_options.contains(&name) => {
+					let value = args
+						.next()
+						.ok_or_else(|| UsageError(format!("missing value of --{}", name)))?;
+					parsed.options.insert(name.to_string(), value.clone());
+				},
+				Some(name) => return Err(UsageError(format!("unknown option --{}", name))),
+				None => parsed.positional.push(arg.clone()),
+			}
+		}
+		Ok(parsed)
+	}
+
+	/// the only positional argument
+	fn single(&self, name: &str) -> Result<&str, UsageError> {
+		match self.positional.as_slice() {
+			[arg] => Ok(arg),
+			[] => Err(UsageError(format!("missing <{}>", name))),
+			_ => Err(UsageError(format!("expected a single <{}>", name))),
+		}
+	}
+
+	fn option(&self, name: &str) -> Option<&str> {
+		self.options.get(name).map(String::as_str)
+	}
+
+	fn required(&self, name: &str) -> Result<&str, UsageError> {
+		self.option(name).ok_or_else(|| UsageError(format!("missing --{}", name)))
+	}
+}
+
+/// runs the command and prints its report; returns whether the report is valid
+fn run(args: &[String]) -> Result<bool, Box<dyn Error>> {
+	let (command, args) = args.split_first().ok_or_else(|| UsageError("missing command".into()))?;
+	let read_block = |path: &str| inspect::light_client_block(Input::read(path)?);
+	let read_head = |args: &Args| args.option("head").map(read_block).transpose();
+
+	let report = match command.as_str() {
+		"verify-block" => {
+			let args = Args::parse(args, &["head", "block", "bps"])?;
+			if let Some(arg) = args.positional.first() {
+				return Err(UsageError(format!("unexpected argument {}", arg)).into())
+			}
+			let head = read_block(args.required("head")?)?;
+			let block_view = read_block(args.required("block")?)?;
+			let bps_block = args.option("bps").map(read_block).transpose()?;
+			inspect::verify_block(&head, &block_view, bps_block.as_ref())
+		},
+		"verify-tx" => {
+			let args = Args::parse(args, &["head"])?;
+			let proof = TransactionProof::decode(Input::read(args.single("proof")?)?)?;
+			inspect::verify_transaction(&proof, read_head(&args)?.as_ref())
+		},
+		"verify-state" => {
+			let args = Args::parse(args, &["head"])?;
+			let proof = StateProof::decode(Input::read(args.single("proof")?)?)?;
+			inspect::verify_state(&proof, read_head(&args)?.as_ref())
+		},
+		"block-hash" => {
+			let args = Args::parse(args, &[])?;
+			inspect::block_hash(&read_block(args.single("block")?)?)
+		},
+		"decode" => {
+			let args = Args::parse(args, &["type"])?;
+			let bytes = inspect::decode_hex(args.single("borsh-hex")?)?;
+			println!("{}", inspect::decode(args.required("type")?, &bytes)?);
+			return Ok(true)
+		},
+		"help" | "--help" | "-h" => {
+			println!("{}", USAGE);
+			return Ok(true)
+		},
+		_ => return Err(UsageError(format!("unknown command {}", command)).into()),
+	};
+	println!("{}", report);
+	Ok(report.is_valid())
+}
@@ -1,4 +1,10 @@
-use std::io;
+use std::{
+	collections::BTreeMap,
+	fmt,
+	path::PathBuf,
+	thread,
+	time::{Duration, Instant},
+};
 
 use borsh::BorshSerialize;
 use near_primitives::{
@@ -10,37 +16,96 @@ use near_primitives::{
 	},
 };
 use near_sdk::json_types::Base58CryptoHash;
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_aux::field_attributes::deserialize_number_from_string;
+use serde_json::Value;
+
+use crate::{
+	client_proof::ExecutionOutcomeViewForLiteClient,
+	error::{RelayerError, RpcError},
+	metrics::RelayerMetrics,
+	replay::{RecordedOutcome, Recording},
+	retry::{Failover, RetryPolicy},
+};
 
-use crate::client_proof::ExecutionOutcomeViewForLiteClient;
+/// RPC of a node started with `nearup run localnet`, or of a sandbox node
+pub const LOCALNET_RPC_URL: &str = "http://127.0.0.1:3030";
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum NearNetwork {
 	Mainnet,
 	Testnet,
+	/// local node, listening on `LOCALNET_RPC_URL`
+	Localnet,
+	/// any other network or node, e.g. a private RPC node
+	Custom {
+		url: String,
+		chain_id: String,
+	},
 }
 
-impl ToString for NearNetwork {
-	fn to_string(&self) -> String {
+impl fmt::Display for NearNetwork {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.chain_id())
+	}
+}
+
+impl NearNetwork {
+	pub fn chain_id(&self) -> &str {
 		match self {
-			Self::Mainnet => "mainnet".to_owned(),
-			Self::Testnet => "testnet".to_owned(),
+			Self::Mainnet => "mainnet",
+			Self::Testnet => "testnet",
+			Self::Localnet => "localnet",
+			Self::Custom { chain_id, .. } => chain_id,
+		}
+	}
+
+	pub fn get_base_url(&self) -> String {
+		match self {
+			Self::Mainnet | Self::Testnet => format!("https://rpc.{}.near.org", self.chain_id()),
+			Self::Localnet => LOCALNET_RPC_URL.to_owned(),
+			Self::Custom { url, .. } => url.clone(),
 		}
 	}
 }
 
-impl NearNetwork {
-	fn get_base_url(&self) -> String {
-		format!("https://rpc.{}.near.org", self.to_string())
+/// Settings of an RPC endpoint
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct RpcEndpoint {
+	pub url: String,
+	/// HTTP headers sent along every request, e.g. an API key
+	#[serde(default)]
+	pub headers: BTreeMap<String, String>,
+	/// timeout of a whole request, in seconds; no timeout when missing
+	#[serde(default)]
+	pub timeout_secs: Option<u64>,
+	/// whether the node keeps the whole history, rather than the last few epochs only
+	#[serde(default)]
+	pub archival: bool,
+}
+
+impl RpcEndpoint {
+	pub fn new(url: impl Into<String>) -> Self {
+		Self { url: url.into(), ..Default::default() }
 	}
 }
 
-/// Connects to Near RPC and submits requests
+/// Connects to Near RPC and submits requests.
+///
+/// Every request is sent to the endpoints in order of preference until one of them answers. The
+/// endpoints are tried again, following the `RetryPolicy`, as long as one of them failed with a
+/// transient error.
 pub struct BlockchainConnector {
-	network: NearNetwork,
+	endpoints: Vec<RpcEndpoint>,
+	agents: Vec<ureq::Agent>,
+	retry_policy: RetryPolicy,
+	/// where the requests and their responses are recorded, see `record_to`
+	recording_dir: Option<PathBuf>,
+	metrics: Option<RelayerMetrics>,
 }
 
-#[derive(Debug, BorshSerialize, Deserialize)]
+#[derive(Debug, Clone, BorshSerialize, Serialize, Deserialize)]
 pub struct RpcLightClientExecutionProofResponse {
 	/// Proof of execution outcome
 	pub outcome_proof: ExecutionOutcomeWithIdView,
@@ -96,247 +161,748 @@ impl From<RpcLightClientExecutionProofResponse>
 	}
 }
 
-impl BlockchainConnector {
-	pub fn new(network: NearNetwork) -> Self {
-		Self { network }
+/// Reference to a block in the `block` RPC method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockReference {
+	/// latest block that has been finalized
+	Final,
+	Height(u64),
+}
+
+impl BlockReference {
+	pub(crate) fn to_params(self) -> Value {
+		match self {
+			Self::Final => serde_json::json!({ "finality": "final" }),
+			Self::Height(height) => serde_json::json!({ "block_id": height }),
+		}
 	}
+}
 
-	/// gets the next client block view given a block hash that already has been validated
-	pub fn get_light_client_block_view(
+/// Outcome whose proof is requested from `EXPERIMENTAL_light_client_proof`, serialized as the
+/// parameters of the method
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProofRequest {
+	Transaction {
+		#[serde(rename = "transaction_hash")]
+		tx_hash: Base58CryptoHash,
+		sender_id: String,
+	},
+	Receipt {
+		receipt_id: Base58CryptoHash,
+		receiver_id: String,
+	},
+}
+
+/// `result` of the `block` RPC method, restricted to the fields used by the relayer
+#[derive(Debug, Deserialize)]
+pub struct RpcBlockResponse {
+	pub header: RpcBlockHeader,
+	pub chunks: Vec<RpcChunkHeader>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcBlockHeader {
+	pub height: u64,
+	pub prev_hash: Base58CryptoHash,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcChunkHeader {
+	pub chunk_hash: Base58CryptoHash,
+	/// the block includes the previous chunk of the shard again when this is not its height
+	pub height_included: u64,
+	#[serde(deserialize_with = "deserialize_number_from_string")]
+	pub balance_burnt: u128,
+	/// state root of the shard the chunk is applied to; the `prev_state_root` of the block is the
+	/// merkle root of the ones of its chunks
+	#[serde(default)]
+	pub prev_state_root: Option<CryptoHash>,
+}
+
+/// `result` of the `chunk` RPC method, restricted to the fields used by the relayer
+#[derive(Debug, Deserialize)]
+pub struct RpcChunkResponse {
+	pub transactions: Vec<RpcTransaction>,
+	pub receipts: Vec<RpcReceipt>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcTransaction {
+	pub hash: Base58CryptoHash,
+	pub signer_id: String,
+	pub receiver_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcReceipt {
+	pub receipt_id: Base58CryptoHash,
+	pub predecessor_id: String,
+	pub receiver_id: String,
+}
+
+/// `result` of the `view_state` query, restricted to the fields used by the relayer
+#[derive(Debug, Deserialize)]
+pub struct RpcViewStateResponse {
+	pub values: Vec<RpcStateItem>,
+	/// raw trie nodes proving the values, from the root of the state trie of their shard
+	#[serde(default, deserialize_with = "deserialize_base64_list")]
+	pub proof: Vec<Vec<u8>>,
+	pub block_height: u64,
+	pub block_hash: Base58CryptoHash,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcStateItem {
+	#[serde(deserialize_with = "deserialize_base64")]
+	pub key: Vec<u8>,
+	#[serde(deserialize_with = "deserialize_base64")]
+	pub value: Vec<u8>,
+}
+
+fn deserialize_base64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+	base64::decode(String::deserialize(deserializer)?).map_err(de::Error::custom)
+}
+
+fn deserialize_base64_list<'de, D: Deserializer<'de>>(
+	deserializer: D,
+) -> Result<Vec<Vec<u8>>, D::Error> {
+	Vec::<String>::deserialize(deserializer)?
+		.into_iter()
+		.map(|encoded| base64::decode(encoded).map_err(de::Error::custom))
+		.collect()
+}
+
+/// The NEAR RPC methods the relayer relies on.
+///
+/// `BlockchainConnector` implements it over HTTP; the helpers used by the relayer are built on
+/// top of these methods, so they can be exercised against any implementation.
+pub trait NearRpc {
+	/// `next_light_client_block`: the next light client block after `last_known_hash`, if the
+	/// node knows a more recent final block
+	fn next_light_client_block(
 		&self,
 		last_known_hash: Base58CryptoHash,
-	) -> io::Result<NearLightClientBlockView> {
-		#[derive(Debug, Deserialize)]
-		struct ResultFromRpc {
-			result: NearLightClientBlockView,
-		}
+	) -> Result<Option<NearLightClientBlockView>, RelayerError>;
 
-		// http post http://127.0.0.1:3030/ jsonrpc=2.0 method=next_light_client_block params:="[<last known hash>]" id="dontcare"
-		let url = format!("{}/", self.network.get_base_url());
-		let last_known_hash_string = String::from(&last_known_hash);
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "next_light_client_block",
-				"params": [last_known_hash_string],
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
+	/// `block`
+	fn block(&self, block_reference: BlockReference) -> Result<RpcBlockResponse, RelayerError>;
 
-		Ok(serde_json::from_value::<ResultFromRpc>(body.into_json().unwrap())?.result)
-	}
+	/// `chunk`
+	fn chunk(&self, chunk_id: Base58CryptoHash) -> Result<RpcChunkResponse, RelayerError>;
 
-	/// gets almost the latest finalized block that's available on the NearNetwork
-	/// helpful for testing purposes where we just want to get a hash, and based on it
-	/// retrieve the block view for the next block
-	pub fn get_almost_latest_finalized_block_hash_and_height(
+	/// `EXPERIMENTAL_light_client_proof` of a transaction or of a receipt, for the given light
+	/// client head
+	fn light_client_proof(
 		&self,
-	) -> io::Result<(Base58CryptoHash, u64)> {
-		#[derive(Debug, Deserialize)]
-		struct Response {
-			pub result: Result,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Result {
-			pub header: Header,
-		}
+		light_client_head: Base58CryptoHash,
+		request: &ProofRequest,
+	) -> Result<RpcLightClientExecutionProofResponse, RelayerError>;
 
-		#[derive(Debug, Deserialize)]
-		struct Header {
-			pub prev_hash: Base58CryptoHash,
-			pub height: u64,
-		}
+	/// `view_state` query of the contract state of `account_id` under `key_prefix`, as of the
+	/// block `block_hash`, with its proof
+	fn view_state(
+		&self,
+		block_hash: Base58CryptoHash,
+		account_id: &str,
+		key_prefix: &[u8],
+	) -> Result<RpcViewStateResponse, RelayerError>;
+
+	/// hints that the proofs of `requests` for `light_client_head` are about to be requested, see
+	/// `JsonRpc::prefetch`; nothing by default
+	fn prefetch_light_client_proofs(
+		&self,
+		_light_client_head: Base58CryptoHash,
+		_requests: &[&ProofRequest],
+	) {
+	}
 
-		let url = format!("{}/", self.network.get_base_url());
-		let params = ureq::json!({"finality": "final"});
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "block",
-				"params": params,
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
-		let r = body.into_json::<Response>()?;
-		Ok((r.result.header.prev_hash, r.result.header.height))
+	/// gets the next client block view given a block hash that already has been validated
+	fn get_light_client_block_view(
+		&self,
+		last_known_hash: Base58CryptoHash,
+	) -> Result<NearLightClientBlockView, RelayerError> {
+		self.next_light_client_block(last_known_hash)?.ok_or_else(|| {
+			RelayerError::Decode(format!(
+				"no light client block after {}",
+				String::from(&last_known_hash)
+			))
+		})
 	}
 
 	/// gets almost the latest finalized block that's available on the NearNetwork
 	/// helpful for testing purposes where we just want to get a hash, and based on it
 	/// retrieve the block view for the next block
-	pub fn get_block_hash_from_block_number(&self, height: u64) -> io::Result<Base58CryptoHash> {
-		#[derive(Debug, Deserialize)]
-		struct Response {
-			pub result: Result,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Result {
-			pub header: Header,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Header {
-			pub prev_hash: Base58CryptoHash,
-		}
+	fn get_almost_latest_finalized_block_hash_and_height(
+		&self,
+	) -> Result<(Base58CryptoHash, u64), RelayerError> {
+		let header = self.block(BlockReference::Final)?.header;
+		Ok((header.prev_hash, header.height))
+	}
 
-		let url = format!("{}/", self.network.get_base_url());
-		let params = ureq::json!({ "block_id": height });
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "block",
-				"params": params,
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
-		Ok(body.into_json::<Response>()?.result.header.prev_hash)
+	/// gets the hash of the block preceding the block at `height`, which is what
+	/// `next_light_client_block` expects in order to return the block at `height`
+	fn get_block_hash_from_block_number(
+		&self,
+		height: u64,
+	) -> Result<Base58CryptoHash, RelayerError> {
+		Ok(self.block(BlockReference::Height(height))?.header.prev_hash)
 	}
 
-	pub fn find_chunk_ids_with_burned_gas(
+	fn find_chunk_ids_with_burned_gas(
 		&self,
 		block_height: u64,
-	) -> io::Result<Vec<Base58CryptoHash>> {
-		#[derive(Debug, Deserialize)]
-		struct Response {
-			pub result: Result,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Result {
-			pub chunks: Vec<Chunks>,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Chunks {
-			#[serde(deserialize_with = "deserialize_number_from_string")]
-			pub balance_burnt: u128,
-			pub chunk_hash: Base58CryptoHash,
-		}
-
-		let url = format!("{}/", self.network.get_base_url());
-		let params = ureq::json!({ "block_id": block_height });
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "block",
-				"params": params,
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
-		Ok(body
-			.into_json::<Response>()?
-			.result
+	) -> Result<Vec<Base58CryptoHash>, RelayerError> {
+		Ok(self
+			.block(BlockReference::Height(block_height))?
 			.chunks
 			.into_iter()
 			.filter_map(|chunk| if chunk.balance_burnt > 0 { Some(chunk.chunk_hash) } else { None })
 			.collect())
 	}
 
-	pub fn get_transaction_ids_in_chunk(
+	fn get_transaction_ids_in_chunk(
 		&self,
 		chunk_id: Base58CryptoHash,
-	) -> io::Result<Vec<(Base58CryptoHash, String)>> {
-		#[derive(Debug, Deserialize)]
-		struct Response {
-			pub result: Result,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Result {
-			pub transactions: Vec<Tx>,
-		}
-
-		#[derive(Debug, Deserialize)]
-		struct Tx {
-			pub hash: Base58CryptoHash,
-			pub signer_id: String,
-		}
-
-		let url = format!("{}/", self.network.get_base_url());
-		let params = ureq::json!({ "chunk_id": chunk_id });
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "chunk",
-				"params": params,
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
-		Ok(body
-			.into_json::<Response>()?
-			.result
+	) -> Result<Vec<(Base58CryptoHash, String)>, RelayerError> {
+		Ok(self
+			.chunk(chunk_id)?
 			.transactions
 			.into_iter()
-			.flat_map(|tx| Some((tx.hash, tx.signer_id)))
+			.map(|tx| (tx.hash, tx.signer_id))
 			.collect())
 	}
 
-	pub fn get_light_client_proof_transaction(
+	fn get_light_client_proof_transaction(
 		&self,
 		light_client_head: Base58CryptoHash,
 		tx_hash: Base58CryptoHash,
 		sender_id: String,
-	) -> io::Result<RpcLightClientExecutionProofResponseForLiteClient> {
-		#[derive(Debug, Deserialize)]
-		struct Response {
-			pub result: RpcLightClientExecutionProofResponse,
+	) -> Result<RpcLightClientExecutionProofResponseForLiteClient, RelayerError> {
+		let request = ProofRequest::Transaction { tx_hash, sender_id };
+		Ok(self.light_client_proof(light_client_head, &request)?.into())
+	}
+}
+
+impl BlockchainConnector {
+	pub fn new(network: NearNetwork) -> Self {
+		Self::from_endpoint(RpcEndpoint::new(network.get_base_url()))
+	}
+
+	/// connects to the RPC node listening on `rpc_url`, e.g. a `MockRpcServer`
+	pub fn with_rpc_url(rpc_url: impl Into<String>) -> Self {
+		Self::from_endpoint(RpcEndpoint::new(rpc_url))
+	}
+
+	pub fn from_endpoint(endpoint: RpcEndpoint) -> Self {
+		Self::from_endpoints(vec![endpoint], RetryPolicy::default())
+	}
+
+	/// connects to `endpoints`, given in order of preference
+	pub fn from_endpoints(endpoints: Vec<RpcEndpoint>, retry_policy: RetryPolicy) -> Self {
+		assert!(!endpoints.is_empty(), "at least one endpoint is required");
+		let agents = endpoints
+			.iter()
+			.map(|endpoint| {
+				let mut agent = ureq::AgentBuilder::new();
+				if let Some(timeout_secs) = endpoint.timeout_secs {
+					agent = agent.timeout(Duration::from_secs(timeout_secs));
+				}
+				agent.build()
+			})
+			.collect();
+		Self { endpoints, agents, retry_policy, recording_dir: None, metrics: None }
+	}
+
+	pub fn endpoints(&self) -> &[RpcEndpoint] {
+		&self.endpoints
+	}
+
+	/// Records every request and its response, or the JSON-RPC error of the node, to a file of
+	/// `dir`, from which a `ReplayConnector` serves them again. Failures to reach the endpoints are
+	/// not recorded.
+	pub fn record_to(self, dir: impl Into<PathBuf>) -> Self {
+		Self { recording_dir: Some(dir.into()), ..self }
+	}
+
+	/// counts the requests to every endpoint, their errors and their latency in `metrics`
+	pub fn with_metrics(self, metrics: RelayerMetrics) -> Self {
+		Self { metrics: Some(metrics), ..self }
+	}
+
+	/// submits a JSON-RPC request and returns its `result`, failing over to the next endpoints
+	/// and retrying as long as the errors are specific to an endpoint
+	fn call_endpoints(&self, method: &str, params: &Value) -> Result<Value, RelayerError> {
+		let metrics = self.metrics.as_ref();
+		let mut failover = Failover::new(&self.retry_policy, &self.endpoints, metrics, method);
+		while let Some(attempt) = failover.next_attempt() {
+			if let Some(backoff) = attempt.backoff {
+				thread::sleep(backoff);
+			}
+			let endpoint = &self.endpoints[attempt.endpoint];
+			let agent = &self.agents[attempt.endpoint];
+			let started = Instant::now();
+			let result = call_endpoint(endpoint, agent, method, params);
+			if let Some(result) = failover.report(result, started.elapsed()) {
+				return result;
+			}
 		}
+		Err(failover.into_error())
+	}
+}
+
+/// submits a JSON-RPC request to a single endpoint
+fn call_endpoint(
+	endpoint: &RpcEndpoint,
+	agent: &ureq::Agent,
+	method: &str,
+	params: &Value,
+) -> Result<Value, RelayerError> {
+	// http post http://127.0.0.1:3030/ jsonrpc=2.0 method=<method> params:=<params> id="dontcare"
+	let url = format!("{}/", endpoint.url);
+	let request = endpoint
+		.headers
+		.iter()
+		.fold(agent.post(&url), |request, (name, value)| request.set(name, value));
+	let response = match request.send_json(request_body(method, params)) {
+		Ok(response) => response,
+		Err(ureq::Error::Status(status, response)) =>
+			return decode_response(status, &response.into_string().unwrap_or_default()),
+		Err(ureq::Error::Transport(err)) => return Err(err.into()),
+	};
+
+	let body = response.into_string().map_err(|err| RelayerError::Transport(err.to_string()))?;
+	decode_response(200, &body)
+}
+
+/// body of a JSON-RPC request
+pub(crate) fn request_body(method: &str, params: &Value) -> Value {
+	serde_json::json!({
+		"jsonrpc": "2.0",
+		"method": method,
+		"params": params,
+		"id": "dontcare",
+	})
+}
+
+/// `result` of the JSON-RPC response `body`, answered with the HTTP `status`
+pub(crate) fn decode_response(status: u16, body: &str) -> Result<Value, RelayerError> {
+	#[derive(Debug, Deserialize)]
+	struct ResponseFromRpc {
+		#[serde(default)]
+		result: Option<Value>,
+		#[serde(default)]
+		error: Option<RpcError>,
+	}
+
+	// nodes answer some JSON-RPC errors with an HTTP error status, e.g. 408 on timeouts
+	if !(200..300).contains(&status) {
+		return Err(match serde_json::from_str::<ResponseFromRpc>(body) {
+			Ok(ResponseFromRpc { error: Some(err), .. }) => err.into(),
+			_ => RelayerError::HttpStatus { status, body: body.to_owned() },
+		});
+	}
+	match serde_json::from_str::<ResponseFromRpc>(body)? {
+		ResponseFromRpc { error: Some(err), .. } => Err(err.into()),
+		ResponseFromRpc { result: Some(result), .. } => Ok(result),
+		ResponseFromRpc { .. } => Err(RelayerError::Decode("response without result".to_owned())),
+	}
+}
+
+/// JSON-RPC transport to a NEAR node, on top of which `NearRpc` is implemented
+pub trait JsonRpc {
+	/// submits a JSON-RPC request and returns its `result`
+	fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError>;
+
+	/// hints that `method` is about to be called with `params`, so that the request may be sent
+	/// ahead of time; nothing by default
+	fn prefetch(&self, _method: &str, _params: Value) {}
+}
+
+impl JsonRpc for BlockchainConnector {
+	fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+		let outcome = self.call_endpoints(method, &params);
+		if let Some(recording_dir) = &self.recording_dir {
+			let recorded_outcome = match &outcome {
+				Ok(result) => Some(RecordedOutcome::Result(result.clone())),
+				Err(RelayerError::JsonRpc(err)) => Some(RecordedOutcome::Error((**err).clone())),
+				Err(_) => None,
+			};
+			if let Some(outcome) = recorded_outcome {
+				Recording { method: method.to_owned(), params, outcome }.save(recording_dir)?;
+			}
+		}
+		outcome
+	}
+}
+
+impl<C: JsonRpc> NearRpc for C {
+	fn next_light_client_block(
+		&self,
+		last_known_hash: Base58CryptoHash,
+	) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		let params = next_light_client_block_params(last_known_hash);
+		decode_next_light_client_block(self.call("next_light_client_block", params)?)
+	}
+
+	fn block(&self, block_reference: BlockReference) -> Result<RpcBlockResponse, RelayerError> {
+		Ok(serde_json::from_value(self.call("block", block_reference.to_params())?)?)
+	}
+
+	fn chunk(&self, chunk_id: Base58CryptoHash) -> Result<RpcChunkResponse, RelayerError> {
+		Ok(serde_json::from_value(self.call("chunk", chunk_params(chunk_id))?)?)
+	}
+
+	fn light_client_proof(
+		&self,
+		light_client_head: Base58CryptoHash,
+		request: &ProofRequest,
+	) -> Result<RpcLightClientExecutionProofResponse, RelayerError> {
+		let params = light_client_proof_params(light_client_head, request)?;
+		Ok(serde_json::from_value(self.call("EXPERIMENTAL_light_client_proof", params)?)?)
+	}
+
+	fn view_state(
+		&self,
+		block_hash: Base58CryptoHash,
+		account_id: &str,
+		key_prefix: &[u8],
+	) -> Result<RpcViewStateResponse, RelayerError> {
+		let params = view_state_params(block_hash, account_id, key_prefix);
+		Ok(serde_json::from_value(self.call("query", params)?)?)
+	}
+
+	fn prefetch_light_client_proofs(
+		&self,
+		light_client_head: Base58CryptoHash,
+		requests: &[&ProofRequest],
+	) {
+		for request in requests {
+			if let Ok(params) = light_client_proof_params(light_client_head, request) {
+				self.prefetch("EXPERIMENTAL_light_client_proof", params);
+			}
+		}
+	}
+}
+
+pub(crate) fn next_light_client_block_params(last_known_hash: Base58CryptoHash) -> Value {
+	serde_json::json!([String::from(&last_known_hash)])
+}
 
-		let url = format!("{}/", self.network.get_base_url());
-		let params = ureq::json!({ "type": "transaction" , "transaction_hash": tx_hash, "sender_id": sender_id, "light_client_head": light_client_head});
-		let body = ureq::post(&url)
-			.send_json(ureq::json!({
-				"jsonrpc": "2.0",
-				"method": "EXPERIMENTAL_light_client_proof",
-				"params": params,
-				"id": "dontcare",
-			}))
-			.map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?; // TODO: improve error message
-		Ok(body.into_json::<Response>()?.result.into())
+pub(crate) fn decode_next_light_client_block(
+	result: Value,
+) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+	// nodes answer with an empty object when `last_known_hash` is their latest final block
+	if matches!(result.as_object(), Some(result) if result.is_empty()) {
+		return Ok(None);
 	}
+	Ok(Some(serde_json::from_value(result)?))
+}
+
+pub(crate) fn chunk_params(chunk_id: Base58CryptoHash) -> Value {
+	serde_json::json!({ "chunk_id": chunk_id })
+}
+
+pub(crate) fn light_client_proof_params(
+	light_client_head: Base58CryptoHash,
+	request: &ProofRequest,
+) -> Result<Value, RelayerError> {
+	let mut params = serde_json::to_value(request)?;
+	params["light_client_head"] = serde_json::to_value(light_client_head)?;
+	Ok(params)
+}
+
+pub(crate) fn view_state_params(
+	block_hash: Base58CryptoHash,
+	account_id: &str,
+	key_prefix: &[u8],
+) -> Value {
+	serde_json::json!({
+		"request_type": "view_state",
+		"block_id": block_hash,
+		"account_id": account_id,
+		"prefix_base64": base64::encode(key_prefix),
+		"include_proof": true,
+	})
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::{
+		mock_rpc::MockRpcServer,
+		test_utils::{PROOF, TX_HASH},
+	};
 
 	#[test]
 	fn test_get_base_url() {
 		for (network, expected_url) in [
 			(NearNetwork::Testnet, "https://rpc.testnet.near.org"),
 			(NearNetwork::Mainnet, "https://rpc.mainnet.near.org"),
+			(NearNetwork::Localnet, "http://127.0.0.1:3030"),
+			(
+				NearNetwork::Custom {
+					url: "https://archival-rpc.testnet.near.org".to_owned(),
+					chain_id: "testnet".to_owned(),
+				},
+				"https://archival-rpc.testnet.near.org",
+			),
 		] {
 			assert_eq!(expected_url.to_owned(), network.get_base_url());
 		}
 	}
 
+	const CHECKPOINT: &str = include_str!("../../test-fixtures/light_client_block_86441383.json");
+
+	fn hash(encoded: &str) -> Base58CryptoHash {
+		serde_json::from_value(serde_json::json!(encoded)).unwrap()
+	}
+
+	fn mock_rpc_server() -> MockRpcServer {
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond(
+				"block",
+				serde_json::json!({ "finality": "final" }),
+				serde_json::json!({
+					"header": {
+						"height": 86441384,
+						"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+					},
+					"chunks": [],
+				}),
+			)
+			.respond(
+				"block",
+				serde_json::json!({ "block_id": 86697768 }),
+				serde_json::json!({
+					"header": {
+						"height": 86697768,
+						"prev_hash": "3SZGvB3WTta5eMZuKqwmNst73jpAj9x77gc7BohTmiht",
+					},
+					"chunks": [
+						{
+							"chunk_hash": "Cx7camxqaUQMLBHmWUrVvbf8JCqq78vCTi4XgnX8gdDX",
+							"height_included": 86697768,
+							"balance_burnt": "0",
+						},
+						{
+							"chunk_hash": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8",
+							"height_included": 86697768,
+							"balance_burnt": "242839501800800000000",
+						},
+					],
+				}),
+			)
+			.respond(
+				"chunk",
+				serde_json::json!({ "chunk_id": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8" }),
+				serde_json::json!({
+					"transactions": [{
+						"hash": TX_HASH,
+						"signer_id": "relay.aurora",
+						"receiver_id": "aurora",
+					}],
+					"receipts": [],
+				}),
+			)
+			.respond_with_recording(
+				"next_light_client_block",
+				serde_json::json!(["FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"]),
+				CHECKPOINT,
+			)
+			.respond_with_recording(
+				"EXPERIMENTAL_light_client_proof",
+				serde_json::json!({
+					"type": "transaction",
+					"transaction_hash": TX_HASH,
+					"sender_id": "relay.aurora",
+					"light_client_head": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+				}),
+				PROOF,
+			);
+		server
+	}
+
 	#[test]
 	fn test_get_latest_finalized_block() {
-		let blockchain_connector = BlockchainConnector::new(NearNetwork::Testnet);
-		blockchain_connector
+		let server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::with_rpc_url(server.url());
+		let (block_hash, block_height) = blockchain_connector
 			.get_almost_latest_finalized_block_hash_and_height()
 			.unwrap();
+
+		assert_eq!(block_hash, hash("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"));
+		assert_eq!(block_height, 86441384);
 	}
 
 	#[test]
 	fn test_get_block_by_number() {
-		let blockchain_connector = BlockchainConnector::new(NearNetwork::Testnet);
-		let (_, block_height) = blockchain_connector
+		let server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::with_rpc_url(server.url());
+
+		assert_eq!(
+			blockchain_connector.get_block_hash_from_block_number(86697768).unwrap(),
+			hash("3SZGvB3WTta5eMZuKqwmNst73jpAj9x77gc7BohTmiht")
+		);
+		// no canned response
+		assert!(blockchain_connector.get_block_hash_from_block_number(86697769).is_err());
+	}
+
+	#[test]
+	fn test_get_light_client_block_view() {
+		let server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::with_rpc_url(server.url());
+		let (block_hash, _) = blockchain_connector
 			.get_almost_latest_finalized_block_hash_and_height()
 			.unwrap();
 
-		blockchain_connector.get_block_hash_from_block_number(block_height).unwrap();
+		let block_view = blockchain_connector.get_light_client_block_view(block_hash).unwrap();
+		assert_eq!(block_view.inner_lite.height, 86441383);
+		assert!(block_view.next_bps.is_some());
 	}
 
 	#[test]
-	fn test_get_light_client_block_view() {
-		let blockchain_connector = BlockchainConnector::new(NearNetwork::Testnet);
-		let (block_hash, _) = blockchain_connector
+	fn test_get_light_client_proof_transaction() {
+		let server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::with_rpc_url(server.url());
+
+		let chunk_ids = blockchain_connector.find_chunk_ids_with_burned_gas(86697768).unwrap();
+		assert_eq!(chunk_ids, vec![hash("3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8")]);
+
+		let tx_info = blockchain_connector.get_transaction_ids_in_chunk(chunk_ids[0]).unwrap();
+		assert_eq!(tx_info, vec![(hash(TX_HASH), "relay.aurora".to_owned())]);
+
+		let (tx_hash, sender_id) = tx_info[0].clone();
+		let proof = blockchain_connector
+			.get_light_client_proof_transaction(
+				hash("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr"),
+				tx_hash,
+				sender_id,
+			)
+			.unwrap();
+		assert_eq!(proof.block_header_lite.inner_lite.height, 86697768);
+		assert_eq!(proof.outcome_proof.id.to_string(), TX_HASH);
+	}
+
+	/// policy retrying right away, so that the tests don't wait
+	fn immediate_retries(max_retries: u32) -> RetryPolicy {
+		RetryPolicy { max_retries, initial_backoff_ms: 0, max_backoff_ms: 0 }
+	}
+
+	/// URL on which nothing listens
+	fn unreachable_url() -> String {
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		format!("http://{}", listener.local_addr().unwrap())
+	}
+
+	#[test]
+	fn test_retry_transient_errors() {
+		let server = mock_rpc_server();
+		let endpoints = vec![RpcEndpoint::new(server.url())];
+
+		server.fail_next(2, 502);
+		let blockchain_connector =
+			BlockchainConnector::from_endpoints(endpoints.clone(), RetryPolicy::no_retry());
+		assert!(matches!(
+			blockchain_connector.get_almost_latest_finalized_block_hash_and_height(),
+			Err(RelayerError::HttpStatus { status: 502, .. })
+		));
+
+		let blockchain_connector =
+			BlockchainConnector::from_endpoints(endpoints.clone(), immediate_retries(1));
+		blockchain_connector
+			.get_almost_latest_finalized_block_hash_and_height()
+			.unwrap();
+
+		server.fail_next(3, 503);
+		let blockchain_connector =
+			BlockchainConnector::from_endpoints(endpoints, immediate_retries(2));
+		assert!(matches!(
+			blockchain_connector.get_almost_latest_finalized_block_hash_and_height(),
+			Err(RelayerError::HttpStatus { status: 503, .. })
+		));
+		blockchain_connector
+			.get_almost_latest_finalized_block_hash_and_height()
+			.unwrap();
+	}
+
+	#[test]
+	fn test_failover() {
+		let server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(unreachable_url()), RpcEndpoint::new(server.url())],
+			RetryPolicy::no_retry(),
+		);
+		blockchain_connector
 			.get_almost_latest_finalized_block_hash_and_height()
 			.unwrap();
 
-		blockchain_connector.get_light_client_block_view(block_hash).unwrap();
+		let blockchain_connector = BlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(unreachable_url())],
+			RetryPolicy::no_retry(),
+		);
+		let err = blockchain_connector.get_almost_latest_finalized_block_hash_and_height();
+		assert!(matches!(err, Err(RelayerError::Transport(_))));
+	}
+
+	#[test]
+	fn test_failover_to_archival_node() {
+		let unknown_block = serde_json::json!({
+			"name": "HANDLER_ERROR",
+			"cause": { "name": "UNKNOWN_BLOCK", "info": {} },
+			"code": -32000,
+			"message": "Server error",
+			"data": "DB Not Found Error: BLOCK HEIGHT: 86697768",
+		});
+		let params = serde_json::json!({ "block_id": 86697768 });
+		let server = MockRpcServer::start().unwrap();
+		server.respond_with_error("block", params.clone(), unknown_block.clone());
+		let archival_server = mock_rpc_server();
+
+		// the error is not transient: every endpoint is tried once, and there is no retry
+		let policy =
+			RetryPolicy { max_retries: 10, initial_backoff_ms: 60_000, max_backoff_ms: 60_000 };
+		let blockchain_connector = BlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(server.url()), RpcEndpoint::new(server.url())],
+			policy.clone(),
+		);
+		match blockchain_connector.get_block_hash_from_block_number(86697768) {
+			Err(RelayerError::JsonRpc(err)) => assert_eq!(err.cause_name(), Some("UNKNOWN_BLOCK")),
+			result => panic!("unexpected result {:?}", result),
+		}
+
+		let blockchain_connector = BlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(server.url()), RpcEndpoint::new(archival_server.url())],
+			policy,
+		);
+		blockchain_connector.get_block_hash_from_block_number(86697768).unwrap();
+	}
+
+	#[test]
+	fn test_errors_are_not_retried() {
+		let server = mock_rpc_server();
+		server.respond(
+			"chunk",
+			serde_json::json!({ "chunk_id": "Cx7camxqaUQMLBHmWUrVvbf8JCqq78vCTi4XgnX8gdDX" }),
+			serde_json::json!({}),
+		);
+		let other_server = mock_rpc_server();
+		let blockchain_connector = BlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(server.url()), RpcEndpoint::new(other_server.url())],
+			RetryPolicy { max_retries: 10, initial_backoff_ms: 60_000, max_backoff_ms: 60_000 },
+		);
+
+		// decoding error
+		assert!(matches!(
+			blockchain_connector
+				.get_transaction_ids_in_chunk(hash("Cx7camxqaUQMLBHmWUrVvbf8JCqq78vCTi4XgnX8gdDX")),
+			Err(RelayerError::Decode(_))
+		));
+		// JSON-RPC error without a cause
+		match blockchain_connector.get_block_hash_from_block_number(86697769) {
+			Err(RelayerError::JsonRpc(err)) => assert_eq!(err.cause_name(), None),
+			result => panic!("unexpected result {:?}", result),
+		}
 	}
 }
@@ -7,6 +7,7 @@ use near_crypto::Signature;
 use near_primitives::{
 	hash::CryptoHash,
 	merkle::MerklePathItem,
+	serialize::u64_dec_format,
 	types::{AccountId, Balance, Gas},
 	views::{
 		validator_stake_view::ValidatorStakeView,
@@ -30,6 +31,10 @@ pub struct BlockHeaderInnerLiteView {
 	pub timestamp: u64,
 	pub next_bp_hash: CryptoHash,
 	pub block_merkle_root: CryptoHash,
+	/// same as `timestamp`, not part of the hash of the block
+	#[borsh_skip]
+	#[serde(with = "u64_dec_format")]
+	pub timestamp_nanosec: u64,
 }
 
 pub struct LightClientBlockLiteView {
@@ -85,6 +90,7 @@ impl From<NearBlockHeaderInnerLiteView> for BlockHeaderInnerLiteView {
 			timestamp: near.timestamp,
 			next_bp_hash: near.next_bp_hash,
 			block_merkle_root: near.block_merkle_root,
+			timestamp_nanosec: near.timestamp_nanosec,
 		}
 	}
 }
@@ -100,7 +106,7 @@ impl From<BlockHeaderInnerLiteView> for NearBlockHeaderInnerLiteView {
 			timestamp: internal.timestamp,
 			next_bp_hash: internal.next_bp_hash,
 			block_merkle_root: internal.block_merkle_root,
-			timestamp_nanosec: 0,
+			timestamp_nanosec: internal.timestamp_nanosec,
 		}
 	}
 }
@@ -158,6 +164,7 @@ fn current_block_hash(
 	.unwrap()
 }
 
+#[cfg(test)]
 pub(crate) fn next_block_hash(
 	next_block_inner_hash: CryptoHash,
 	current_block_hash: CryptoHash,
@@ -168,24 +175,12 @@ pub(crate) fn next_block_hash(
 		.unwrap()
 }
 
+#[cfg(test)]
 #[derive(Debug, Clone, BorshSerialize)]
 pub enum ApprovalInner {
 	Endorsement(CryptoHash),
 }
 
-pub fn reconstruct_light_client_block_view_fields(
-	block_view: &LightClientBlockView,
-) -> (CryptoHash, CryptoHash, Vec<u8>) {
-	let current_block_hash = block_view.current_block_hash();
-	let next_block_hash = next_block_hash(block_view.next_block_inner_hash, current_block_hash);
-	let approval_message = [
-		ApprovalInner::Endorsement(next_block_hash).try_to_vec().unwrap(),
-		(block_view.inner_lite.height + 2).to_le().try_to_vec().unwrap(),
-	]
-	.concat();
-	(current_block_hash, next_block_hash, approval_message)
-}
-
 #[derive(Debug, Deserialize)]
 pub struct ClientProofResponse {
 	block_header_lite: BlockHeaderInnerLiteView,
@@ -527,6 +522,18 @@ mod tests {
 		assert_eq!(parsed_response.outcome_proof.proof.len(), 3);
 	}
 
+	#[test]
+	fn test_inner_lite_keeps_timestamp_nanosec() {
+		let inner_lite = get_block_header_lite(CLIENT_PROOF_RESPONSE)
+			.unwrap()
+			.get_block_header_inner_lite_view()
+			.clone();
+		assert_eq!(inner_lite.timestamp_nanosec, 1649062589965425850);
+		let near = NearBlockHeaderInnerLiteView::from(inner_lite);
+		assert_eq!(near.timestamp_nanosec, 1649062589965425850);
+		assert_eq!(BlockHeaderInnerLiteView::from(near).timestamp_nanosec, 1649062589965425850);
+	}
+
 	#[test]
 	fn calculate_hash_light_client_block_lite_view() {
 		let block_header_lite = get_block_header_lite(CLIENT_PROOF_RESPONSE).unwrap();
@@ -0,0 +1,400 @@
+//! Relayer configuration file
+//!
+//! ```toml
+//! network = "testnet"
+//!
+//! [[endpoints]]
+//! url = "https://rpc.testnet.near.org"
+//! timeout_secs = 10
+//!
+//! [[endpoints]]
+//! url = "https://archival-rpc.testnet.near.org"
+//! archival = true
+//! headers = { x-api-key = "..." }
+//!
+//! [retry]
+//! max_retries = 5
+//! initial_backoff_ms = 250
+//! max_backoff_ms = 10000
+//!
+//! [follow]
+//! checkpoint_hash = "..."
+//! store_path = "relayer-store"
+//! epoch_retention = 8
+//! poll_interval_secs = 5
+//! # "every_block" (default), "epoch_boundary_only", "on_demand" or { interval = 100 }
+//! policy = "epoch_boundary_only"
+//! # proofs of their transactions and receipts are queued and submitted to the sink
+//! accounts = ["locker.bridge.testnet"]
+//! # "log" (default), "stdout" or "jsonl"
+//! sink = { type = "jsonl", path = "headers.jsonl" }
+//! # Prometheus metrics are served on http://<metrics_addr>/metrics
+//! metrics_addr = "127.0.0.1:9100"
+//! # headers and proofs are served to other services on http://<service_addr>, see `service`
+//! service_addr = "127.0.0.1:8080"
+//!
+//! # the blocks are cross-checked with the ones served by every witness
+//! [[follow.witnesses]]
+//! url = "https://near-testnet.example.org"
+//!
+//! # the responses are fetched ahead of the relayer, see `prefetch`; not prefetched when missing
+//! [follow.prefetch]
+//! parallelism = 8
+//! depth = 16
+//! ```
+//!
+//! `network` is one of `mainnet`, `testnet`, `localnet` or
+//! `{ custom = { url = "...", chain_id = "..." } }`.
+
+use std::{
+	fs, io,
+	net::SocketAddr,
+	path::{Path, PathBuf},
+};
+
+use near_sdk::json_types::Base58CryptoHash;
+use serde::Deserialize;
+
+use crate::{
+	async_connector::AsyncBlockchainConnector,
+	blockchain_connector::{BlockchainConnector, NearNetwork, RpcEndpoint},
+	policy::RelayPolicy,
+	prefetch::PrefetchConfig,
+	retry::RetryPolicy,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RelayerConfig {
+	pub network: NearNetwork,
+	/// endpoints in order of preference; the public RPC of the network when empty
+	#[serde(default)]
+	pub endpoints: Vec<RpcEndpoint>,
+	#[serde(default)]
+	pub retry: RetryPolicy,
+	/// settings of the relayer daemon
+	#[serde(default)]
+	pub follow: Option<FollowConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FollowConfig {
+	/// the trusted checkpoint is the light client block following this block
+	pub checkpoint_hash: Base58CryptoHash,
+	/// directory of the store, where the relayer state is saved after every submission and
+	/// resumed from
+	pub store_path: PathBuf,
+	/// epochs whose block producers and submissions are kept in the store
+	#[serde(default = "default_epoch_retention")]
+	pub epoch_retention: usize,
+	#[serde(default = "default_poll_interval_secs")]
+	pub poll_interval_secs: u64,
+	#[serde(default)]
+	pub policy: RelayPolicy,
+	/// accounts whose activity is scanned, see `scanner`
+	#[serde(default)]
+	pub accounts: Vec<String>,
+	#[serde(default)]
+	pub sink: SinkConfig,
+	/// where the metrics are served, see `metrics`; not served when missing
+	#[serde(default)]
+	pub metrics_addr: Option<SocketAddr>,
+	/// where the proof service is served, see `service`; not served when missing
+	#[serde(default)]
+	pub service_addr: Option<SocketAddr>,
+	/// endpoints of other nodes the blocks are cross-checked with, see `cross_check`
+	#[serde(default)]
+	pub witnesses: Vec<RpcEndpoint>,
+	/// how the responses are prefetched; they are not when missing
+	#[serde(default)]
+	pub prefetch: Option<PrefetchConfig>,
+}
+
+/// Destination of the headers and the proofs, see `sink`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+	#[default]
+	Log,
+	Stdout,
+	Jsonl {
+		path: PathBuf,
+	},
+}
+
+fn default_poll_interval_secs() -> u64 {
+	5
+}
+
+fn default_epoch_retention() -> usize {
+	8
+}
+
+impl RelayerConfig {
+	pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+		Self::from_toml(&fs::read_to_string(path)?)
+	}
+
+	pub fn from_toml(config: &str) -> io::Result<Self> {
+		toml::from_str(config).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+	}
+
+	/// configured endpoints, or the public RPC of the network when there is none
+	pub fn endpoints(&self) -> Vec<RpcEndpoint> {
+		if self.endpoints.is_empty() {
+			vec![RpcEndpoint::new(self.network.get_base_url())]
+		} else {
+			self.endpoints.clone()
+		}
+	}
+
+	/// connects to every endpoint
+	pub fn connector(&self) -> BlockchainConnector {
+		BlockchainConnector::from_endpoints(self.endpoints(), self.retry.clone())
+	}
+
+	/// connects asynchronously to every endpoint
+	pub fn async_connector(&self) -> AsyncBlockchainConnector {
+		AsyncBlockchainConnector::from_endpoints(self.endpoints(), self.retry.clone())
+	}
+
+	/// connects to the archival endpoints, needed to fetch blocks of old epochs
+	pub fn archival_connector(&self) -> Option<BlockchainConnector> {
+		let endpoints = self
+			.endpoints
+			.iter()
+			.filter(|endpoint| endpoint.archival)
+			.cloned()
+			.collect::<Vec<_>>();
+		if endpoints.is_empty() {
+			return None;
+		}
+		Some(BlockchainConnector::from_endpoints(endpoints, self.retry.clone()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::{blockchain_connector::NearRpc, mock_rpc::MockRpcServer};
+
+	#[test]
+	fn test_parse_config() {
+		let config = RelayerConfig::from_toml(
+			r#"
+			network = "testnet"
+
+			[[endpoints]]
+			url = "https://rpc.testnet.near.org"
+			timeout_secs = 10
+
+			[[endpoints]]
+			url = "https://archival-rpc.testnet.near.org"
+			archival = true
+			headers = { x-api-key = "secret" }
+			"#,
+		)
+		.unwrap();
+
+		assert_eq!(config.network, NearNetwork::Testnet);
+		assert_eq!(
+			config.endpoints,
+			vec![
+				RpcEndpoint {
+					timeout_secs: Some(10),
+					..RpcEndpoint::new("https://rpc.testnet.near.org")
+				},
+				RpcEndpoint {
+					headers: [("x-api-key".to_owned(), "secret".to_owned())].into_iter().collect(),
+					archival: true,
+					..RpcEndpoint::new("https://archival-rpc.testnet.near.org")
+				},
+			]
+		);
+		assert_eq!(config.retry, RetryPolicy::default());
+		assert_eq!(config.follow, None);
+		assert_eq!(config.connector().endpoints(), &config.endpoints[..]);
+		assert_eq!(config.archival_connector().unwrap().endpoints(), &config.endpoints[1..]);
+	}
+
+	#[test]
+	fn test_parse_network() {
+		let config = RelayerConfig::from_toml(
+			r#"
+			network = "localnet"
+
+			[retry]
+			max_retries = 1
+
+			[follow]
+			checkpoint_hash = "kobvwf6idnjzf1zUCdU8igL9G9ZUZyexkqVXFSpUVTK"
+			store_path = "/var/lib/near-lite-relayer"
+			"#,
+		)
+		.unwrap();
+		assert_eq!(config.retry, RetryPolicy { max_retries: 1, ..Default::default() });
+		assert_eq!(
+			config.follow,
+			Some(FollowConfig {
+				checkpoint_hash: Base58CryptoHash::try_from(
+					"kobvwf6idnjzf1zUCdU8igL9G9ZUZyexkqVXFSpUVTK"
+				)
+				.unwrap(),
+				store_path: "/var/lib/near-lite-relayer".into(),
+				epoch_retention: 8,
+				poll_interval_secs: 5,
+				policy: RelayPolicy::EveryBlock,
+				accounts: Vec::new(),
+				sink: SinkConfig::Log,
+				metrics_addr: None,
+				service_addr: None,
+				witnesses: Vec::new(),
+				prefetch: None,
+			})
+		);
+		assert_eq!(config.endpoints(), vec![RpcEndpoint::new("http://127.0.0.1:3030")]);
+		assert!(config.archival_connector().is_none());
+
+		let config = RelayerConfig::from_toml(
+			r#"network = { custom = { url = "http://10.0.0.1:3030", chain_id = "private" } }"#,
+		)
+		.unwrap();
+		assert_eq!(config.network.chain_id(), "private");
+		assert_eq!(config.endpoints(), vec![RpcEndpoint::new("http://10.0.0.1:3030")]);
+
+		assert!(RelayerConfig::from_toml(r#"network = "betanet""#).is_err());
+		assert!(RelayerConfig::from_toml("").is_err());
+	}
+
+	/// parses a `[follow]` section with the given extra settings
+	fn follow_config(settings: &str) -> io::Result<FollowConfig> {
+		let config = RelayerConfig::from_toml(&format!(
+			r#"
+			network = "testnet"
+
+			[follow]
+			checkpoint_hash = "kobvwf6idnjzf1zUCdU8igL9G9ZUZyexkqVXFSpUVTK"
+			store_path = "store"
+			{}
+			"#,
+			settings
+		))?;
+		Ok(config.follow.unwrap())
+	}
+
+	#[test]
+	fn test_parse_sink() {
+		let sink =
+			|sink: &str| Ok::<_, io::Error>(follow_config(&format!("sink = {}", sink))?.sink);
+
+		assert_eq!(sink(r#"{ type = "stdout" }"#).unwrap(), SinkConfig::Stdout);
+		assert_eq!(
+			sink(r#"{ type = "jsonl", path = "headers.jsonl" }"#).unwrap(),
+			SinkConfig::Jsonl { path: "headers.jsonl".into() }
+		);
+		assert!(sink(r#"{ type = "jsonl" }"#).is_err());
+		assert!(sink(r#"{ type = "pallet" }"#).is_err());
+	}
+
+	#[test]
+	fn test_parse_policy() {
+		let policy = |policy: &str| {
+			Ok::<_, io::Error>(follow_config(&format!("policy = {}", policy))?.policy)
+		};
+
+		assert_eq!(policy(r#""epoch_boundary_only""#).unwrap(), RelayPolicy::EpochBoundaryOnly);
+		assert_eq!(policy(r#""on_demand""#).unwrap(), RelayPolicy::OnDemand);
+		assert_eq!(policy("{ interval = 100 }").unwrap(), RelayPolicy::Interval(100));
+		assert!(policy(r#""interval""#).is_err());
+	}
+
+	#[test]
+	fn test_parse_accounts() {
+		let follow = follow_config(r#"accounts = ["locker.bridge.testnet", "aurora"]"#).unwrap();
+		assert_eq!(follow.accounts, vec!["locker.bridge.testnet", "aurora"]);
+	}
+
+	#[test]
+	fn test_parse_metrics_addr() {
+		let follow = follow_config(r#"metrics_addr = "127.0.0.1:9100""#).unwrap();
+		assert_eq!(follow.metrics_addr, Some("127.0.0.1:9100".parse().unwrap()));
+		assert!(follow_config(r#"metrics_addr = "localhost""#).is_err());
+	}
+
+	#[test]
+	fn test_parse_service_addr() {
+		let follow = follow_config(r#"service_addr = "0.0.0.0:8080""#).unwrap();
+		assert_eq!(follow.service_addr, Some("0.0.0.0:8080".parse().unwrap()));
+		assert_eq!(follow_config("").unwrap().service_addr, None);
+	}
+
+	#[test]
+	fn test_parse_witnesses() {
+		let follow = follow_config(
+			r#"
+			[[follow.witnesses]]
+			url = "https://a.example.org"
+
+			[[follow.witnesses]]
+			url = "https://b.example.org"
+			timeout_secs = 3
+			"#,
+		)
+		.unwrap();
+		assert_eq!(
+			follow.witnesses,
+			vec![
+				RpcEndpoint::new("https://a.example.org"),
+				RpcEndpoint { timeout_secs: Some(3), ..RpcEndpoint::new("https://b.example.org") },
+			]
+		);
+		assert!(follow_config("").unwrap().witnesses.is_empty());
+	}
+
+	#[test]
+	fn test_parse_prefetch() {
+		let follow = follow_config(
+			r#"
+			[follow.prefetch]
+			depth = 64
+			"#,
+		)
+		.unwrap();
+		assert_eq!(follow.prefetch, Some(PrefetchConfig { parallelism: 8, depth: 64 }));
+		assert_eq!(follow_config("").unwrap().prefetch, None);
+	}
+
+	#[test]
+	fn test_endpoint_headers() {
+		let server = MockRpcServer::start().unwrap();
+		server.require_header("x-api-key", "secret").respond(
+			"block",
+			json!({ "finality": "final" }),
+			json!({
+				"header": {
+					"height": 86441384,
+					"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+				},
+				"chunks": [],
+			}),
+		);
+		let mut config = RelayerConfig::from_toml(&format!(
+			r#"
+			network = "testnet"
+
+			[[endpoints]]
+			url = "{}"
+
+			[retry]
+			max_retries = 0
+			"#,
+			server.url()
+		))
+		.unwrap();
+		assert!(config.connector().get_almost_latest_finalized_block_hash_and_height().is_err());
+
+		config.endpoints[0].headers.insert("x-api-key".to_owned(), "secret".to_owned());
+		assert!(config.connector().get_almost_latest_finalized_block_hash_and_height().is_ok());
+	}
+}
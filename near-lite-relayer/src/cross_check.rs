@@ -0,0 +1,361 @@
+//! # Cross-checking of the RPC endpoints
+//!
+//! The relayer relies on a single node, through its `NearRpc`, for `next_light_client_block`. A
+//! malicious or broken node may serve a validly signed block which is not canonical, or withhold
+//! the blocks. A `CrossChecker` asks other nodes, its witnesses, for the block following the same
+//! head, and compares the hashes of the blocks at the same height.
+//!
+//! Two different blocks at the same height which are both valid for the light client can only be
+//! signed by equivocating block producers: they are kept as `Misbehaviour` evidence, and neither
+//! is relayed.
+
+use near_primitives::{hash::CryptoHash, views::LightClientBlockView as NearLightClientBlockView};
+use near_sdk::json_types::Base58CryptoHash;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	blockchain_connector::NearRpc, client_proof::LightClientBlockView, state::LightClientState,
+};
+
+/// Two different blocks following the same head, both valid for the light client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Misbehaviour {
+	/// hash of the head both blocks follow
+	pub head_hash: CryptoHash,
+	/// block served by the RPC of the relayer
+	pub first: NearLightClientBlockView,
+	/// block served by the witness
+	pub second: NearLightClientBlockView,
+	/// name of the witness serving `second`
+	pub witness: String,
+}
+
+impl Misbehaviour {
+	pub fn height(&self) -> u64 {
+		self.first.inner_lite.height
+	}
+
+	/// whether the blocks are different blocks at the same height, both valid against `state`,
+	/// whose head is the one they follow
+	pub fn verify(&self, state: &LightClientState) -> bool {
+		state.head_hash() == self.head_hash &&
+			self.first.inner_lite.height == self.second.inner_lite.height &&
+			block_hash(&self.first) != block_hash(&self.second) &&
+			state.clone().validate_head(&self.first).is_ok() &&
+			state.clone().validate_head(&self.second).is_ok()
+	}
+}
+
+/// What a witness serves after the head, compared to the block served by the RPC of the relayer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WitnessOutcome {
+	/// the same block, or no block either
+	Agrees,
+	/// no block after the head, e.g. as the witness is behind
+	NoBlock,
+	/// a block at another height, which is not comparable
+	OtherHeight(u64),
+	/// a different block, not valid for the light client
+	Invalid { height: u64, reason: String },
+	/// a different block, also valid for the light client
+	Equivocates(Box<Misbehaviour>),
+	/// a valid block at this height, while the RPC of the relayer serves none
+	Withheld(u64),
+	/// the witness could not be reached
+	Unavailable(String),
+}
+
+impl WitnessOutcome {
+	/// kind of the alert raised by the outcome, if any
+	pub fn alert(&self) -> Option<&'static str> {
+		match self {
+			Self::Invalid { .. } => Some("invalid"),
+			Self::Equivocates(_) => Some("equivocation"),
+			Self::Withheld(_) => Some("withheld"),
+			Self::Agrees | Self::NoBlock | Self::OtherHeight(_) | Self::Unavailable(_) => None,
+		}
+	}
+}
+
+/// Outcomes of the witnesses, by name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrossCheckReport {
+	pub outcomes: Vec<(String, WitnessOutcome)>,
+}
+
+impl CrossCheckReport {
+	pub fn misbehaviours(&self) -> impl Iterator<Item = &Misbehaviour> {
+		self.outcomes.iter().filter_map(|(_, outcome)| match outcome {
+			WitnessOutcome::Equivocates(misbehaviour) => Some(&**misbehaviour),
+			_ => None,
+		})
+	}
+
+	/// outcomes raising an alert, with its kind
+	pub fn alerts(&self) -> impl Iterator<Item = (&str, &'static str, &WitnessOutcome)> {
+		self.outcomes.iter().filter_map(|(witness, outcome)| {
+			outcome.alert().map(|alert| (witness.as_str(), alert, outcome))
+		})
+	}
+}
+
+struct Witness {
+	name: String,
+	rpc: Box<dyn NearRpc + Send>,
+}
+
+/// Witnesses the blocks served by the RPC of the relayer are compared to
+#[derive(Default)]
+pub struct CrossChecker {
+	witnesses: Vec<Witness>,
+}
+
+impl CrossChecker {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// adds a witness, e.g. a `BlockchainConnector` to a single endpoint named by its URL
+	pub fn with_witness(
+		mut self,
+		name: impl Into<String>,
+		rpc: impl NearRpc + Send + 'static,
+	) -> Self {
+		self.witnesses.push(Witness { name: name.into(), rpc: Box::new(rpc) });
+		self
+	}
+
+	pub fn witnesses(&self) -> impl Iterator<Item = &str> {
+		self.witnesses.iter().map(|witness| witness.name.as_str())
+	}
+
+	/// Asks every witness for the block following the head of `state`, and compares it to
+	/// `block_view`, the block following it served by the RPC of the relayer, if any. The blocks
+	/// are only validated when they differ.
+	pub fn check(
+		&self,
+		state: &LightClientState,
+		block_view: Option<&NearLightClientBlockView>,
+	) -> CrossCheckReport {
+		let head_hash = state.head_hash();
+		let outcomes = self
+			.witnesses
+			.iter()
+			.map(|witness| {
+				let outcome = match witness
+					.rpc
+					.next_light_client_block(Base58CryptoHash::from(head_hash.0))
+				{
+					Ok(witness_block) => compare(state, block_view, witness_block, &witness.name),
+					Err(err) => WitnessOutcome::Unavailable(err.to_string()),
+				};
+				(witness.name.clone(), outcome)
+			})
+			.collect();
+		CrossCheckReport { outcomes }
+	}
+}
+
+fn compare(
+	state: &LightClientState,
+	block_view: Option<&NearLightClientBlockView>,
+	witness_block: Option<NearLightClientBlockView>,
+	witness: &str,
+) -> WitnessOutcome {
+	// as in `Relayer::step`, a block which is not ahead of the head is no block
+	let witness_block = match witness_block {
+		Some(witness_block) if witness_block.inner_lite.height > state.head_height() =>
+			witness_block,
+		_ if block_view.is_none() => return WitnessOutcome::Agrees,
+		_ => return WitnessOutcome::NoBlock,
+	};
+	let height = witness_block.inner_lite.height;
+	if let Some(block_view) = block_view {
+		if height != block_view.inner_lite.height {
+			return WitnessOutcome::OtherHeight(height);
+		}
+		if block_hash(&witness_block) == block_hash(block_view) {
+			return WitnessOutcome::Agrees;
+		}
+	}
+
+	if let Err(err) = state.clone().validate_head(&witness_block) {
+		return WitnessOutcome::Invalid { height, reason: err.to_string() };
+	}
+	match block_view {
+		Some(block_view) => WitnessOutcome::Equivocates(Box::new(Misbehaviour {
+			head_hash: state.head_hash(),
+			first: block_view.clone(),
+			second: witness_block,
+			witness: witness.to_owned(),
+		})),
+		None => WitnessOutcome::Withheld(height),
+	}
+}
+
+fn block_hash(block_view: &NearLightClientBlockView) -> CryptoHash {
+	LightClientBlockView::from(block_view.clone()).current_block_hash()
+}
+
+#[cfg(test)]
+mod tests {
+	use near_chain_simulator::{ChainSimulator, Tampering, Validator};
+	use near_primitives_wasm::CryptoHash as SimulatorCryptoHash;
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		relayer::{Relayer, Step},
+		replay::{RecordedOutcome, Recording, ReplayConnector},
+		sink::LiteClientSink,
+	};
+
+	fn chain() -> ChainSimulator {
+		let mut chain = ChainSimulator::new(vec![
+			Validator::new("alice.near", 400),
+			Validator::new("bob.near", 300),
+			Validator::new("carol.near", 200),
+		]);
+		chain.produce_blocks(3 * chain.epoch_length());
+		chain
+	}
+
+	fn tampered_block(
+		chain: &ChainSimulator,
+		height: u64,
+		tampering: Tampering,
+	) -> NearLightClientBlockView {
+		chain
+			.tampered_light_client_block(height, &tampering)
+			.unwrap()
+			.try_into()
+			.unwrap()
+	}
+
+	fn block(chain: &ChainSimulator, height: u64) -> NearLightClientBlockView {
+		tampered_block(chain, height, Tampering::default())
+	}
+
+	fn equivocating_block(chain: &ChainSimulator, height: u64) -> NearLightClientBlockView {
+		let inner_rest_hash = SimulatorCryptoHash::hash_bytes(b"equivocation");
+		tampered_block(
+			chain,
+			height,
+			Tampering { inner_rest_hash: Some(inner_rest_hash), ..Default::default() },
+		)
+	}
+
+	/// serves `block_view` as the block following `head_hash`
+	fn rpc(
+		head_hash: CryptoHash,
+		block_view: Option<&NearLightClientBlockView>,
+	) -> ReplayConnector {
+		ReplayConnector::new(vec![Recording {
+			method: "next_light_client_block".to_owned(),
+			params: json!([head_hash.to_string()]),
+			outcome: RecordedOutcome::Result(
+				block_view
+					.map_or(json!({}), |block_view| serde_json::to_value(block_view).unwrap()),
+			),
+		}])
+	}
+
+	/// state at the last block of the first epoch, and the height of the block following it
+	fn checkpoint(chain: &ChainSimulator) -> (LightClientState, u64) {
+		let height = chain.last_block_of_epoch(0);
+		(LightClientState::new_from_checkpoint(block(chain, height)), height + 1)
+	}
+
+	#[test]
+	fn test_cross_check() {
+		let chain = chain();
+		let (state, height) = checkpoint(&chain);
+		let head_hash = state.head_hash();
+		let block_view = block(&chain, height);
+		// a different block, lacking the approval of the largest block producer
+		let forged = Tampering {
+			inner_rest_hash: Some(SimulatorCryptoHash::hash_bytes(b"forged")),
+			forged_approvals: [0].into(),
+			..Default::default()
+		};
+
+		let checker = CrossChecker::new()
+			.with_witness("agreeing", rpc(head_hash, Some(&block_view)))
+			.with_witness("behind", rpc(head_hash, None))
+			.with_witness("ahead", rpc(head_hash, Some(&block(&chain, height + 1))))
+			.with_witness("lying", rpc(head_hash, Some(&tampered_block(&chain, height, forged))))
+			.with_witness("equivocating", rpc(head_hash, Some(&equivocating_block(&chain, height))))
+			.with_witness("unreachable", ReplayConnector::default());
+		assert_eq!(
+			checker.witnesses().collect::<Vec<_>>(),
+			vec!["agreeing", "behind", "ahead", "lying", "equivocating", "unreachable"]
+		);
+
+		let report = checker.check(&state, Some(&block_view));
+		let outcomes = &report.outcomes;
+		assert_eq!(outcomes[0].1, WitnessOutcome::Agrees);
+		assert_eq!(outcomes[1].1, WitnessOutcome::NoBlock);
+		assert_eq!(outcomes[2].1, WitnessOutcome::OtherHeight(height + 1));
+		assert!(
+			matches!(&outcomes[3].1, WitnessOutcome::Invalid { height: h, .. } if *h == height)
+		);
+		assert!(matches!(outcomes[5].1, WitnessOutcome::Unavailable(_)));
+		assert_eq!(
+			report.alerts().map(|(witness, alert, _)| (witness, alert)).collect::<Vec<_>>(),
+			vec![("lying", "invalid"), ("equivocating", "equivocation")]
+		);
+
+		let misbehaviours = report.misbehaviours().collect::<Vec<_>>();
+		assert_eq!(misbehaviours.len(), 1);
+		let misbehaviour = misbehaviours[0];
+		assert_eq!(misbehaviour.height(), height);
+		assert_eq!(misbehaviour.head_hash, head_hash);
+		assert_eq!(misbehaviour.first, block_view);
+		assert_eq!(misbehaviour.witness, "equivocating");
+		assert!(misbehaviour.verify(&state));
+		// the same block twice is no evidence
+		let mut same_blocks = misbehaviour.clone();
+		same_blocks.second = same_blocks.first.clone();
+		assert!(!same_blocks.verify(&state));
+		// nor evidence against another head
+		let mut later_state = state.clone();
+		later_state.validate_head(&block_view).unwrap();
+		assert!(!misbehaviour.verify(&later_state));
+
+		// the RPC of the relayer serves no block
+		let report = checker.check(&state, None);
+		let outcomes = &report.outcomes;
+		assert_eq!(outcomes[0].1, WitnessOutcome::Withheld(height));
+		assert_eq!(outcomes[1].1, WitnessOutcome::Agrees);
+		assert_eq!(outcomes[2].1, WitnessOutcome::Withheld(height + 1));
+		assert_eq!(report.misbehaviours().count(), 0);
+	}
+
+	#[test]
+	fn test_relayer_halts_on_equivocation() {
+		let chain = chain();
+		let (state, height) = checkpoint(&chain);
+		let head_hash = state.head_hash();
+		let block_view = block(&chain, height);
+		let new_relayer = |witness: ReplayConnector| {
+			Relayer::new(
+				rpc(head_hash, Some(&block_view)),
+				LiteClientSink::new(state.head().clone()).unwrap(),
+				state.clone(),
+			)
+			.with_cross_checker(CrossChecker::new().with_witness("witness", witness))
+		};
+
+		let mut relayer = new_relayer(rpc(head_hash, Some(&block_view)));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(height));
+		assert!(relayer.misbehaviours().is_empty());
+
+		let mut relayer = new_relayer(rpc(head_hash, Some(&equivocating_block(&chain, height))));
+		assert_eq!(relayer.step().unwrap(), Step::Misbehaviour(height));
+		assert_eq!(relayer.step().unwrap(), Step::Misbehaviour(height));
+		assert!(relayer.sink().headers().is_empty());
+		assert_eq!(relayer.state().head_height(), height - 1);
+		assert_eq!(relayer.misbehaviours().len(), 1);
+		assert!(relayer.misbehaviours()[0].verify(relayer.state()));
+	}
+}
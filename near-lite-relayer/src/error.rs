@@ -0,0 +1,180 @@
+use std::{fmt, io};
+
+use near_primitives_wasm::ConversionError;
+use serde::{Deserialize, Serialize};
+
+/// Causes of JSON-RPC errors after which the same request may succeed later on
+const TRANSIENT_CAUSES: [&str; 3] = ["TIMEOUT_ERROR", "NO_SYNCED_BLOCKS", "NOT_SYNCED_YET"];
+
+/// Causes of JSON-RPC errors after which another node, e.g. an archival one, may still serve the
+/// request
+const MISSING_DATA_CAUSES: [&str; 3] =
+	["UNKNOWN_BLOCK", "UNKNOWN_CHUNK", "GARBAGE_COLLECTED_BLOCK"];
+
+#[derive(Debug)]
+pub enum RelayerError {
+	/// the endpoint could not be reached, or the connection broke
+	Transport(String),
+	/// the endpoint answered with an HTTP error status and no JSON-RPC error
+	HttpStatus { status: u16, body: String },
+	/// the endpoint answered with a JSON-RPC error object
+	JsonRpc(Box<RpcError>),
+	/// the response is not the JSON expected from the method
+	Decode(String),
+	/// a header is not valid for the light client
+	InvalidHeader { reason: HeaderRejection, message: String },
+	/// a header or a proof could not be submitted to its destination
+	Sink(String),
+	/// the relayer store, or a file sink, could not be read or written
+	Io(io::Error),
+}
+
+/// Why the light client rejects a header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeaderRejection {
+	/// the block producers of the epoch of the header are not known
+	UnknownEpoch,
+	/// the header doesn't follow the head, or is not final
+	InvalidBlock,
+	/// an approval is not signed by its block producer
+	InvalidSignature,
+	Other,
+}
+
+impl HeaderRejection {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::UnknownEpoch => "unknown_epoch",
+			Self::InvalidBlock => "invalid_block",
+			Self::InvalidSignature => "invalid_signature",
+			Self::Other => "other",
+		}
+	}
+}
+
+impl fmt::Display for HeaderRejection {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// JSON-RPC error object, with the structured `name` and `cause` of NEAR nodes, e.g.
+/// `{ "name": "HANDLER_ERROR", "cause": { "name": "UNKNOWN_BLOCK", "info": {...} }, ... }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+	pub code: i64,
+	pub message: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub data: Option<serde_json::Value>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub cause: Option<RpcErrorCause>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcErrorCause {
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub info: Option<serde_json::Value>,
+}
+
+impl RpcError {
+	pub fn cause_name(&self) -> Option<&str> {
+		self.cause.as_ref().map(|cause| cause.name.as_str())
+	}
+}
+
+impl RelayerError {
+	/// whether the same request may succeed when sent again to the same endpoint
+	pub fn is_transient(&self) -> bool {
+		match self {
+			Self::Transport(_) => true,
+			Self::HttpStatus { status, .. } => *status == 408 || *status == 429 || *status >= 500,
+			Self::JsonRpc(err) =>
+				err.name.as_deref() == Some("INTERNAL_ERROR") ||
+					matches!(err.cause_name(), Some(cause) if TRANSIENT_CAUSES.contains(&cause)),
+			Self::Decode(_) | Self::InvalidHeader { .. } | Self::Sink(_) | Self::Io(_) => false,
+		}
+	}
+
+	/// whether another endpoint may serve the request, either because the error is transient or
+	/// because the endpoint doesn't have the data, e.g. an old block on a non-archival node
+	pub fn is_endpoint_specific(&self) -> bool {
+		let missing_data = match self {
+			Self::JsonRpc(err) => {
+				matches!(err.cause_name(), Some(cause) if MISSING_DATA_CAUSES.contains(&cause))
+			},
+			_ => false,
+		};
+		self.is_transient() || missing_data
+	}
+}
+
+impl fmt::Display for RelayerError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Transport(err) => write!(f, "transport error: {}", err),
+			Self::HttpStatus { status, body } => write!(f, "HTTP status {}: {}", status, body),
+			Self::JsonRpc(err) => match err.cause_name() {
+				Some(cause) => {
+					write!(f, "JSON-RPC error {}: {} ({})", err.code, err.message, cause)
+				},
+				None => write!(f, "JSON-RPC error {}: {}", err.code, err.message),
+			},
+			Self::Decode(err) => write!(f, "unexpected response: {}", err),
+			Self::InvalidHeader { reason, message } => {
+				write!(f, "invalid header ({}): {}", reason, message)
+			},
+			Self::Sink(err) => write!(f, "submission failed: {}", err),
+			Self::Io(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl std::error::Error for RelayerError {}
+
+impl From<ureq::Transport> for RelayerError {
+	fn from(err: ureq::Transport) -> Self {
+		Self::Transport(err.to_string())
+	}
+}
+
+impl From<serde_json::Error> for RelayerError {
+	fn from(err: serde_json::Error) -> Self {
+		Self::Decode(err.to_string())
+	}
+}
+
+impl From<ConversionError> for RelayerError {
+	fn from(err: ConversionError) -> Self {
+		Self::Decode(err.to_string())
+	}
+}
+
+impl From<io::Error> for RelayerError {
+	fn from(err: io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+impl From<sled::Error> for RelayerError {
+	fn from(err: sled::Error) -> Self {
+		Self::Io(err.into())
+	}
+}
+
+impl From<sled::transaction::TransactionError> for RelayerError {
+	fn from(err: sled::transaction::TransactionError) -> Self {
+		match err {
+			sled::transaction::TransactionError::Abort(err) |
+			sled::transaction::TransactionError::Storage(err) => err.into(),
+		}
+	}
+}
+
+impl From<RpcError> for RelayerError {
+	fn from(err: RpcError) -> Self {
+		Self::JsonRpc(Box::new(err))
+	}
+}
@@ -0,0 +1,971 @@
+//! # Offline inspection
+//!
+//! Verification reports of the `near-lite` CLI. Headers and proofs are read from files, in the
+//! JSON of the RPC or of the proof service, or in Borsh, and verified with `near-lite-client`
+//! without any network access.
+
+use std::{collections::BTreeMap, fmt, fs, io, str};
+
+use borsh::BorshDeserialize;
+use near_lite_client::{
+	validate_contract_state, validate_light_block_with_report, validate_transaction,
+	ApprovalStatus, CommitmentProofBytes, CryptoHash as LiteClientCryptoHash, IbcContractStorage,
+	LightClientBlockView as LiteClientBlockView, MerklePath, OutcomeProof, ShardStateRootProof,
+};
+use near_merkle_proofs::commitment::{verify_membership, verify_non_membership, CommitmentRoot};
+use near_primitives::{
+	hash::CryptoHash,
+	merkle::{compute_root_from_path, compute_root_from_path_and_item},
+	views::LightClientBlockView as NearLightClientBlockView,
+};
+use near_primitives_wasm::{ConversionError, HostFunctions};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+	blockchain_connector::RpcLightClientExecutionProofResponse,
+	client_proof::{BlockHeaderInnerLiteView, LightClientBlockLiteView},
+	proof_queue::LiteClientProof,
+	service::LiteClientStateProof,
+	state::NativeHostFunctions,
+};
+
+/// Borsh types known to `decode`
+pub const BORSH_TYPES: [&str; 3] =
+	["LightClientBlockView", "LiteClientProof", "LiteClientStateProof"];
+
+#[derive(Debug)]
+pub enum InspectError {
+	/// an input file could not be read
+	Io(io::Error),
+	/// an input is not the JSON or the Borsh of the expected type
+	Decode(String),
+}
+
+impl fmt::Display for InspectError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Io(err) => write!(f, "{}", err),
+			Self::Decode(err) => write!(f, "invalid input: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for InspectError {}
+
+impl From<io::Error> for InspectError {
+	fn from(err: io::Error) -> Self {
+		Self::Io(err)
+	}
+}
+
+impl From<serde_json::Error> for InspectError {
+	fn from(err: serde_json::Error) -> Self {
+		Self::Decode(err.to_string())
+	}
+}
+
+impl From<ConversionError> for InspectError {
+	fn from(err: ConversionError) -> Self {
+		Self::Decode(err.to_string())
+	}
+}
+
+/// Contents of an input: JSON, or Borsh given as raw bytes or as hex
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+	Json(Value),
+	Borsh(Vec<u8>),
+}
+
+impl Input {
+	pub fn read(path: &str) -> Result<Self, InspectError> {
+		Ok(Self::parse(&fs::read(path)?))
+	}
+
+	pub fn parse(bytes: &[u8]) -> Self {
+		if let Ok(value) = serde_json::from_slice(bytes) {
+			return Self::Json(value)
+		}
+		match str::from_utf8(bytes).ok().and_then(|text| decode_hex(text).ok()) {
+			Some(bytes) => Self::Borsh(bytes),
+			None => Self::Borsh(bytes.to_vec()),
+		}
+	}
+
+	/// the view of a JSON input: the `result` of an RPC response, or the `json` of a response of
+	/// the proof service
+	fn into_view(self) -> Option<Value> {
+		match self {
+			Self::Json(mut value) => {
+				for field in ["result", "json"] {
+					if let Some(view) = value.get_mut(field) {
+						return Some(view.take())
+					}
+				}
+				Some(value)
+			},
+			Self::Borsh(_) => None,
+		}
+	}
+}
+
+/// decodes hex, with or without `0x`, ignoring the surrounding whitespace
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, InspectError> {
+	let text = text.trim();
+	hex::decode(text.strip_prefix("0x").unwrap_or(text))
+		.map_err(|err| InspectError::Decode(format!("invalid hex: {}", err)))
+}
+
+fn decode_borsh<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, InspectError> {
+	T::try_from_slice(bytes).map_err(|err| InspectError::Decode(format!("invalid Borsh: {}", err)))
+}
+
+/// Reads a light client block from the JSON of `next_light_client_block`, of the proof service, or
+/// from its Borsh encoding.
+pub fn light_client_block(input: Input) -> Result<LiteClientBlockView, InspectError> {
+	match input {
+		Input::Borsh(bytes) => decode_borsh(&bytes),
+		input => {
+			let view = input.into_view().expect("the input is JSON");
+			let block_view: NearLightClientBlockView = serde_json::from_value(view)?;
+			Ok(block_view.into())
+		},
+	}
+}
+
+/// Result of one step of a verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+	Passed,
+	Failed,
+	/// the inputs are not enough to run the step
+	Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct Check {
+	pub name: &'static str,
+	pub outcome: Outcome,
+	pub detail: String,
+}
+
+/// Human-readable verification report: facts about the inputs, detailed listings and the checks
+/// run on them
+#[derive(Debug, Clone)]
+pub struct Report {
+	pub title: String,
+	pub facts: Vec<(&'static str, String)>,
+	pub sections: Vec<(&'static str, Vec<String>)>,
+	pub checks: Vec<Check>,
+}
+
+impl Report {
+	pub fn new(title: impl Into<String>) -> Self {
+		Self { title: title.into(), facts: Vec::new(), sections: Vec::new(), checks: Vec::new() }
+	}
+
+	/// whether none of the checks failed
+	pub fn is_valid(&self) -> bool {
+		self.checks.iter().all(|check| check.outcome != Outcome::Failed)
+	}
+
+	pub fn check(&self, name: &str) -> Option<&Check> {
+		self.checks.iter().find(|check| check.name == name)
+	}
+
+	fn fact(&mut self, name: &'static str, value: impl ToString) {
+		self.facts.push((name, value.to_string()));
+	}
+
+	fn verify(&mut self, name: &'static str, result: Result<String, String>) {
+		let (outcome, detail) = match result {
+			Ok(detail) => (Outcome::Passed, detail),
+			Err(detail) => (Outcome::Failed, detail),
+		};
+		self.checks.push(Check { name, outcome, detail });
+	}
+
+	fn skip(&mut self, name: &'static str, reason: impl Into<String>) {
+		self.checks
+			.push(Check { name, outcome: Outcome::Skipped, detail: reason.into() });
+	}
+}
+
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "{}", self.title)?;
+		let width = self.facts.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+		for (name, value) in &self.facts {
+			writeln!(f, "  {:width$}  {}", name, value, width = width)?;
+		}
+		for (name, lines) in &self.sections {
+			writeln!(f, "\n{}:", name)?;
+			for line in lines {
+				writeln!(f, "  {}", line)?;
+			}
+		}
+		if self.checks.is_empty() {
+			return Ok(())
+		}
+		writeln!(f, "\nchecks:")?;
+		for check in &self.checks {
+			let outcome = match check.outcome {
+				Outcome::Passed => "[ok]  ",
+				Outcome::Failed => "[FAIL]",
+				Outcome::Skipped => "[skip]",
+			};
+			writeln!(f, "  {} {}: {}", outcome, check.name, check.detail)?;
+		}
+		let skipped = self.checks.iter().any(|check| check.outcome == Outcome::Skipped);
+		let result = match (self.is_valid(), skipped) {
+			(false, _) => "INVALID",
+			(true, false) => "valid",
+			(true, true) => "valid, with skipped checks",
+		};
+		write!(f, "\nresult: {}", result)
+	}
+}
+
+fn base58(hash: LiteClientCryptoHash) -> String {
+	CryptoHash::from(hash).to_string()
+}
+
+/// hash of the block and hash of the next block
+fn block_hashes(block_view: &LiteClientBlockView) -> (LiteClientCryptoHash, LiteClientCryptoHash) {
+	let hash = block_view.current_block_hash::<NativeHostFunctions>();
+	let next_hash = NativeHostFunctions::sha256(
+		&[block_view.next_block_inner_hash.as_ref(), hash.as_ref()].concat(),
+	);
+	(hash, LiteClientCryptoHash(next_hash))
+}
+
+/// `block-hash`: the hashes and the epochs of a light client block
+pub fn block_hash(block_view: &LiteClientBlockView) -> Report {
+	let (hash, next_hash) = block_hashes(block_view);
+	let mut report = Report::new(format!("block {}", block_view.inner_lite.height));
+	report.fact("hash", base58(hash));
+	report.fact("prev hash", base58(block_view.prev_block_hash));
+	report.fact("next block hash", base58(next_hash));
+	report.fact("epoch", base58(block_view.inner_lite.epoch_id));
+	report.fact("next epoch", base58(block_view.inner_lite.next_epoch_id));
+	report.fact(
+		"next block producers",
+		block_view
+			.next_bps
+			.as_ref()
+			.map_or("none".to_string(), |bps| bps.len().to_string()),
+	);
+	report
+}
+
+/// `verify-block`: verifies `block_view` against `head` with `validate_light_block_with_report`,
+/// like `LightClientState::validate_head`.
+///
+/// The block producers of the next epoch of the head are the `next_bps` of the head; the ones of
+/// the epoch of the head are only known from `bps_block`, a block of the previous epoch.
+pub fn verify_block(
+	head: &LiteClientBlockView,
+	block_view: &LiteClientBlockView,
+	bps_block: Option<&LiteClientBlockView>,
+) -> Report {
+	let epoch_block_producers = [Some(head), bps_block]
+		.into_iter()
+		.flatten()
+		.filter_map(|block| Some((block.inner_lite.next_epoch_id, block.next_bps.clone()?)))
+		.collect::<BTreeMap<_, _>>();
+	let known_block_producers = epoch_block_producers.contains_key(&block_view.inner_lite.epoch_id);
+
+	let height = block_view.inner_lite.height;
+	let head_height = head.inner_lite.height;
+	let mut report = Report::new(format!("block {} against the head {}", height, head_height));
+	report.fact("head hash", base58(block_hashes(head).0));
+	let validation = match validate_light_block_with_report::<NativeHostFunctions>(
+		head,
+		block_view,
+		&epoch_block_producers,
+	) {
+		Ok(validation) => validation,
+		Err(err) => {
+			report.verify("near-lite-client", Err(format!("{:?}", err)));
+			return report
+		},
+	};
+	report.fact("hash", base58(validation.block_hash));
+	report.fact("next block hash", base58(validation.next_block_hash));
+	report.fact("epoch", base58(block_view.inner_lite.epoch_id));
+	report.fact("approval message", hex::encode(&validation.approval_message));
+	report.fact(
+		"block producers",
+		if known_block_producers {
+			validation.approvals.len().to_string()
+		} else {
+			"unknown, see --bps".to_string()
+		},
+	);
+	let approvals = validation
+		.approvals
+		.iter()
+		.map(|approval| {
+			let status = match approval.status {
+				ApprovalStatus::Signed => "signed",
+				ApprovalStatus::Missing => "missing",
+				ApprovalStatus::Invalid => "INVALID signature",
+			};
+			format!("{} stake={} {}", approval.account_id, approval.stake, status)
+		})
+		.collect();
+	report.sections.push(("approvals", approvals));
+
+	let epoch_id = block_view.inner_lite.epoch_id;
+	for (name, outcome) in validation.checks() {
+		let detail = match name {
+			"height" => format!("{} against the head {}", height, head_height),
+			"epoch" if epoch_id == head.inner_lite.epoch_id => "epoch of the head".to_string(),
+			"epoch" if epoch_id == head.inner_lite.next_epoch_id =>
+				"next epoch of the head".to_string(),
+			"epoch" => {
+				format!("{} is neither the epoch of the head nor the next one", base58(epoch_id))
+			},
+			"next_bps" => match &block_view.next_bps {
+				Some(next_bps) => format!("{} block producers", next_bps.len()),
+				None if epoch_id == head.inner_lite.next_epoch_id =>
+					"missing in a block of the next epoch".to_string(),
+				None => "not required in the epoch of the head".to_string(),
+			},
+			"signatures" | "stake" if !known_block_producers =>
+				"unknown block producers of the epoch, see --bps".to_string(),
+			"signatures" => {
+				let (invalid, signed): (Vec<_>, Vec<_>) = validation
+					.approvals
+					.iter()
+					.filter(|approval| approval.status != ApprovalStatus::Missing)
+					.partition(|approval| approval.status == ApprovalStatus::Invalid);
+				if invalid.is_empty() {
+					format!("{} approvals signed by their block producer", signed.len())
+				} else {
+					let accounts = invalid.iter().map(|approval| approval.account_id.as_str());
+					format!("invalid signatures of {}", accounts.collect::<Vec<_>>().join(", "))
+				}
+			},
+			"stake" => format!(
+				"approved stake {} of {}, threshold {}",
+				validation.approved_stake, validation.total_stake, validation.threshold
+			),
+			_ => match (&block_view.next_bps, outcome) {
+				(None, _) => "no next_bps".to_string(),
+				(Some(_), Ok(())) => "hash of next_bps".to_string(),
+				(Some(_), Err(_)) => "not the hash of next_bps".to_string(),
+			},
+		};
+		report.verify(name, if outcome.is_ok() { Ok(detail) } else { Err(detail) });
+	}
+	report
+}
+
+/// Proof of an outcome, from the JSON of `EXPERIMENTAL_light_client_proof` or of the proof
+/// service, or from the Borsh of a `LiteClientProof`
+#[derive(Debug, Clone)]
+pub struct TransactionProof {
+	/// head the proof was requested against, unknown in the RPC responses
+	pub light_client_head: Option<LiteClientCryptoHash>,
+	/// height and hash of the block of the outcome root, unknown in the Borsh proofs
+	pub outcome_block: Option<(u64, LiteClientCryptoHash)>,
+	pub outcome_proof: OutcomeProof,
+	pub outcome_root_proof: MerklePath,
+	pub block_outcome_root: LiteClientCryptoHash,
+	pub block_proof: MerklePath,
+}
+
+impl TransactionProof {
+	pub fn decode(input: Input) -> Result<Self, InspectError> {
+		let light_client_head = match &input {
+			Input::Borsh(bytes) => {
+				let proof: LiteClientProof = decode_borsh(bytes)?;
+				return Ok(Self {
+					light_client_head: Some(proof.light_client_head),
+					outcome_block: None,
+					outcome_proof: proof.outcome_proof,
+					outcome_root_proof: proof.outcome_root_proof,
+					block_outcome_root: proof.block_outcome_root,
+					block_proof: proof.block_proof,
+				})
+			},
+			Input::Json(value) => value
+				.get("light_client_head")
+				.map(|head| serde_json::from_value::<CryptoHash>(head.clone()))
+				.transpose()?,
+		};
+		let view = input.into_view().expect("the input is JSON");
+		let response: RpcLightClientExecutionProofResponse = serde_json::from_value(view)?;
+		let block_header_lite = response.block_header_lite;
+		let block_hash = LightClientBlockLiteView {
+			prev_block_hash: block_header_lite.prev_block_hash,
+			inner_rest_hash: block_header_lite.inner_rest_hash,
+			inner_lite: BlockHeaderInnerLiteView::from(block_header_lite.inner_lite.clone()),
+		}
+		.current_block_hash();
+		Ok(Self {
+			light_client_head: light_client_head.map(Into::into),
+			outcome_block: Some((block_header_lite.inner_lite.height, block_hash.into())),
+			outcome_proof: response.outcome_proof.into(),
+			outcome_root_proof: response.outcome_root_proof.into_iter().map(Into::into).collect(),
+			block_outcome_root: block_header_lite.inner_lite.outcome_root.into(),
+			block_proof: response.block_proof.into_iter().map(Into::into).collect(),
+		})
+	}
+}
+
+/// `verify-tx`: verifies that the outcome is in its block, and with `head` that the block is in
+/// the block merkle tree of the head
+pub fn verify_transaction(proof: &TransactionProof, head: Option<&LiteClientBlockView>) -> Report {
+	let outcome = &proof.outcome_proof;
+	let mut report = Report::new(format!("outcome {}", base58(outcome.id)));
+	report.fact("executor", &outcome.outcome.executor_id);
+	report.fact("receipts", outcome.outcome.receipt_ids.len());
+	report.fact("logs", outcome.outcome.logs.len());
+	report.fact("gas burnt", outcome.outcome.gas_burnt);
+	if let Some((height, hash)) = proof.outcome_block {
+		report.fact("block height", height);
+		report.fact("block hash", base58(hash));
+	}
+	report.fact("block outcome root", base58(proof.block_outcome_root));
+	if let Some(light_client_head) = proof.light_client_head {
+		report.fact("light client head", base58(light_client_head));
+	}
+	if let Some(head) = head {
+		report.fact("head", format!("{} {}", head.inner_lite.height, base58(block_hashes(head).0)));
+	}
+
+	report.verify(
+		"outcome",
+		validate_transaction::<NativeHostFunctions>(
+			outcome,
+			proof.outcome_root_proof.clone(),
+			proof.block_outcome_root,
+		)
+		.map(|_| "the outcome is in the outcome root of its block".to_string())
+		.map_err(|err| format!("validate_transaction rejects the proof: {:?}", err)),
+	);
+
+	let head = match head {
+		Some(head) => head,
+		None => {
+			report.skip("head", "no head given, see --head");
+			report.skip("block_proof", "no head given, see --head");
+			return report
+		},
+	};
+	let head_hash = block_hashes(head).0;
+	match proof.light_client_head {
+		Some(light_client_head) if light_client_head == head_hash =>
+			report.verify("head", Ok("the proof was requested against the head".to_string())),
+		Some(light_client_head) => report.verify(
+			"head",
+			Err(format!("the proof was requested against {}", base58(light_client_head))),
+		),
+		None => report.skip("head", "the proof doesn't name its light client head"),
+	}
+	match proof.outcome_block {
+		// the block merkle tree of the head only covers the blocks before it
+		Some((height, _)) if height >= head.inner_lite.height => report
+			.verify("block_proof", Err(format!("the block {} is not covered by the head", height))),
+		Some((_, block_hash)) => {
+			let block_proof = proof.block_proof.iter().cloned().map(Into::into).collect();
+			let root = compute_root_from_path(&block_proof, block_hash.into());
+			report.verify(
+				"block_proof",
+				if root == head.inner_lite.block_merkle_root.into() {
+					Ok("the block is in the block merkle root of the head".to_string())
+				} else {
+					Err(format!("the block proof leads to {} instead", root))
+				},
+			);
+		},
+		None => report.skip("block_proof", "the Borsh proof doesn't hold the block of the outcome"),
+	}
+	report
+}
+
+/// Proof of the value of a key of a contract storage, from the JSON of `POST /proofs/state`
+#[derive(Debug, Clone)]
+pub struct StateProof {
+	pub light_client_head: Option<LiteClientCryptoHash>,
+	pub block_height: Option<u64>,
+	pub account_id: String,
+	pub key: Vec<u8>,
+	pub value: Option<Vec<u8>>,
+	pub proof: LiteClientStateProof,
+}
+
+#[derive(Deserialize)]
+struct StateProofResponse {
+	#[serde(default)]
+	light_client_head: Option<CryptoHash>,
+	#[serde(default)]
+	block_height: Option<u64>,
+	account_id: String,
+	key: String,
+	value: Option<String>,
+	json: StateProofJson,
+}
+
+#[derive(Deserialize)]
+struct StateProofJson {
+	state_root: CryptoHash,
+	state_root_path: near_primitives::merkle::MerklePath,
+	nodes: Vec<String>,
+}
+
+impl StateProof {
+	pub fn decode(input: Input) -> Result<Self, InspectError> {
+		let value = match input {
+			Input::Json(value) => value,
+			Input::Borsh(_) => {
+				let err = "a Borsh state proof doesn't hold its key, use the JSON of the service";
+				return Err(InspectError::Decode(err.to_string()))
+			},
+		};
+		let response: StateProofResponse = serde_json::from_value(value)?;
+		let base64 = |encoded: &str| {
+			base64::decode(encoded)
+				.map_err(|err| InspectError::Decode(format!("invalid base64: {}", err)))
+		};
+		Ok(Self {
+			light_client_head: response.light_client_head.map(Into::into),
+			block_height: response.block_height,
+			account_id: response.account_id,
+			key: base64(&response.key)?,
+			value: response.value.as_deref().map(base64).transpose()?,
+			proof: LiteClientStateProof {
+				shard_state_root_proof: ShardStateRootProof {
+					state_root: response.json.state_root.into(),
+					path: response.json.state_root_path.into_iter().map(Into::into).collect(),
+				},
+				nodes: response
+					.json
+					.nodes
+					.iter()
+					.map(|node| base64(node))
+					.collect::<Result<_, _>>()?,
+			},
+		})
+	}
+}
+
+/// key or value as text when it is printable, as base64 otherwise
+fn display_bytes(bytes: &[u8]) -> String {
+	match str::from_utf8(bytes) {
+		Ok(text) if !text.chars().any(char::is_control) => format!("{:?}", text),
+		_ => format!("base64:{}", base64::encode(bytes)),
+	}
+}
+
+/// `verify-state`: verifies the value against the state root of the shard of the account, and
+/// with `head` that the state root is part of the state of the head
+pub fn verify_state(proof: &StateProof, head: Option<&LiteClientBlockView>) -> Report {
+	let shard_state_root_proof = &proof.proof.shard_state_root_proof;
+	let state_root = shard_state_root_proof.state_root;
+	let path = shard_state_root_proof.path.iter().cloned().map(Into::into).collect::<Vec<_>>();
+	let prev_state_root = compute_root_from_path_and_item(&path, &CryptoHash::from(state_root));
+
+	let mut report = Report::new(format!("state of {}", proof.account_id));
+	report.fact("key", display_bytes(&proof.key));
+	report.fact("value", proof.value.as_deref().map_or("absent".to_string(), display_bytes));
+	report.fact("shard state root", base58(state_root));
+	report.fact("state root", prev_state_root);
+	report.fact("trie nodes", proof.proof.nodes.len());
+	if let Some(block_height) = proof.block_height {
+		report.fact("block height", block_height);
+	}
+	if let Some(light_client_head) = proof.light_client_head {
+		report.fact("light client head", base58(light_client_head));
+	}
+
+	let storage =
+		IbcContractStorage { account_id: proof.account_id.clone(), storage_prefix: Vec::new() };
+	let state_proof = CommitmentProofBytes::from(proof.proof.nodes.clone());
+	let prefix = storage.commitment_prefix();
+	let root = CommitmentRoot(state_root);
+	report.verify(
+		"trie",
+		match &proof.value {
+			Some(value) => verify_membership::<NativeHostFunctions>(
+				&prefix,
+				&state_proof,
+				&root,
+				&proof.key,
+				value.clone(),
+			)
+			.map(|_| "the value is stored under the key".to_string()),
+			None => verify_non_membership::<NativeHostFunctions>(
+				&prefix,
+				&state_proof,
+				&root,
+				&proof.key,
+			)
+			.map(|_| "nothing is stored under the key".to_string()),
+		}
+		.map_err(|err| format!("{:?}", err)),
+	);
+
+	let head = match head {
+		Some(head) => head,
+		None => {
+			for check in ["head", "state_root", "near-lite-client"] {
+				report.skip(check, "no head given, see --head");
+			}
+			return report
+		},
+	};
+	match proof.light_client_head {
+		Some(light_client_head) if light_client_head == block_hashes(head).0 =>
+			report.verify("head", Ok("the proof was requested against the head".to_string())),
+		Some(light_client_head) => report.verify(
+			"head",
+			Err(format!("the proof was requested against {}", base58(light_client_head))),
+		),
+		None => report.skip("head", "the proof doesn't name its light client head"),
+	}
+	report.verify(
+		"state_root",
+		if prev_state_root == head.inner_lite.prev_state_root.into() {
+			Ok("the shard state root is part of the state root of the head".to_string())
+		} else {
+			Err(format!(
+				"the state root of the head is {}",
+				base58(head.inner_lite.prev_state_root)
+			))
+		},
+	);
+	report.verify(
+		"near-lite-client",
+		validate_contract_state::<NativeHostFunctions>(
+			head,
+			&storage,
+			&proof.key,
+			shard_state_root_proof,
+			&state_proof,
+			proof.value.clone(),
+		)
+		.map(|_| "validate_contract_state accepts the proof".to_string())
+		.map_err(|err| format!("validate_contract_state rejects the proof: {:?}", err)),
+	);
+	report
+}
+
+/// `decode`: decodes the Borsh of one of the `BORSH_TYPES`, printed as JSON for the blocks and as
+/// `Debug` for the proofs
+pub fn decode(type_name: &str, bytes: &[u8]) -> Result<String, InspectError> {
+	match type_name {
+		"LightClientBlockView" => {
+			let block_view: LiteClientBlockView = decode_borsh(bytes)?;
+			let block_view = NearLightClientBlockView::try_from(block_view)?;
+			Ok(serde_json::to_string_pretty(&block_view)?)
+		},
+		"LiteClientProof" => Ok(format!("{:#?}", decode_borsh::<LiteClientProof>(bytes)?)),
+		"LiteClientStateProof" =>
+			Ok(format!("{:#?}", decode_borsh::<LiteClientStateProof>(bytes)?)),
+		_ => Err(InspectError::Decode(format!(
+			"unknown type {}, expected one of {}",
+			type_name,
+			BORSH_TYPES.join(", ")
+		))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use borsh::BorshSerialize;
+	use near_chain_simulator::{success_outcome, ChainSimulator, Tampering, Validator};
+	use near_primitives::{
+		merkle::merklize,
+		views::{
+			ExecutionOutcomeWithIdView, LightClientBlockLiteView as NearLightClientBlockLiteView,
+		},
+	};
+	use serde_json::json;
+
+	use super::*;
+	use crate::test_utils::{single_leaf_trie, ACCOUNT_ID, PROOF};
+
+	const BLOCKS: [&str; 2] = [
+		include_str!("../../test-fixtures/light_client_block_86380519.json"),
+		include_str!("../../test-fixtures/light_client_block_86441383.json"),
+	];
+
+	fn chain() -> ChainSimulator {
+		let mut chain = ChainSimulator::new(vec![
+			Validator::new("alice.near", 400),
+			Validator::new("bob.near", 300),
+			Validator::new("carol.near", 200),
+		]);
+		chain.produce_blocks(3 * chain.epoch_length());
+		chain
+	}
+
+	fn all_passed(report: &Report) -> bool {
+		report.checks.iter().all(|check| check.outcome == Outcome::Passed)
+	}
+
+	fn outcomes(report: &Report) -> Vec<(&'static str, Outcome)> {
+		report.checks.iter().map(|check| (check.name, check.outcome)).collect()
+	}
+
+	#[test]
+	fn test_inputs() {
+		let block_view = light_client_block(Input::parse(BLOCKS[1].as_bytes())).unwrap();
+		assert_eq!(block_view.inner_lite.height, 86441383);
+		let borsh = block_view.try_to_vec().unwrap();
+
+		for input in [
+			format!("0x{}\n", hex::encode(&borsh)).into_bytes(),
+			hex::encode(&borsh).into_bytes(),
+			borsh.clone(),
+		] {
+			assert_eq!(Input::parse(&input), Input::Borsh(borsh.clone()));
+			let decoded = light_client_block(Input::parse(&input)).unwrap();
+			assert_eq!(decoded.try_to_vec().unwrap(), borsh);
+		}
+
+		// the view of a response of the proof service
+		let view = serde_json::to_value(NearLightClientBlockView::try_from(block_view).unwrap());
+		let response = json!({ "height": 86441383, "json": view.unwrap(), "borsh": "00" });
+		let decoded = light_client_block(Input::Json(response)).unwrap();
+		assert_eq!(decoded.try_to_vec().unwrap(), borsh);
+
+		assert!(light_client_block(Input::Borsh(borsh[1..].to_vec())).is_err());
+		assert!(light_client_block(Input::Json(json!({ "result": {} }))).is_err());
+	}
+
+	#[test]
+	fn test_block_hash() {
+		let block_view = light_client_block(Input::parse(BLOCKS[1].as_bytes())).unwrap();
+		let report = block_hash(&block_view);
+		assert_eq!(report.title, "block 86441383");
+		assert_eq!(
+			report.facts[0],
+			("hash", "853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7".into())
+		);
+		assert!(report.to_string().contains("853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7"));
+	}
+
+	#[test]
+	fn test_verify_block() {
+		let [head, block_view] =
+			BLOCKS.map(|block| light_client_block(Input::parse(block.as_bytes())).unwrap());
+		let report = verify_block(&head, &block_view, None);
+		assert!(report.is_valid(), "{}", report);
+		assert!(all_passed(&report), "{}", report);
+		assert!(report.to_string().ends_with("result: valid"));
+
+		// the block producers of the epoch of the head are unknown without a block of the epoch
+		// before, so the approvals can't be verified
+		let chain = chain();
+		let height = chain.last_block_of_epoch(0);
+		let block = |height, tampering: Tampering| {
+			chain.tampered_light_client_block(height, &tampering).unwrap()
+		};
+		let (bps_block, head) =
+			(block(height, Tampering::default()), block(height + 1, Tampering::default()));
+		let block_view = block(height + 2, Tampering::default());
+		let report = verify_block(&head, &block_view, None);
+		let signatures = report.check("signatures").unwrap();
+		assert_eq!(signatures.outcome, Outcome::Failed);
+		assert_eq!(signatures.detail, "unknown block producers of the epoch, see --bps");
+		assert_eq!(report.check("stake").unwrap().outcome, Outcome::Failed);
+		assert!(report.to_string().ends_with("result: INVALID"));
+		let report = verify_block(&head, &block_view, Some(&bps_block));
+		assert!(all_passed(&report), "{}", report);
+		assert_eq!(report.sections[0].1[0], "alice.near stake=400 signed");
+
+		let forged = Tampering { forged_approvals: [0].into(), ..Default::default() };
+		let report = verify_block(&bps_block, &block(height + 1, forged), None);
+		assert!(!report.is_valid());
+		assert_eq!(
+			outcomes(&report),
+			vec![
+				("height", Outcome::Passed),
+				("epoch", Outcome::Passed),
+				("next_bps", Outcome::Passed),
+				("signatures", Outcome::Failed),
+				("stake", Outcome::Passed),
+				("next_bp_hash", Outcome::Passed),
+			]
+		);
+		assert_eq!(report.sections[0].1[0], "alice.near stake=400 INVALID signature");
+		assert!(report.to_string().ends_with("result: INVALID"));
+
+		let missing = Tampering { missing_approvals: [0].into(), ..Default::default() };
+		let report = verify_block(&bps_block, &block(height + 1, missing), None);
+		let stake = report.check("stake").unwrap();
+		assert_eq!(stake.outcome, Outcome::Failed);
+		assert_eq!(stake.detail, "approved stake 500 of 900, threshold 600");
+
+		let report = verify_block(&head, &bps_block, Some(&bps_block));
+		assert_eq!(report.check("height").unwrap().outcome, Outcome::Failed);
+	}
+
+	/// the proof of the outcome of a simulated transaction as the JSON of the RPC, and the head
+	/// its block is covered by
+	fn transaction_proof() -> (Value, LiteClientBlockView) {
+		let mut chain = chain();
+		let id = LiteClientCryptoHash::hash_bytes(b"transaction");
+		let outcome = success_outcome("alice.near", vec!["log".to_string()], Vec::new());
+		let height = chain.produce_block_with_outcomes(vec![Vec::new(), vec![(id, outcome)]]);
+		chain.produce_blocks(3);
+
+		let proof = chain.outcome_proof(id).unwrap();
+		let block_view =
+			NearLightClientBlockView::try_from(chain.light_client_block(height).unwrap()).unwrap();
+		let near_path = |path: MerklePath| {
+			path.into_iter()
+				.map(Into::into)
+				.collect::<near_primitives::merkle::MerklePath>()
+		};
+		let response = json!({
+			"outcome_proof": ExecutionOutcomeWithIdView::try_from(proof.outcome_proof).unwrap(),
+			"outcome_root_proof": near_path(proof.outcome_root_proof),
+			"block_header_lite": NearLightClientBlockLiteView {
+				prev_block_hash: block_view.prev_block_hash,
+				inner_rest_hash: block_view.inner_rest_hash,
+				inner_lite: block_view.inner_lite,
+			},
+			"block_proof": near_path(chain.block_proof(height, height + 2).unwrap()),
+		});
+		(json!({ "result": response }), chain.light_client_block(height + 2).unwrap())
+	}
+
+	#[test]
+	fn test_verify_transaction() {
+		let (response, head) = transaction_proof();
+		let proof = TransactionProof::decode(Input::Json(response.clone())).unwrap();
+		let report = verify_transaction(&proof, Some(&head));
+		assert_eq!(
+			outcomes(&report),
+			vec![
+				("outcome", Outcome::Passed),
+				("head", Outcome::Skipped),
+				("block_proof", Outcome::Passed),
+			]
+		);
+
+		// the response of the proof service names its head
+		let head_hash = block_hashes(&head).0;
+		let response = json!({
+			"light_client_head": CryptoHash::from(head_hash),
+			"json": response["result"],
+		});
+		let proof = TransactionProof::decode(Input::Json(response)).unwrap();
+		assert!(all_passed(&verify_transaction(&proof, Some(&head))));
+		assert_eq!(
+			verify_transaction(&proof, None).check("block_proof").unwrap().outcome,
+			Outcome::Skipped
+		);
+
+		let mut other_head = head.clone();
+		other_head.inner_lite.block_merkle_root = LiteClientCryptoHash::hash_bytes(b"other");
+		let report = verify_transaction(&proof, Some(&other_head));
+		assert_eq!(report.check("head").unwrap().outcome, Outcome::Failed);
+		assert_eq!(report.check("block_proof").unwrap().outcome, Outcome::Failed);
+
+		let mut tampered = proof.clone();
+		tampered.block_outcome_root = LiteClientCryptoHash::hash_bytes(b"other");
+		assert_eq!(
+			verify_transaction(&tampered, None).check("outcome").unwrap().outcome,
+			Outcome::Failed
+		);
+
+		// the Borsh proofs don't hold the block of the outcome
+		let borsh = LiteClientProof {
+			light_client_head: head_hash,
+			outcome_proof: proof.outcome_proof,
+			outcome_root_proof: proof.outcome_root_proof,
+			block_outcome_root: proof.block_outcome_root,
+			block_proof: proof.block_proof,
+		};
+		let proof = TransactionProof::decode(Input::Borsh(borsh.try_to_vec().unwrap())).unwrap();
+		let report = verify_transaction(&proof, Some(&head));
+		assert_eq!(
+			outcomes(&report),
+			vec![
+				("outcome", Outcome::Passed),
+				("head", Outcome::Passed),
+				("block_proof", Outcome::Skipped),
+			]
+		);
+
+		let proof = TransactionProof::decode(Input::parse(PROOF.as_bytes())).unwrap();
+		let report = verify_transaction(&proof, None);
+		assert_eq!(report.check("outcome").unwrap().outcome, Outcome::Passed, "{}", report);
+		assert_eq!(proof.outcome_block.unwrap().0, 86697768);
+	}
+
+	#[test]
+	fn test_verify_state() {
+		let (state_root, node) = single_leaf_trie(b"counter", b"42");
+		let state_roots = [CryptoHash::hash_bytes(b"other shard"), state_root];
+		let (prev_state_root, paths) = merklize(&state_roots);
+		let mut head = light_client_block(Input::parse(BLOCKS[1].as_bytes())).unwrap();
+		head.inner_lite.prev_state_root = prev_state_root.into();
+		let response = |key: &str, value: Option<&str>| {
+			json!({
+				"light_client_head": CryptoHash::from(block_hashes(&head).0),
+				"block_height": 86441383,
+				"account_id": ACCOUNT_ID,
+				"key": base64::encode(key),
+				"value": value.map(base64::encode),
+				"json": {
+					"state_root": state_root,
+					"state_root_path": paths[1],
+					"nodes": [base64::encode(&node)],
+				},
+				"borsh": "",
+			})
+		};
+
+		let proof = StateProof::decode(Input::Json(response("counter", Some("42")))).unwrap();
+		let report = verify_state(&proof, Some(&head));
+		assert!(all_passed(&report), "{}", report);
+		assert!(report.facts.contains(&("value", "\"42\"".to_string())));
+		let report = verify_state(&proof, None);
+		assert_eq!(
+			outcomes(&report),
+			vec![
+				("trie", Outcome::Passed),
+				("head", Outcome::Skipped),
+				("state_root", Outcome::Skipped),
+				("near-lite-client", Outcome::Skipped),
+			]
+		);
+
+		let mut other_head = head.clone();
+		other_head.inner_lite.prev_state_root = state_root.into();
+		let report = verify_state(&proof, Some(&other_head));
+		assert_eq!(report.check("trie").unwrap().outcome, Outcome::Passed);
+		assert_eq!(report.check("state_root").unwrap().outcome, Outcome::Failed);
+		assert_eq!(report.check("near-lite-client").unwrap().outcome, Outcome::Failed);
+
+		for (key, value) in [("counter", Some("41")), ("counter", None), ("answer", Some("42"))] {
+			let proof = StateProof::decode(Input::Json(response(key, value))).unwrap();
+			let report = verify_state(&proof, Some(&head));
+			assert_eq!(report.check("trie").unwrap().outcome, Outcome::Failed, "{}", report);
+			assert!(!report.is_valid());
+		}
+		assert!(StateProof::decode(Input::Borsh(vec![0])).is_err());
+	}
+
+	#[test]
+	fn test_decode() {
+		let block_view = light_client_block(Input::parse(BLOCKS[1].as_bytes())).unwrap();
+		let borsh = block_view.try_to_vec().unwrap();
+		let decoded: Value =
+			serde_json::from_str(&decode("LightClientBlockView", &borsh).unwrap()).unwrap();
+		let expected: Value = serde_json::from_str(BLOCKS[1]).unwrap();
+		assert_eq!(decoded["inner_lite"], expected["result"]["inner_lite"]);
+
+		assert!(decode("LiteClientProof", &borsh).is_err());
+		assert!(decode("BlockView", &borsh).is_err());
+		assert!(decode_hex("0xzz").is_err());
+	}
+}
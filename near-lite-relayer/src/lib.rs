@@ -1,6 +1,26 @@
 //! Near lite client
 
+pub mod async_connector;
 pub mod blockchain_connector;
 mod client_block;
 mod client_proof;
+pub mod config;
+pub mod cross_check;
+pub mod error;
+pub mod inspect;
+pub mod metrics;
+#[cfg(any(test, feature = "mock-rpc"))]
+pub mod mock_rpc;
+pub mod policy;
+pub mod prefetch;
+pub mod proof_queue;
+pub mod relayer;
+pub mod replay;
+pub mod retry;
+pub mod scanner;
+pub mod service;
+pub mod sink;
 pub mod state;
+pub mod store;
+#[cfg(test)]
+mod test_utils;
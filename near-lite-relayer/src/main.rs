@@ -0,0 +1,140 @@
+//! # Relayer daemon
+//!
+//! `near-lite-relayer <config.toml>`: follows the chain from the checkpoint of the `[follow]`
+//! section of the configuration, until it receives SIGINT or SIGTERM. See `config` for the
+//! format of the configuration.
+
+use std::{
+	env,
+	error::Error,
+	process,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use near_lite_relayer::{
+	blockchain_connector::{BlockchainConnector, NearRpc},
+	config::{FollowConfig, RelayerConfig, SinkConfig},
+	cross_check::CrossChecker,
+	metrics::{MetricsServer, RelayerMetrics},
+	prefetch::Prefetcher,
+	relayer::Relayer,
+	scanner::AccountScanner,
+	service::{ProofServer, ProofService},
+	sink::{HeaderSink, JsonlSink, LogSink, ProofSink, StdoutSink},
+	store::SledStore,
+};
+
+fn main() {
+	env_logger::init();
+
+	let config_path = match env::args().nth(1) {
+		Some(config_path) => config_path,
+		None => {
+			eprintln!("usage: near-lite-relayer <config.toml>");
+			process::exit(2)
+		},
+	};
+	if let Err(err) = run(&config_path) {
+		log::error!("{}", err);
+		process::exit(1)
+	}
+}
+
+fn run(config_path: &str) -> Result<(), Box<dyn Error>> {
+	let config = RelayerConfig::from_file(config_path)?;
+	let follow = config.follow.clone().ok_or("the configuration has no [follow] section")?;
+
+	let shutdown = Arc::new(AtomicBool::new(false));
+	{
+		let shutdown = shutdown.clone();
+		ctrlc::set_handler(move || {
+			log::info!("shutting down");
+			shutdown.store(true, Ordering::Relaxed);
+		})?;
+	}
+
+	match &follow.sink {
+		SinkConfig::Log => run_with_sink(&config, &follow, LogSink, &shutdown),
+		SinkConfig::Stdout => run_with_sink(&config, &follow, StdoutSink, &shutdown),
+		SinkConfig::Jsonl { path } =>
+			run_with_sink(&config, &follow, JsonlSink::open(path)?, &shutdown),
+	}
+}
+
+fn run_with_sink<S: HeaderSink + ProofSink>(
+	config: &RelayerConfig,
+	follow: &FollowConfig,
+	sink: S,
+	shutdown: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+	let metrics = RelayerMetrics::new();
+	let _metrics_server = follow
+		.metrics_addr
+		.map(|addr| MetricsServer::start(addr, metrics.clone()))
+		.transpose()?;
+	let connector = || {
+		let rpc = config.connector();
+		match follow.metrics_addr {
+			Some(_) => rpc.with_metrics(metrics.clone()),
+			None => rpc,
+		}
+	};
+
+	let store = SledStore::open(&follow.store_path, follow.epoch_retention)?;
+	let _proof_server = follow
+		.service_addr
+		.map(|addr| ProofServer::start(addr, ProofService::new(connector(), store.clone())))
+		.transpose()?;
+	match &follow.prefetch {
+		Some(prefetch) => {
+			let rpc = config.async_connector();
+			let rpc = match follow.metrics_addr {
+				Some(_) => rpc.with_metrics(metrics.clone()),
+				None => rpc,
+			};
+			let rpc = Prefetcher::new(rpc, prefetch.clone())?;
+			let relayer = Relayer::resume_or_start(rpc, sink, store, follow.checkpoint_hash)?;
+			relay(relayer, config, follow, metrics, shutdown)
+		},
+		None => {
+			let relayer =
+				Relayer::resume_or_start(connector(), sink, store, follow.checkpoint_hash)?;
+			relay(relayer, config, follow, metrics, shutdown)
+		},
+	}
+}
+
+fn relay<R: NearRpc, S: HeaderSink + ProofSink>(
+	relayer: Relayer<R, S, SledStore>,
+	config: &RelayerConfig,
+	follow: &FollowConfig,
+	metrics: RelayerMetrics,
+	shutdown: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+	let mut relayer = relayer.with_policy(follow.policy);
+	if !follow.accounts.is_empty() {
+		relayer = relayer.with_scanner(AccountScanner::new(&follow.accounts));
+	}
+	if !follow.witnesses.is_empty() {
+		let mut cross_checker = CrossChecker::new();
+		for endpoint in &follow.witnesses {
+			let name = endpoint.url.clone();
+			let mut witness =
+				BlockchainConnector::from_endpoints(vec![endpoint.clone()], config.retry.clone());
+			if follow.metrics_addr.is_some() {
+				witness = witness.with_metrics(metrics.clone());
+			}
+			cross_checker = cross_checker.with_witness(name, witness);
+		}
+		relayer = relayer.with_cross_checker(cross_checker);
+	}
+	if follow.metrics_addr.is_some() {
+		relayer = relayer.with_metrics(metrics);
+	}
+	relayer.run(Duration::from_secs(follow.poll_interval_secs), shutdown);
+	Ok(())
+}
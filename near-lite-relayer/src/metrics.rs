@@ -0,0 +1,300 @@
+//! # Prometheus metrics
+//!
+//! `RelayerMetrics` is shared by the `BlockchainConnector` and the `Relayer`, see their
+//! `with_metrics`, and exported in the Prometheus text format by a `MetricsServer` on `/metrics`:
+//!
+//! - `near_lite_relayer_head_height` and `near_lite_relayer_head_epoch{epoch_id}`
+//! - `near_lite_relayer_final_height` and `near_lite_relayer_head_lag`, the blocks between the head
+//!   and the latest final block
+//! - `near_lite_relayer_rpc_requests_total`, `near_lite_relayer_rpc_errors_total` and
+//!   `near_lite_relayer_rpc_request_duration_seconds`, by `endpoint` and `method`
+//! - `near_lite_relayer_submissions_total`, by `sink` and `kind`, `header` or `proof`
+//! - `near_lite_relayer_validation_failures_total`, by `reason`, see `HeaderRejection`
+//! - `near_lite_relayer_proof_queue_depth`
+//! - `near_lite_relayer_cross_check_alerts_total`, by `witness` and `kind`, see `WitnessOutcome`
+
+use std::{
+	io,
+	net::{SocketAddr, TcpListener},
+	sync::Arc,
+	thread::{self, JoinHandle},
+	time::Duration,
+};
+
+use prometheus::{
+	Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+	TextEncoder,
+};
+use tiny_http::{Header, Response, Server};
+
+use crate::{error::HeaderRejection, state::LightClientState};
+
+/// Metrics of a relayer; clones share the same metrics
+#[derive(Clone)]
+pub struct RelayerMetrics {
+	registry: Registry,
+	head_height: IntGauge,
+	head_epoch: IntGaugeVec,
+	final_height: IntGauge,
+	head_lag: IntGauge,
+	rpc_requests: IntCounterVec,
+	rpc_errors: IntCounterVec,
+	rpc_request_duration: HistogramVec,
+	submissions: IntCounterVec,
+	validation_failures: IntCounterVec,
+	proof_queue_depth: IntGauge,
+	cross_check_alerts: IntCounterVec,
+}
+
+impl Default for RelayerMetrics {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl RelayerMetrics {
+	pub fn new() -> Self {
+		let registry = Registry::new_custom(Some("near_lite_relayer".to_owned()), None)
+			.expect("the prefix is valid");
+		let metrics = Self {
+			head_height: IntGauge::new("head_height", "height of the head").unwrap(),
+			head_epoch: IntGaugeVec::new(
+				Opts::new("head_epoch", "epoch of the head, the one with the value 1"),
+				&["epoch_id"],
+			)
+			.unwrap(),
+			final_height: IntGauge::new("final_height", "height of the latest final block")
+				.unwrap(),
+			head_lag: IntGauge::new(
+				"head_lag",
+				"blocks between the head and the latest final block",
+			)
+			.unwrap(),
+			rpc_requests: IntCounterVec::new(
+				Opts::new("rpc_requests_total", "requests sent to the RPC endpoints"),
+				&["endpoint", "method"],
+			)
+			.unwrap(),
+			rpc_errors: IntCounterVec::new(
+				Opts::new("rpc_errors_total", "requests failed on the RPC endpoints"),
+				&["endpoint", "method"],
+			)
+			.unwrap(),
+			rpc_request_duration: HistogramVec::new(
+				HistogramOpts::new(
+					"rpc_request_duration_seconds",
+					"duration of the requests to the RPC endpoints",
+				),
+				&["endpoint", "method"],
+			)
+			.unwrap(),
+			submissions: IntCounterVec::new(
+				Opts::new("submissions_total", "headers and proofs submitted to the sink"),
+				&["sink", "kind"],
+			)
+			.unwrap(),
+			validation_failures: IntCounterVec::new(
+				Opts::new("validation_failures_total", "headers rejected by the light client"),
+				&["reason"],
+			)
+			.unwrap(),
+			proof_queue_depth: IntGauge::new(
+				"proof_queue_depth",
+				"proof requests waiting for the head to cover their outcome",
+			)
+			.unwrap(),
+			cross_check_alerts: IntCounterVec::new(
+				Opts::new("cross_check_alerts_total", "alerts raised by the witnesses"),
+				&["witness", "kind"],
+			)
+			.unwrap(),
+			registry,
+		};
+		metrics.register().expect("the metrics are registered once");
+		metrics
+	}
+
+	fn register(&self) -> prometheus::Result<()> {
+		self.registry.register(Box::new(self.head_height.clone()))?;
+		self.registry.register(Box::new(self.head_epoch.clone()))?;
+		self.registry.register(Box::new(self.final_height.clone()))?;
+		self.registry.register(Box::new(self.head_lag.clone()))?;
+		self.registry.register(Box::new(self.rpc_requests.clone()))?;
+		self.registry.register(Box::new(self.rpc_errors.clone()))?;
+		self.registry.register(Box::new(self.rpc_request_duration.clone()))?;
+		self.registry.register(Box::new(self.submissions.clone()))?;
+		self.registry.register(Box::new(self.validation_failures.clone()))?;
+		self.registry.register(Box::new(self.proof_queue_depth.clone()))?;
+		self.registry.register(Box::new(self.cross_check_alerts.clone()))
+	}
+
+	/// registry of the metrics, e.g. to register more of them
+	pub fn registry(&self) -> &Registry {
+		&self.registry
+	}
+
+	/// the metrics in the Prometheus text format
+	pub fn encode(&self) -> String {
+		let mut buffer = Vec::new();
+		TextEncoder::new()
+			.encode(&self.registry.gather(), &mut buffer)
+			.expect("encoding into a vec doesn't fail");
+		String::from_utf8(buffer).expect("the text format is UTF-8")
+	}
+
+	pub fn observe_head(&self, state: &LightClientState) {
+		self.head_height.set(state.head_height() as i64);
+		let epoch_id = state.epoch_id().to_string();
+		if self.head_epoch.with_label_values(&[&epoch_id]).get() != 1 {
+			self.head_epoch.reset();
+			self.head_epoch.with_label_values(&[&epoch_id]).set(1);
+		}
+		self.update_head_lag();
+	}
+
+	pub fn observe_final_height(&self, final_height: u64) {
+		self.final_height.set(final_height as i64);
+		self.update_head_lag();
+	}
+
+	fn update_head_lag(&self) {
+		if self.final_height.get() > 0 {
+			self.head_lag.set((self.final_height.get() - self.head_height.get()).max(0));
+		}
+	}
+
+	pub fn observe_rpc_request(
+		&self,
+		endpoint: &str,
+		method: &str,
+		duration: Duration,
+		succeeded: bool,
+	) {
+		let labels = [endpoint, method];
+		self.rpc_requests.with_label_values(&labels).inc();
+		self.rpc_request_duration
+			.with_label_values(&labels)
+			.observe(duration.as_secs_f64());
+		if !succeeded {
+			self.rpc_errors.with_label_values(&labels).inc();
+		}
+	}
+
+	pub fn inc_header_submissions(&self, sink: &str) {
+		self.submissions.with_label_values(&[sink, "header"]).inc();
+	}
+
+	pub fn inc_proof_submissions(&self, sink: &str, count: usize) {
+		self.submissions.with_label_values(&[sink, "proof"]).inc_by(count as u64);
+	}
+
+	pub fn inc_validation_failures(&self, reason: HeaderRejection) {
+		self.validation_failures.with_label_values(&[reason.as_str()]).inc();
+	}
+
+	pub fn set_proof_queue_depth(&self, depth: usize) {
+		self.proof_queue_depth.set(depth as i64);
+	}
+
+	pub fn inc_cross_check_alerts(&self, witness: &str, kind: &str) {
+		self.cross_check_alerts.with_label_values(&[witness, kind]).inc();
+	}
+}
+
+/// HTTP server exporting `RelayerMetrics` on `/metrics`, until dropped
+pub struct MetricsServer {
+	addr: SocketAddr,
+	server: Arc<Server>,
+	handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+	/// starts the server on `addr`; a port 0 picks a free port, see `addr`
+	pub fn start(addr: SocketAddr, metrics: RelayerMetrics) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr)?;
+		let addr = listener.local_addr()?;
+		let server = Arc::new(
+			Server::from_listener(listener, None)
+				.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?,
+		);
+		let handle = {
+			let server = server.clone();
+			thread::spawn(move || serve(&server, &metrics))
+		};
+		log::info!("serving the metrics on http://{}/metrics", addr);
+		Ok(Self { addr, server, handle: Some(handle) })
+	}
+
+	pub fn addr(&self) -> SocketAddr {
+		self.addr
+	}
+}
+
+impl Drop for MetricsServer {
+	fn drop(&mut self) {
+		self.server.unblock();
+		if let Some(handle) = self.handle.take() {
+			let _ = handle.join();
+		}
+	}
+}
+
+fn serve(server: &Server, metrics: &RelayerMetrics) {
+	for request in server.incoming_requests() {
+		let response = if request.url() == "/metrics" {
+			let header = Header::from_bytes("Content-Type", TextEncoder::new().format_type())
+				.expect("the content type is a valid header");
+			Response::from_string(metrics.encode()).with_header(header)
+		} else {
+			Response::from_string("not found").with_status_code(404)
+		};
+		let _ = request.respond(response);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scrape(server: &MetricsServer) -> String {
+		ureq::get(&format!("http://{}/metrics", server.addr()))
+			.call()
+			.unwrap()
+			.into_string()
+			.unwrap()
+	}
+
+	#[test]
+	fn test_scrape() {
+		let metrics = RelayerMetrics::new();
+		let server = MetricsServer::start("127.0.0.1:0".parse().unwrap(), metrics.clone()).unwrap();
+
+		metrics.observe_rpc_request("http://rpc", "block", Duration::from_millis(20), true);
+		metrics.observe_rpc_request("http://rpc", "block", Duration::from_millis(30), false);
+		metrics.inc_header_submissions("log");
+		metrics.inc_proof_submissions("log", 2);
+		metrics.inc_validation_failures(HeaderRejection::InvalidSignature);
+		metrics.set_proof_queue_depth(3);
+		metrics.inc_cross_check_alerts("witness", "equivocation");
+		metrics.observe_final_height(100);
+
+		let scraped = scrape(&server);
+		for line in [
+			r#"near_lite_relayer_rpc_requests_total{endpoint="http://rpc",method="block"} 2"#,
+			r#"near_lite_relayer_rpc_errors_total{endpoint="http://rpc",method="block"} 1"#,
+			r#"near_lite_relayer_rpc_request_duration_seconds_count{endpoint="http://rpc",method="block"} 2"#,
+			r#"near_lite_relayer_submissions_total{kind="header",sink="log"} 1"#,
+			r#"near_lite_relayer_submissions_total{kind="proof",sink="log"} 2"#,
+			r#"near_lite_relayer_validation_failures_total{reason="invalid_signature"} 1"#,
+			"near_lite_relayer_proof_queue_depth 3",
+			r#"near_lite_relayer_cross_check_alerts_total{kind="equivocation",witness="witness"} 1"#,
+			"near_lite_relayer_final_height 100",
+			"near_lite_relayer_head_lag 100",
+		] {
+			assert!(scraped.lines().any(|scraped| scraped == line), "no {} in {}", line, scraped);
+		}
+
+		let not_found = ureq::get(&format!("http://{}/", server.addr())).call();
+		assert!(matches!(not_found, Err(ureq::Error::Status(404, _))));
+	}
+}
@@ -0,0 +1,168 @@
+//! In-process stand-in for a NEAR RPC node
+//!
+//! Serves canned JSON-RPC responses on a local port, so that the relayer can be tested without
+//! network access: point a `BlockchainConnector` at `MockRpcServer::url`.
+
+use std::{
+	collections::VecDeque,
+	io,
+	net::TcpListener,
+	sync::{Arc, Mutex},
+	thread::{self, JoinHandle},
+};
+
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+struct CannedResponse {
+	method: String,
+	params: Value,
+	/// `result` or `error` of the response
+	outcome: Result<Value, Value>,
+}
+
+#[derive(Default)]
+struct State {
+	responses: Vec<CannedResponse>,
+	/// headers every request must carry, e.g. an API key
+	required_headers: Vec<(String, String)>,
+	/// HTTP statuses answered to the next requests, whatever they are
+	failures: VecDeque<u16>,
+}
+
+/// JSON-RPC server answering the requests whose `method` and `params` match one of its canned
+/// responses. Any other request gets a JSON-RPC error.
+pub struct MockRpcServer {
+	url: String,
+	server: Arc<Server>,
+	state: Arc<Mutex<State>>,
+	handle: Option<JoinHandle<()>>,
+}
+
+impl MockRpcServer {
+	/// starts the server on a free local port
+	pub fn start() -> io::Result<Self> {
+		let listener = TcpListener::bind("127.0.0.1:0")?;
+		let url = format!("http://{}", listener.local_addr()?);
+		let server = Arc::new(
+			Server::from_listener(listener, None)
+				.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?,
+		);
+		let state = Arc::new(Mutex::new(State::default()));
+
+		let handle = {
+			let server = server.clone();
+			let state = state.clone();
+			thread::spawn(move || serve(&server, &state))
+		};
+
+		Ok(Self { url, server, state, handle: Some(handle) })
+	}
+
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	/// answers `method` called with `params` with `result`
+	pub fn respond(&self, method: &str, params: Value, result: Value) -> &Self {
+		self.push(method, params, Ok(result))
+	}
+
+	/// answers `method` called with `params` with the JSON-RPC `error` object
+	pub fn respond_with_error(&self, method: &str, params: Value, error: Value) -> &Self {
+		self.push(method, params, Err(error))
+	}
+
+	/// answers `method` called with `params` with the `result` of a recorded RPC response
+	pub fn respond_with_recording(&self, method: &str, params: Value, rpc_response: &str) -> &Self {
+		let mut rpc_response =
+			serde_json::from_str::<Value>(rpc_response).expect("recorded response is not JSON");
+		let result = rpc_response["result"].take();
+		self.respond(method, params, result)
+	}
+
+	/// rejects the requests without the header `name: value` with `401 Unauthorized`
+	pub fn require_header(&self, name: &str, value: &str) -> &Self {
+		self.state
+			.lock()
+			.unwrap()
+			.required_headers
+			.push((name.to_owned(), value.to_owned()));
+		self
+	}
+
+	/// answers the next `count` requests with the HTTP `status` and an empty body
+	pub fn fail_next(&self, count: usize, status: u16) -> &Self {
+		let failures = &mut self.state.lock().unwrap().failures;
+		failures.resize(failures.len() + count, status);
+		self
+	}
+
+	fn push(&self, method: &str, params: Value, outcome: Result<Value, Value>) -> &Self {
+		self.state.lock().unwrap().responses.push(CannedResponse {
+			method: method.to_owned(),
+			params,
+			outcome,
+		});
+		self
+	}
+}
+
+impl Drop for MockRpcServer {
+	fn drop(&mut self) {
+		self.server.unblock();
+		if let Some(handle) = self.handle.take() {
+			let _ = handle.join();
+		}
+	}
+}
+
+fn serve(server: &Server, state: &Mutex<State>) {
+	for mut request in server.incoming_requests() {
+		let mut state = state.lock().unwrap();
+		if let Some(status) = state.failures.pop_front() {
+			let _ = request.respond(Response::empty(status));
+			continue;
+		}
+		let authorized = state.required_headers.iter().all(|(name, value)| {
+			request.headers().iter().any(|header| {
+				header.field.as_str().as_str().eq_ignore_ascii_case(name) &&
+					header.value.as_str() == value
+			})
+		});
+		if !authorized {
+			let _ = request.respond(Response::empty(401));
+			continue;
+		}
+
+		let body = serde_json::from_reader::<_, Value>(request.as_reader()).unwrap_or_default();
+		let id = body["id"].clone();
+		let method = body["method"].as_str().unwrap_or_default();
+		let params = &body["params"];
+
+		let outcome = state
+			.responses
+			.iter()
+			.find(|response| response.method == method && &response.params == params)
+			.map(|response| response.outcome.clone());
+		let response = match outcome {
+			Some(Ok(result)) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+			Some(Err(error)) => json!({ "jsonrpc": "2.0", "error": error, "id": id }),
+			None => {
+				log::warn!("no canned response for method={} params={}", method, params);
+				json!({
+					"jsonrpc": "2.0",
+					"error": {
+						"code": -32000,
+						"message": "Server error",
+						"data": format!("no canned response for {} {}", method, params),
+					},
+					"id": id,
+				})
+			},
+		};
+
+		let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+		let _ = request.respond(Response::from_string(response.to_string()).with_header(header));
+	}
+}
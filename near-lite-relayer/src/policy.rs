@@ -0,0 +1,85 @@
+//! # Relay policy
+//!
+//! Decides which of the validated blocks are submitted, so that the destination chain doesn't pay
+//! for headers nobody needs.
+//!
+//! Whatever the policy, a block of a new epoch is always submitted: the destination only learns
+//! the block producers of an epoch from a block of the previous one, and would not be able to
+//! validate anything once more than one epoch behind.
+
+use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+use serde::Deserialize;
+
+/// In the relayer configuration: `policy = "every_block"`, `"epoch_boundary_only"`,
+/// `"on_demand"` or `{ interval = 100 }`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayPolicy {
+	/// every block returned by `next_light_client_block`
+	#[default]
+	EveryBlock,
+	/// only the first block of each epoch, which carries the block producers of the next one
+	EpochBoundaryOnly,
+	/// blocks at least this many heights after the last submitted one
+	Interval(u64),
+	/// only when proof requests are pending, so that the head covers their blocks
+	OnDemand,
+}
+
+impl RelayPolicy {
+	/// whether `block_view`, validated on top of the last submitted `head`, is submitted
+	pub fn should_submit(
+		&self,
+		head: &NearLightClientBlockView,
+		block_view: &NearLightClientBlockView,
+		has_pending_proof_requests: bool,
+	) -> bool {
+		if block_view.inner_lite.epoch_id != head.inner_lite.epoch_id {
+			return true;
+		}
+		match self {
+			Self::EveryBlock => true,
+			Self::EpochBoundaryOnly => false,
+			Self::Interval(interval) =>
+				block_view.inner_lite.height >= head.inner_lite.height.saturating_add(*interval),
+			Self::OnDemand => has_pending_proof_requests,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn block_view(rpc_response: &str) -> NearLightClientBlockView {
+		let rpc_response: serde_json::Value = serde_json::from_str(rpc_response).unwrap();
+		serde_json::from_value(rpc_response["result"].clone()).unwrap()
+	}
+
+	#[test]
+	fn test_should_submit() {
+		// 86441383 is in the epoch before the one of 86455909 and 86456070
+		let previous_epoch =
+			block_view(include_str!("../../test-fixtures/light_client_block_86441383.json"));
+		let head = block_view(include_str!("../../test-fixtures/light_client_block_86455909.json"));
+		let next = block_view(include_str!("../../test-fixtures/light_client_block_86456070.json"));
+		let policies = [
+			RelayPolicy::EveryBlock,
+			RelayPolicy::EpochBoundaryOnly,
+			RelayPolicy::Interval(1_000),
+			RelayPolicy::OnDemand,
+		];
+
+		for policy in policies {
+			assert!(policy.should_submit(&previous_epoch, &head, false));
+		}
+
+		let same_epoch = policies.map(|policy| policy.should_submit(&head, &next, false));
+		assert_eq!(same_epoch, [true, false, false, false]);
+
+		assert!(RelayPolicy::Interval(161).should_submit(&head, &next, false));
+		assert!(!RelayPolicy::Interval(162).should_submit(&head, &next, false));
+		assert!(RelayPolicy::OnDemand.should_submit(&head, &next, true));
+		assert!(!RelayPolicy::EpochBoundaryOnly.should_submit(&head, &next, true));
+	}
+}
@@ -0,0 +1,446 @@
+//! # Prefetching
+//!
+//! The relayer sends its requests one after the other, so catching up with the chain is bound by
+//! the round-trip latency of the RPC. A `Prefetcher` serves them from responses fetched ahead of
+//! time, concurrently, by an `AsyncBlockchainConnector`:
+//! - the light client blocks following the last one asked for, each one following the previous
+//!   response
+//! - the blocks at the heights following the last one asked for, e.g. by the scanner, and the
+//!   chunks included in every fetched block
+//! - the proofs hinted with `NearRpc::prefetch_light_client_proofs`
+//!
+//! Every prefetched response is handed out once. Requests which have not been prefetched, or
+//! whose prefetching failed, are sent when they are made.
+
+use std::{
+	collections::HashMap,
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+};
+
+use near_sdk::json_types::Base58CryptoHash;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::{
+	runtime::{Handle, Runtime},
+	sync::{mpsc, Semaphore},
+	task::JoinHandle,
+};
+
+use crate::{
+	async_connector::AsyncBlockchainConnector,
+	blockchain_connector::{
+		chunk_params, decode_next_light_client_block, next_light_client_block_params,
+		BlockReference, JsonRpc, RpcBlockResponse,
+	},
+	client_proof::LightClientBlockView,
+	error::RelayerError,
+};
+
+/// prefetched responses kept until they are asked for; when there are more, the ones nobody
+/// asked for are dropped
+const MAX_PREFETCHED_RESPONSES: usize = 1024;
+
+/// How far ahead, and how concurrently, a `Prefetcher` fetches responses
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PrefetchConfig {
+	/// requests in flight at once
+	pub parallelism: usize,
+	/// light client blocks, and blocks, fetched ahead of the last one asked for
+	pub depth: usize,
+}
+
+impl Default for PrefetchConfig {
+	fn default() -> Self {
+		Self { parallelism: 8, depth: 16 }
+	}
+}
+
+/// `JsonRpc` serving prefetched responses, and thereby `NearRpc`.
+///
+/// It runs its own tokio runtime, so it must not be used from async code.
+pub struct Prefetcher {
+	runtime: Runtime,
+	inner: Arc<Inner>,
+	config: PrefetchConfig,
+	light_client_blocks: Mutex<Option<LightClientBlocks>>,
+}
+
+/// state shared with the tasks of the runtime
+struct Inner {
+	rpc: AsyncBlockchainConnector,
+	handle: Handle,
+	/// bounds the requests in flight
+	permits: Semaphore,
+	/// prefetched responses, by method and parameters
+	responses: Mutex<HashMap<String, JoinHandle<Result<Value, RelayerError>>>>,
+}
+
+/// light client blocks fetched one after the other by a task, ahead of the relayer
+struct LightClientBlocks {
+	/// hash of the block the next received response follows
+	next_hash: Base58CryptoHash,
+	/// responses, with the hash of their block
+	receiver: mpsc::Receiver<(Result<Value, RelayerError>, Option<Base58CryptoHash>)>,
+	/// responses received by `receiver` which have not been handed out yet
+	ahead: Arc<AtomicUsize>,
+	task: JoinHandle<()>,
+}
+
+impl Drop for LightClientBlocks {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+impl Prefetcher {
+	pub fn new(
+		rpc: AsyncBlockchainConnector,
+		config: PrefetchConfig,
+	) -> Result<Self, RelayerError> {
+		let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+		let inner = Arc::new(Inner {
+			rpc,
+			handle: runtime.handle().clone(),
+			permits: Semaphore::new(config.parallelism.max(1)),
+			responses: Mutex::default(),
+		});
+		Ok(Self { runtime, inner, config, light_client_blocks: Mutex::default() })
+	}
+
+	pub fn config(&self) -> &PrefetchConfig {
+		&self.config
+	}
+
+	/// responses fetched ahead of time, which have not been asked for yet
+	pub fn prefetched(&self) -> usize {
+		let responses = self.inner.responses.lock().unwrap();
+		let light_client_blocks = self.light_client_blocks.lock().unwrap();
+		responses.values().filter(|handle| handle.is_finished()).count() +
+			light_client_blocks
+				.as_ref()
+				.map_or(0, |blocks| blocks.ahead.load(Ordering::Relaxed))
+	}
+
+	fn serve_light_client_block(&self, params: Value) -> Result<Value, RelayerError> {
+		let last_known_hash: Base58CryptoHash = serde_json::from_value(params[0].clone())?;
+		let mut light_client_blocks = self.light_client_blocks.lock().unwrap();
+		// the relayer doesn't follow the prefetched blocks, e.g. because it rejected one of them
+		let next_hash = light_client_blocks.as_ref().map(|blocks| blocks.next_hash);
+		if next_hash != Some(last_known_hash) {
+			*light_client_blocks = Some(self.follow_light_client_blocks(last_known_hash));
+		}
+		let blocks = light_client_blocks.as_mut().expect("the blocks are followed");
+		let received = self.runtime.block_on(blocks.receiver.recv());
+		blocks.ahead.fetch_sub(received.is_some() as usize, Ordering::Relaxed);
+		match received {
+			Some((Ok(result), Some(next_hash))) => {
+				blocks.next_hash = next_hash;
+				Ok(result)
+			},
+			// the task stops after the latest block known to the node; the relayer asks again
+			// later anyway
+			Some((Ok(result), None)) => {
+				*light_client_blocks = None;
+				Ok(result)
+			},
+			Some((Err(_), _)) | None => {
+				*light_client_blocks = None;
+				self.fetch_now("next_light_client_block", params)
+			},
+		}
+	}
+
+	/// starts a task fetching the light client blocks following `last_known_hash`, until the
+	/// latest one known to the node or up to `depth` blocks ahead of the relayer
+	fn follow_light_client_blocks(&self, last_known_hash: Base58CryptoHash) -> LightClientBlocks {
+		let (sender, receiver) = mpsc::channel(self.config.depth.max(1));
+		let ahead = Arc::new(AtomicUsize::new(0));
+		let task = self.runtime.spawn({
+			let inner = self.inner.clone();
+			let ahead = ahead.clone();
+			let mut last_known_hash = last_known_hash;
+			async move {
+				loop {
+					let params = next_light_client_block_params(last_known_hash);
+					let result =
+						inner.clone().fetch("next_light_client_block".to_owned(), params).await;
+					let next_hash = result.as_ref().ok().and_then(light_client_block_hash);
+					let slot = match sender.reserve().await {
+						Ok(slot) => slot,
+						Err(_) => return,
+					};
+					// counted before it can be received
+					ahead.fetch_add(1, Ordering::Relaxed);
+					slot.send((result, next_hash));
+					match next_hash {
+						Some(next_hash) => last_known_hash = next_hash,
+						None => return,
+					}
+				}
+			}
+		});
+		LightClientBlocks { next_hash: last_known_hash, receiver, ahead, task }
+	}
+
+	fn fetch_now(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+		self.runtime.block_on(self.inner.clone().fetch(method.to_owned(), params))
+	}
+}
+
+impl Inner {
+	/// sends a request once fewer than `parallelism` are in flight; the chunks included in a
+	/// block are prefetched along with it
+	async fn fetch(self: Arc<Self>, method: String, params: Value) -> Result<Value, RelayerError> {
+		let result = {
+			let _permit = self.permits.acquire().await.expect("the semaphore is never closed");
+			self.rpc.call(&method, params).await
+		};
+		if let (Ok(block), "block") = (&result, method.as_str()) {
+			for chunk_id in included_chunks(block) {
+				self.prefetch("chunk", chunk_params(chunk_id));
+			}
+		}
+		result
+	}
+
+	/// sends a request in the background, unless it has already been
+	fn prefetch(self: &Arc<Self>, method: &str, params: Value) {
+		let key = key(method, &params);
+		let mut responses = self.responses.lock().unwrap();
+		if responses.contains_key(&key) {
+			return;
+		}
+		if responses.len() >= MAX_PREFETCHED_RESPONSES {
+			log::debug!("dropping {} prefetched responses nobody asked for", responses.len());
+			responses.drain().for_each(|(_, handle)| handle.abort());
+		}
+		let handle = self.handle.spawn(self.clone().fetch(method.to_owned(), params));
+		responses.insert(key, handle);
+	}
+}
+
+impl JsonRpc for Prefetcher {
+	fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+		if method == "next_light_client_block" {
+			return self.serve_light_client_block(params);
+		}
+		let prefetched = self.inner.responses.lock().unwrap().remove(&key(method, &params));
+		let result = match prefetched.map(|handle| self.runtime.block_on(handle)) {
+			Some(Ok(Ok(result))) => Ok(result),
+			// the request may have been sent too early, e.g. for a block not produced yet
+			_ => self.fetch_now(method, params.clone()),
+		};
+		if let ("block", Some(height)) = (method, params["block_id"].as_u64()) {
+			for height in height + 1..=height + self.config.depth as u64 {
+				self.inner.prefetch("block", BlockReference::Height(height).to_params());
+			}
+		}
+		result
+	}
+
+	fn prefetch(&self, method: &str, params: Value) {
+		self.inner.prefetch(method, params)
+	}
+}
+
+fn key(method: &str, params: &Value) -> String {
+	format!("{} {}", method, params)
+}
+
+/// hash of the light client block of a `next_light_client_block` response, if there is one
+fn light_client_block_hash(result: &Value) -> Option<Base58CryptoHash> {
+	let block_view = decode_next_light_client_block(result.clone()).ok()??;
+	Some(Base58CryptoHash::from(LightClientBlockView::from(block_view).current_block_hash().0))
+}
+
+/// chunks of a `block` response produced at the height of the block
+fn included_chunks(block: &Value) -> Vec<Base58CryptoHash> {
+	match RpcBlockResponse::deserialize(block) {
+		Ok(block) => block
+			.chunks
+			.into_iter()
+			.filter(|chunk| chunk.height_included == block.header.height)
+			.map(|chunk| chunk.chunk_hash)
+			.collect(),
+		Err(_) => Vec::new(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		thread,
+		time::{Duration, Instant},
+	};
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		blockchain_connector::{NearRpc, ProofRequest, RpcEndpoint},
+		mock_rpc::MockRpcServer,
+		retry::RetryPolicy,
+		test_utils::{PROOF, TX_HASH},
+	};
+
+	const CHECKPOINT_HASH: &str = "kobvwf6idnjzf1zUCdU8igL9G9ZUZyexkqVXFSpUVTK";
+	/// blocks 86380519, 86441383, 86455909 and 86456070, with their hashes
+	const BLOCKS: [(&str, &str); 4] = [
+		(
+			"DixB3qV9kRwPDWMKTuhBLM67QgW7bpJ6M5hrZr79kC8F",
+			include_str!("../../test-fixtures/light_client_block_86380519.json"),
+		),
+		(
+			"853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7",
+			include_str!("../../test-fixtures/light_client_block_86441383.json"),
+		),
+		(
+			"9NSyUcAfp7usW87j6dQiRfSdibsLrRtCcwgHyPRMZC3B",
+			include_str!("../../test-fixtures/light_client_block_86455909.json"),
+		),
+		(
+			"ENyzTmZ2qRkxDKcDMCS9hky4T8qKp4t1hDESmcsiqL4V",
+			include_str!("../../test-fixtures/light_client_block_86456070.json"),
+		),
+	];
+
+	fn hash(encoded: &str) -> Base58CryptoHash {
+		serde_json::from_value(json!(encoded)).unwrap()
+	}
+
+	fn prefetcher(server: &MockRpcServer, depth: usize) -> Prefetcher {
+		let rpc = AsyncBlockchainConnector::from_endpoints(
+			vec![RpcEndpoint::new(server.url())],
+			RetryPolicy::no_retry(),
+		);
+		Prefetcher::new(rpc, PrefetchConfig { parallelism: 4, depth }).unwrap()
+	}
+
+	fn wait_for_prefetched(prefetcher: &Prefetcher, count: usize) {
+		let started = Instant::now();
+		while prefetcher.prefetched() < count {
+			assert!(started.elapsed() < Duration::from_secs(10), "{} prefetched", count);
+			thread::sleep(Duration::from_millis(10));
+		}
+	}
+
+	fn block(height: u64, chunks: Value) -> Value {
+		json!({
+			"header": {
+				"height": height,
+				"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+			},
+			"chunks": chunks,
+		})
+	}
+
+	#[test]
+	fn test_prefetch_light_client_blocks() {
+		let server = MockRpcServer::start().unwrap();
+		server.respond_with_recording(
+			"next_light_client_block",
+			json!([CHECKPOINT_HASH]),
+			BLOCKS[0].1,
+		);
+		for window in BLOCKS.windows(2) {
+			server.respond_with_recording(
+				"next_light_client_block",
+				json!([window[0].0]),
+				window[1].1,
+			);
+		}
+		server.respond("next_light_client_block", json!([BLOCKS[3].0]), json!({}));
+		let prefetcher = prefetcher(&server, 8);
+
+		let checkpoint = prefetcher.get_light_client_block_view(hash(CHECKPOINT_HASH)).unwrap();
+		assert_eq!(checkpoint.inner_lite.height, 86380519);
+		// the following blocks, up to the latest one, are served even though the node fails
+		wait_for_prefetched(&prefetcher, 4);
+		server.fail_next(100, 503);
+		let mut heights = Vec::new();
+		for (block_hash, _) in &BLOCKS[..3] {
+			let block_view = prefetcher.get_light_client_block_view(hash(block_hash)).unwrap();
+			heights.push(block_view.inner_lite.height);
+		}
+		assert_eq!(heights, vec![86441383, 86455909, 86456070]);
+		assert!(prefetcher.next_light_client_block(hash(BLOCKS[3].0)).unwrap().is_none());
+		assert_eq!(prefetcher.prefetched(), 0);
+
+		// asking for another block fails
+		assert!(prefetcher.next_light_client_block(hash(BLOCKS[3].0)).is_err());
+	}
+
+	#[test]
+	fn test_prefetch_blocks_and_chunks() {
+		let chunk_hash = "Cx7camxqaUQMLBHmWUrVvbf8JCqq78vCTi4XgnX8gdDX";
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond("block", json!({ "block_id": 100 }), block(100, json!([])))
+			.respond(
+				"block",
+				json!({ "block_id": 101 }),
+				block(
+					101,
+					json!([
+						{ "chunk_hash": chunk_hash, "height_included": 101, "balance_burnt": "1" },
+						{
+							"chunk_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+							"height_included": 100,
+							"balance_burnt": "0",
+						},
+					]),
+				),
+			)
+			.respond("block", json!({ "block_id": 102 }), block(102, json!([])))
+			.respond(
+				"chunk",
+				json!({ "chunk_id": chunk_hash }),
+				json!({ "transactions": [], "receipts": [] }),
+			);
+		let prefetcher = prefetcher(&server, 2);
+
+		assert_eq!(prefetcher.block(BlockReference::Height(100)).unwrap().header.height, 100);
+		// blocks 101 and 102, and the chunk included in block 101
+		wait_for_prefetched(&prefetcher, 3);
+		server.fail_next(100, 503);
+		let block = prefetcher.block(BlockReference::Height(101)).unwrap();
+		assert_eq!(block.chunks.len(), 2);
+		let chunk = prefetcher.chunk(hash(chunk_hash)).unwrap();
+		assert!(chunk.transactions.is_empty());
+		assert_eq!(prefetcher.block(BlockReference::Height(102)).unwrap().header.height, 102);
+		// the requests which have not been prefetched, e.g. for block 103, fail
+		assert!(prefetcher.block(BlockReference::Height(103)).is_err());
+	}
+
+	#[test]
+	fn test_prefetch_hinted_proofs() {
+		let request = ProofRequest::Transaction {
+			tx_hash: hash(TX_HASH),
+			sender_id: "relay.aurora".to_owned(),
+		};
+		let light_client_head = hash("FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr");
+		let server = MockRpcServer::start().unwrap();
+		server.respond_with_recording(
+			"EXPERIMENTAL_light_client_proof",
+			json!({
+				"type": "transaction",
+				"transaction_hash": TX_HASH,
+				"sender_id": "relay.aurora",
+				"light_client_head": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+			}),
+			PROOF,
+		);
+		let prefetcher = prefetcher(&server, 2);
+
+		prefetcher.prefetch_light_client_proofs(light_client_head, &[&request]);
+		wait_for_prefetched(&prefetcher, 1);
+		server.fail_next(100, 503);
+		let proof = prefetcher.light_client_proof(light_client_head, &request).unwrap();
+		let outcome_id = proof.outcome_proof.id.to_string();
+		assert_eq!(outcome_id, TX_HASH);
+	}
+}
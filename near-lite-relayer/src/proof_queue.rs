@@ -0,0 +1,299 @@
+//! # Proof queue
+//!
+//! A proof is only valid once the head of the destination is past the block of its outcome.
+//! Proof requests therefore wait in the store until the head of the sink covers the block of their
+//! outcome; their proof is then fetched against that exact head, and submitted to the sink.
+
+use std::collections::HashMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_lite_client::{CryptoHash as LiteClientCryptoHash, MerklePath, OutcomeProof};
+use near_primitives::{
+	hash::CryptoHash, merkle::compute_root_from_path,
+	views::LightClientBlockView as NearLightClientBlockView,
+};
+use near_sdk::json_types::Base58CryptoHash;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	blockchain_connector::{NearRpc, ProofRequest, RpcLightClientExecutionProofResponse},
+	client_proof::{LightClientBlockLiteView, LightClientBlockView},
+	error::RelayerError,
+	sink::ProofSink,
+	store::RelayerStore,
+};
+
+/// Proof of an outcome, relative to a head known by the destination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionProof {
+	pub request: ProofRequest,
+	/// hash of the head the proof was fetched against
+	pub light_client_head: CryptoHash,
+	pub response: RpcLightClientExecutionProofResponse,
+}
+
+/// `ExecutionProof` in the `near-primitives-wasm` types validated by `near-lite-client`
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct LiteClientProof {
+	pub light_client_head: LiteClientCryptoHash,
+	pub outcome_proof: OutcomeProof,
+	pub outcome_root_proof: MerklePath,
+	/// outcome root of the block of the outcome
+	pub block_outcome_root: LiteClientCryptoHash,
+	/// proof of the block of the outcome in the block merkle tree of the head
+	pub block_proof: MerklePath,
+}
+
+impl ExecutionProof {
+	/// height of the block of the outcome
+	pub fn block_height(&self) -> u64 {
+		self.response.block_header_lite.inner_lite.height
+	}
+
+	/// root of the block merkle tree the block proof leads to, the one of the light client head
+	/// when the proof is valid
+	pub fn block_merkle_root(&self) -> CryptoHash {
+		let block_header_lite = &self.response.block_header_lite;
+		let block_hash = LightClientBlockLiteView {
+			prev_block_hash: block_header_lite.prev_block_hash,
+			inner_rest_hash: block_header_lite.inner_rest_hash,
+			inner_lite: block_header_lite.inner_lite.clone().into(),
+		}
+		.current_block_hash();
+		compute_root_from_path(&self.response.block_proof, block_hash)
+	}
+
+	pub fn to_lite_client(&self) -> LiteClientProof {
+		let response = self.response.clone();
+		LiteClientProof {
+			light_client_head: self.light_client_head.into(),
+			outcome_proof: response.outcome_proof.into(),
+			outcome_root_proof: response.outcome_root_proof.into_iter().map(Into::into).collect(),
+			block_outcome_root: response.block_header_lite.inner_lite.outcome_root.into(),
+			block_proof: response.block_proof.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// Proof requests are queued in the `RelayerStore`; the queue only remembers the heights of the
+/// blocks of their outcomes, which are found again after a restart
+#[derive(Debug, Default)]
+pub struct ProofQueue {
+	/// block height of the outcome, by request id
+	outcome_heights: HashMap<u64, u64>,
+}
+
+impl ProofQueue {
+	/// Submits the proofs of the requests queued in `store` whose outcome is covered by `head`,
+	/// the latest header submitted to `sink`, and removes them from the queue. Returns how many
+	/// proofs have been submitted.
+	///
+	/// A request failing, e.g. because its outcome doesn't exist yet or because the sink rejects
+	/// its proof, stays queued.
+	pub fn process(
+		&mut self,
+		rpc: &impl NearRpc,
+		store: &mut impl RelayerStore,
+		sink: &mut impl ProofSink,
+		head: &NearLightClientBlockView,
+	) -> Result<usize, RelayerError> {
+		let head_hash = LightClientBlockView::from(head.clone()).current_block_hash();
+		let requests = store.proof_requests()?;
+		// the proofs locating new outcomes, and then the proofs of the covered ones, are hinted
+		// all at once so that an RPC prefetching them doesn't wait for each one in turn
+		let new_requests = requests
+			.iter()
+			.filter(|(id, _)| !self.outcome_heights.contains_key(id))
+			.map(|(_, request)| request)
+			.collect::<Vec<_>>();
+		let final_block_hash = if new_requests.is_empty() {
+			None
+		} else {
+			rpc.get_almost_latest_finalized_block_hash_and_height()
+				.ok()
+				.map(|(hash, _)| hash)
+		};
+		if let Some(final_block_hash) = final_block_hash {
+			rpc.prefetch_light_client_proofs(final_block_hash, &new_requests);
+		}
+
+		let mut covered = Vec::new();
+		for (id, request) in &requests {
+			let outcome_height = match self.outcome_height(rpc, *id, request, final_block_hash) {
+				Ok(outcome_height) => outcome_height,
+				Err(err) => {
+					log::warn!("no outcome for proof request id={} yet: {}", id, err);
+					continue;
+				},
+			};
+			// the block merkle tree of the head only covers the blocks before it
+			if outcome_height < head.inner_lite.height {
+				covered.push((*id, request));
+			}
+		}
+		let covered_requests = covered.iter().map(|(_, request)| *request).collect::<Vec<_>>();
+		rpc.prefetch_light_client_proofs(Base58CryptoHash::from(head_hash.0), &covered_requests);
+
+		let mut submitted = 0;
+		for (id, request) in covered {
+			match self.submit(rpc, sink, head_hash, request) {
+				Ok(()) => {
+					log::info!("submitted proof of request id={}", id);
+					store.remove_proof_request(id)?;
+					self.outcome_heights.remove(&id);
+					submitted += 1;
+				},
+				Err(err) => log::warn!("proof request id={} failed: {}", id, err),
+			}
+		}
+		Ok(submitted)
+	}
+
+	/// height of the block of the outcome, found in a proof against the latest final block, which
+	/// is fetched unless it is given
+	fn outcome_height(
+		&mut self,
+		rpc: &impl NearRpc,
+		id: u64,
+		request: &ProofRequest,
+		final_block_hash: Option<Base58CryptoHash>,
+	) -> Result<u64, RelayerError> {
+		if let Some(outcome_height) = self.outcome_heights.get(&id) {
+			return Ok(*outcome_height);
+		}
+		let final_block_hash = match final_block_hash {
+			Some(final_block_hash) => final_block_hash,
+			None => rpc.get_almost_latest_finalized_block_hash_and_height()?.0,
+		};
+		let outcome_height = rpc
+			.light_client_proof(final_block_hash, request)?
+			.block_header_lite
+			.inner_lite
+			.height;
+		self.outcome_heights.insert(id, outcome_height);
+		Ok(outcome_height)
+	}
+
+	fn submit(
+		&self,
+		rpc: &impl NearRpc,
+		sink: &mut impl ProofSink,
+		head_hash: CryptoHash,
+		request: &ProofRequest,
+	) -> Result<(), RelayerError> {
+		let response = rpc.light_client_proof(Base58CryptoHash::from(head_hash.0), request)?;
+		let proof =
+			ExecutionProof { request: request.clone(), light_client_head: head_hash, response };
+		sink.submit_proof(proof)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		blockchain_connector::BlockchainConnector,
+		mock_rpc::MockRpcServer,
+		sink::LiteClientSink,
+		store::MemoryStore,
+		test_utils::{head, proof_head, FINAL_BLOCK_PREV_HASH, PROOF, TX_HASH},
+	};
+
+	fn head_hash(head: &NearLightClientBlockView) -> String {
+		LightClientBlockView::from(head.clone()).current_block_hash().to_string()
+	}
+
+	fn request() -> ProofRequest {
+		ProofRequest::Transaction {
+			tx_hash: Base58CryptoHash::try_from(TX_HASH).unwrap(),
+			sender_id: "relay.aurora".to_string(),
+		}
+	}
+
+	/// serves the proof for the latest final block and for `heads`
+	fn mock_rpc_server(heads: &[&NearLightClientBlockView]) -> MockRpcServer {
+		let server = MockRpcServer::start().unwrap();
+		server.respond(
+			"block",
+			json!({ "finality": "final" }),
+			json!({ "header": { "height": 86698268, "prev_hash": FINAL_BLOCK_PREV_HASH }, "chunks": [] }),
+		);
+		let light_client_heads = heads
+			.iter()
+			.map(|head| head_hash(head))
+			.chain([FINAL_BLOCK_PREV_HASH.to_string()]);
+		for light_client_head in light_client_heads {
+			server.respond_with_recording(
+				"EXPERIMENTAL_light_client_proof",
+				json!({
+					"type": "transaction",
+					"transaction_hash": TX_HASH,
+					"sender_id": "relay.aurora",
+					"light_client_head": light_client_head,
+				}),
+				PROOF,
+			);
+		}
+		server
+	}
+
+	#[test]
+	fn test_wait_for_the_head() {
+		// the outcome is in the block 86697768
+		let (early_head, head) = (proof_head(86697768), proof_head(86697769));
+		let server = mock_rpc_server(&[&head]);
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let mut store = MemoryStore::default();
+		let mut sink = LiteClientSink::new(head.clone()).unwrap();
+		let mut queue = ProofQueue::default();
+
+		let id = store.enqueue_proof_request(request()).unwrap();
+		assert_eq!(queue.process(&rpc, &mut store, &mut sink, &early_head).unwrap(), 0);
+		assert_eq!(store.proof_requests().unwrap(), vec![(id, request())]);
+
+		assert_eq!(queue.process(&rpc, &mut store, &mut sink, &head).unwrap(), 1);
+		assert!(store.proof_requests().unwrap().is_empty());
+		assert_eq!(sink.proofs().len(), 1);
+		assert_eq!(sink.proofs()[0].light_client_head.to_string(), head_hash(&head));
+	}
+
+	#[test]
+	fn test_rejected_proof_stays_queued() {
+		// the block proof of the node doesn't lead to the block merkle root of this head
+		let (other_head, head) = (head(86697769), proof_head(86697769));
+		let server = mock_rpc_server(&[&other_head, &head]);
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let mut store = MemoryStore::default();
+		let mut sink = LiteClientSink::new(other_head.clone()).unwrap();
+		let mut queue = ProofQueue::default();
+
+		let id = store.enqueue_proof_request(request()).unwrap();
+		assert_eq!(queue.process(&rpc, &mut store, &mut sink, &other_head).unwrap(), 0);
+		assert_eq!(store.proof_requests().unwrap(), vec![(id, request())]);
+		assert!(sink.proofs().is_empty());
+
+		let mut sink = LiteClientSink::new(head.clone()).unwrap();
+		assert_eq!(queue.process(&rpc, &mut store, &mut sink, &head).unwrap(), 1);
+		assert!(store.proof_requests().unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_unknown_outcome_stays_queued() {
+		let server = mock_rpc_server(&[]);
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let mut store = MemoryStore::default();
+		let head = proof_head(86697769);
+		let mut sink = LiteClientSink::new(head.clone()).unwrap();
+		let mut queue = ProofQueue::default();
+
+		let unknown_request = ProofRequest::Receipt {
+			receipt_id: Base58CryptoHash::try_from(TX_HASH).unwrap(),
+			receiver_id: "aurora".to_string(),
+		};
+		store.enqueue_proof_request(unknown_request).unwrap();
+		assert_eq!(queue.process(&rpc, &mut store, &mut sink, &head).unwrap(), 0);
+		assert_eq!(store.proof_requests().unwrap().len(), 1);
+	}
+}
@@ -0,0 +1,738 @@
+//! # Relayer
+//!
+//! Follows the chain from a trusted checkpoint: fetches the light client block following the
+//! head, pre-validates it and submits it to a `HeaderSink`. Once the head covers the outcomes of
+//! the queued proof requests, their proofs are submitted to the same sink. Proof requests are
+//! queued by hand, or for the activity of the accounts of an `AccountScanner`. With a
+//! `CrossChecker`, the blocks are compared to the ones served by its witnesses first.
+
+use std::{
+	sync::atomic::{AtomicBool, Ordering},
+	thread,
+	time::{Duration, Instant},
+};
+
+use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+use near_sdk::json_types::Base58CryptoHash;
+
+use crate::{
+	blockchain_connector::{BlockReference, NearRpc, ProofRequest},
+	cross_check::{CrossChecker, Misbehaviour},
+	error::RelayerError,
+	metrics::RelayerMetrics,
+	policy::RelayPolicy,
+	proof_queue::ProofQueue,
+	scanner::AccountScanner,
+	sink::{HeaderSink, ProofSink},
+	state::LightClientState,
+	store::{MemoryStore, RelayerStore, Submission},
+};
+
+/// blocks scanned by a `Relayer::scan` at most, so that following the chain is not delayed
+pub const MAX_SCANNED_BLOCKS: u64 = 100;
+
+/// Outcome of a `Relayer::step`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+	/// the block at this height has been validated and submitted
+	Submitted(u64),
+	/// the node doesn't know a more recent final block
+	UpToDate,
+	/// the block at this height is not valid, and has not been submitted
+	Rejected(u64),
+	/// the block at this height is valid, but the policy doesn't submit it
+	Skipped(u64),
+	/// a witness serves another valid block at this height, and none of them is submitted
+	Misbehaviour(u64),
+}
+
+pub struct Relayer<R, S, T = MemoryStore> {
+	rpc: R,
+	sink: S,
+	state: LightClientState,
+	/// where the state is saved after every submission
+	store: T,
+	policy: RelayPolicy,
+	proof_queue: ProofQueue,
+	scanner: Option<AccountScanner>,
+	metrics: Option<RelayerMetrics>,
+	cross_checker: Option<CrossChecker>,
+	/// evidence of the equivocations found by the cross checker
+	misbehaviours: Vec<Misbehaviour>,
+}
+
+impl<R: NearRpc, S: HeaderSink> Relayer<R, S> {
+	pub fn new(rpc: R, sink: S, state: LightClientState) -> Self {
+		Self {
+			rpc,
+			sink,
+			state,
+			store: MemoryStore::default(),
+			policy: RelayPolicy::default(),
+			proof_queue: ProofQueue::default(),
+			scanner: None,
+			metrics: None,
+			cross_checker: None,
+			misbehaviours: Vec::new(),
+		}
+	}
+}
+
+impl<R: NearRpc, S: HeaderSink, T: RelayerStore> Relayer<R, S, T> {
+	/// Resumes from the state saved in `store`, or from the latest head known by the sink, or
+	/// starts from the trusted checkpoint: the light client block following `checkpoint_hash`. The
+	/// state is saved to `store` after every submission.
+	///
+	/// A crash between a submission and the save of the state makes the relayer submit the same
+	/// header again once resumed.
+	pub fn resume_or_start(
+		rpc: R,
+		sink: S,
+		store: T,
+		checkpoint_hash: Base58CryptoHash,
+	) -> Result<Self, RelayerError> {
+		let state = if let Some(state) = store.load_state()? {
+			log::info!("resuming from head height={}", state.head().inner_lite.height);
+			state
+		} else if let Some(head) = sink.latest_known_head()? {
+			log::info!("resuming from the head of the sink height={}", head.inner_lite.height);
+			LightClientState::new_from_checkpoint(head)
+		} else {
+			let checkpoint = rpc.get_light_client_block_view(checkpoint_hash)?;
+			log::info!("starting from checkpoint height={}", checkpoint.inner_lite.height);
+			LightClientState::new_from_checkpoint(checkpoint)
+		};
+		Ok(Self {
+			rpc,
+			sink,
+			state,
+			store,
+			policy: RelayPolicy::default(),
+			proof_queue: ProofQueue::default(),
+			scanner: None,
+			metrics: None,
+			cross_checker: None,
+			misbehaviours: Vec::new(),
+		})
+	}
+
+	pub fn with_policy(self, policy: RelayPolicy) -> Self {
+		Self { policy, ..self }
+	}
+
+	/// queues proof requests for the activity of the accounts of `scanner`, see `scan`
+	pub fn with_scanner(self, scanner: AccountScanner) -> Self {
+		Self { scanner: Some(scanner), ..self }
+	}
+
+	/// reports the head, the submissions, the rejected headers and the proof queue in `metrics`,
+	/// see `update_metrics`
+	pub fn with_metrics(self, metrics: RelayerMetrics) -> Self {
+		Self { metrics: Some(metrics), ..self }
+	}
+
+	/// compares the blocks following the head to the ones served by the witnesses of
+	/// `cross_checker`, see `misbehaviours`
+	pub fn with_cross_checker(self, cross_checker: CrossChecker) -> Self {
+		Self { cross_checker: Some(cross_checker), ..self }
+	}
+
+	pub fn state(&self) -> &LightClientState {
+		&self.state
+	}
+
+	pub fn sink(&self) -> &S {
+		&self.sink
+	}
+
+	pub fn store(&self) -> &T {
+		&self.store
+	}
+
+	/// evidence of the equivocations found by the cross checker, if any
+	pub fn misbehaviours(&self) -> &[Misbehaviour] {
+		&self.misbehaviours
+	}
+
+	/// queues a proof request, whose proof is submitted once the head covers its outcome. Returns
+	/// the id of the request.
+	pub fn enqueue_proof_request(&mut self, request: ProofRequest) -> Result<u64, RelayerError> {
+		self.store.enqueue_proof_request(request)
+	}
+
+	/// Scans up to `MAX_SCANNED_BLOCKS` blocks following the last scanned one, or starting at the
+	/// head, and queues proof requests for the activity of the accounts of the scanner. Returns
+	/// how many requests have been queued.
+	///
+	/// A crash between queuing the requests of a block and saving it as scanned makes the relayer
+	/// queue them again once resumed.
+	pub fn scan(&mut self) -> Result<usize, RelayerError> {
+		let scanner = match &self.scanner {
+			Some(scanner) => scanner,
+			None => return Ok(0),
+		};
+		let from = match self.store.scanned_height()? {
+			Some(height) => height + 1,
+			None => self.state.head().inner_lite.height,
+		};
+		// the outcomes are read from proofs against the block before the final one, which only
+		// covers the blocks before it
+		let (_, final_height) = self.rpc.get_almost_latest_finalized_block_hash_and_height()?;
+		let until = final_height.saturating_sub(1).min(from + MAX_SCANNED_BLOCKS);
+
+		let mut queued = 0;
+		for height in from..until {
+			for activity in scanner.scan_block(&self.rpc, height)? {
+				log::info!(
+					"queuing proof of {:?} in block height={} (success={})",
+					activity.request,
+					height,
+					activity.is_success()
+				);
+				self.store.enqueue_proof_request(activity.request)?;
+				queued += 1;
+			}
+			self.store.save_scanned_height(height)?;
+		}
+		Ok(queued)
+	}
+
+	/// fetches the block following the head and, if it is valid and the policy says so, submits
+	/// it and makes it the new head
+	pub fn step(&mut self) -> Result<Step, RelayerError> {
+		let block_view = match self
+			.rpc
+			.next_light_client_block(Base58CryptoHash::from(self.state.head_hash().0))?
+		{
+			Some(block_view) if block_view.inner_lite.height > self.state.head_height() =>
+				block_view,
+			_ => {
+				self.cross_check(None);
+				return Ok(Step::UpToDate)
+			},
+		};
+		let height = block_view.inner_lite.height;
+
+		// the head only moves once the block has been submitted
+		let mut state = self.state.clone();
+		match state.validate_head(&block_view) {
+			Ok(()) => {},
+			Err(RelayerError::InvalidHeader { reason, message }) => {
+				log::warn!("rejecting invalid header height={} ({}): {}", height, reason, message);
+				if let Some(metrics) = &self.metrics {
+					metrics.inc_validation_failures(reason);
+				}
+				return Ok(Step::Rejected(height));
+			},
+			Err(err) => return Err(err),
+		}
+		if self.cross_check(Some(&block_view)) {
+			return Ok(Step::Misbehaviour(height))
+		}
+		let has_pending_proof_requests = !self.store.proof_requests()?.is_empty();
+		if !self
+			.policy
+			.should_submit(self.state.head(), &block_view, has_pending_proof_requests)
+		{
+			log::debug!("skipping header height={}", height);
+			return Ok(Step::Skipped(height));
+		}
+		let submission = Submission::new(height, state.head_hash(), state.epoch_id());
+		self.sink.submit(block_view)?;
+		self.state = state;
+		if let Some(metrics) = &self.metrics {
+			metrics.inc_header_submissions(self.sink.name());
+			metrics.observe_head(&self.state);
+		}
+		self.store.save_submission(&self.state, submission)?;
+		Ok(Step::Submitted(height))
+	}
+
+	/// Compares `block_view`, the valid block following the head if any, to the ones served by
+	/// the witnesses, raising their alerts. Returns whether a witness serves another valid block
+	/// at the same height, whose evidence is kept.
+	fn cross_check(&mut self, block_view: Option<&NearLightClientBlockView>) -> bool {
+		let report = match &self.cross_checker {
+			Some(cross_checker) => cross_checker.check(&self.state, block_view),
+			None => return false,
+		};
+		for (witness, alert, outcome) in report.alerts() {
+			log::error!("witness {} raised an alert ({}): {:?}", witness, alert, outcome);
+			if let Some(metrics) = &self.metrics {
+				metrics.inc_cross_check_alerts(witness, alert);
+			}
+		}
+		let mut equivocated = false;
+		for misbehaviour in report.misbehaviours() {
+			equivocated = true;
+			if !self.misbehaviours.contains(misbehaviour) {
+				self.misbehaviours.push(misbehaviour.clone());
+			}
+		}
+		equivocated
+	}
+
+	/// reports the head, the latest final block of the node and the depth of the proof queue in
+	/// the metrics, if any
+	pub fn update_metrics(&self) -> Result<(), RelayerError> {
+		let metrics = match &self.metrics {
+			Some(metrics) => metrics,
+			None => return Ok(()),
+		};
+		metrics.observe_head(&self.state);
+		metrics.set_proof_queue_depth(self.store.proof_requests()?.len());
+		let header = self.rpc.block(BlockReference::Final)?.header;
+		metrics.observe_final_height(header.height);
+		Ok(())
+	}
+}
+
+impl<R: NearRpc, S: HeaderSink + ProofSink, T: RelayerStore> Relayer<R, S, T> {
+	/// submits the proofs of the queued requests whose outcome is covered by the head. Returns
+	/// how many proofs have been submitted.
+	pub fn process_proof_requests(&mut self) -> Result<usize, RelayerError> {
+		let submitted = self.proof_queue.process(
+			&self.rpc,
+			&mut self.store,
+			&mut self.sink,
+			self.state.head(),
+		)?;
+		if let Some(metrics) = &self.metrics {
+			metrics.inc_proof_submissions(self.sink.name(), submitted);
+		}
+		Ok(submitted)
+	}
+
+	/// steps, scans and processes the proof requests until `shutdown` is set, waiting for
+	/// `poll_interval` whenever the head is up to date or the step fails
+	pub fn run(&mut self, poll_interval: Duration, shutdown: &AtomicBool) {
+		while !shutdown.load(Ordering::Relaxed) {
+			let step = self.step();
+			if let Err(err) = self.scan() {
+				log::error!("scanning failed: {}", err);
+			}
+			if let Err(err) = self.process_proof_requests() {
+				log::error!("processing the proof requests failed: {}", err);
+			}
+			if let Err(err) = self.update_metrics() {
+				log::warn!("updating the metrics failed: {}", err);
+			}
+			match step {
+				Ok(Step::Submitted(height)) => {
+					log::info!("submitted header height={}", height);
+					continue;
+				},
+				Ok(Step::UpToDate) |
+				Ok(Step::Rejected(_)) |
+				Ok(Step::Skipped(_)) |
+				Ok(Step::Misbehaviour(_)) => {},
+				Err(err) => log::error!("relaying failed: {}", err),
+			}
+
+			let deadline = Instant::now() + poll_interval;
+			while !shutdown.load(Ordering::Relaxed) && Instant::now() < deadline {
+				thread::sleep(Duration::from_millis(100).min(poll_interval));
+			}
+		}
+		log::info!("stopped at head height={}", self.state.head().inner_lite.height);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs, sync::Arc};
+
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		async_connector::AsyncBlockchainConnector,
+		blockchain_connector::BlockchainConnector,
+		mock_rpc::MockRpcServer,
+		prefetch::{PrefetchConfig, Prefetcher},
+		proof_queue::ExecutionProof,
+		store::SledStore,
+		test_utils::{FINAL_BLOCK_PREV_HASH, PROOF, TX_HASH},
+	};
+
+	/// blocks 86380519 (checkpoint), 86441383, 86455909 and 86456070, with their hashes
+	const BLOCKS: [(&str, &str); 4] = [
+		(
+			"DixB3qV9kRwPDWMKTuhBLM67QgW7bpJ6M5hrZr79kC8F",
+			include_str!("../../test-fixtures/light_client_block_86380519.json"),
+		),
+		(
+			"853G4n846XdQNpYwM6Y2LwSWExro91Bkbf3dHdaRV3g7",
+			include_str!("../../test-fixtures/light_client_block_86441383.json"),
+		),
+		(
+			"9NSyUcAfp7usW87j6dQiRfSdibsLrRtCcwgHyPRMZC3B",
+			include_str!("../../test-fixtures/light_client_block_86455909.json"),
+		),
+		(
+			"ENyzTmZ2qRkxDKcDMCS9hky4T8qKp4t1hDESmcsiqL4V",
+			include_str!("../../test-fixtures/light_client_block_86456070.json"),
+		),
+	];
+	const CHECKPOINT_HASH: &str = "kobvwf6idnjzf1zUCdU8igL9G9ZUZyexkqVXFSpUVTK";
+
+	#[derive(Default)]
+	struct VecSink(Vec<NearLightClientBlockView>);
+
+	impl HeaderSink for VecSink {
+		fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+			Ok(self.0.last().cloned())
+		}
+
+		fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError> {
+			self.0.push(block_view);
+			Ok(())
+		}
+	}
+
+	impl ProofSink for VecSink {
+		fn submit_proof(&mut self, _proof: ExecutionProof) -> Result<(), RelayerError> {
+			Ok(())
+		}
+	}
+
+	/// serves the blocks following each other, the last one being the latest final block
+	fn mock_rpc_server() -> MockRpcServer {
+		let server = MockRpcServer::start().unwrap();
+		server.respond_with_recording(
+			"next_light_client_block",
+			json!([CHECKPOINT_HASH]),
+			BLOCKS[0].1,
+		);
+		for window in BLOCKS.windows(2) {
+			server.respond_with_recording(
+				"next_light_client_block",
+				json!([window[0].0]),
+				window[1].1,
+			);
+		}
+		server.respond("next_light_client_block", json!([BLOCKS[3].0]), json!({}));
+		server
+	}
+
+	fn heights(sink: &VecSink) -> Vec<u64> {
+		sink.0.iter().map(|block_view| block_view.inner_lite.height).collect()
+	}
+
+	fn checkpoint_hash() -> Base58CryptoHash {
+		Base58CryptoHash::try_from(CHECKPOINT_HASH).unwrap()
+	}
+
+	fn block(height: u64, chunks: serde_json::Value) -> serde_json::Value {
+		json!({ "header": { "height": height, "prev_hash": CHECKPOINT_HASH }, "chunks": chunks })
+	}
+
+	fn relayer(server: &MockRpcServer) -> Relayer<BlockchainConnector, VecSink> {
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let checkpoint = rpc.get_light_client_block_view(checkpoint_hash()).unwrap();
+		Relayer::new(rpc, VecSink::default(), LightClientState::new_from_checkpoint(checkpoint))
+	}
+
+	#[test]
+	fn test_follow_the_chain() {
+		let server = mock_rpc_server();
+		let mut relayer = relayer(&server);
+
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86455909));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86456070));
+		assert_eq!(relayer.step().unwrap(), Step::UpToDate);
+		assert_eq!(heights(relayer.sink()), vec![86441383, 86455909, 86456070]);
+		assert_eq!(relayer.state().head().inner_lite.height, 86456070);
+	}
+
+	#[test]
+	fn test_follow_the_chain_with_prefetching() {
+		let server = mock_rpc_server();
+		let rpc = AsyncBlockchainConnector::with_rpc_url(server.url());
+		let prefetcher = Prefetcher::new(rpc, PrefetchConfig::default()).unwrap();
+		let mut relayer = Relayer::resume_or_start(
+			prefetcher,
+			VecSink::default(),
+			MemoryStore::default(),
+			checkpoint_hash(),
+		)
+		.unwrap();
+
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86455909));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86456070));
+		assert_eq!(relayer.step().unwrap(), Step::UpToDate);
+		assert_eq!(heights(relayer.sink()), vec![86441383, 86455909, 86456070]);
+	}
+
+	#[test]
+	fn test_reject_invalid_block() {
+		let mut tampered_block = serde_json::from_str::<serde_json::Value>(BLOCKS[2].1).unwrap();
+		tampered_block["result"]["inner_lite"]["prev_state_root"] =
+			json!("11111111111111111111111111111111");
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond_with_recording(
+				"next_light_client_block",
+				json!([CHECKPOINT_HASH]),
+				BLOCKS[0].1,
+			)
+			.respond_with_recording("next_light_client_block", json!([BLOCKS[0].0]), BLOCKS[1].1)
+			.respond(
+				"next_light_client_block",
+				json!([BLOCKS[1].0]),
+				tampered_block["result"].clone(),
+			);
+		let mut relayer = relayer(&server);
+
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Rejected(86455909));
+		assert_eq!(relayer.step().unwrap(), Step::Rejected(86455909));
+		assert_eq!(heights(relayer.sink()), vec![86441383]);
+		assert_eq!(relayer.state().head().inner_lite.height, 86441383);
+	}
+
+	#[test]
+	fn test_metrics() {
+		let mut tampered_block = serde_json::from_str::<serde_json::Value>(BLOCKS[2].1).unwrap();
+		tampered_block["result"]["inner_lite"]["prev_state_root"] =
+			json!("11111111111111111111111111111111");
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond_with_recording(
+				"next_light_client_block",
+				json!([CHECKPOINT_HASH]),
+				BLOCKS[0].1,
+			)
+			.respond_with_recording("next_light_client_block", json!([BLOCKS[0].0]), BLOCKS[1].1)
+			.respond(
+				"next_light_client_block",
+				json!([BLOCKS[1].0]),
+				tampered_block["result"].clone(),
+			)
+			.respond(
+				"block",
+				json!({ "finality": "final" }),
+				json!({
+					"header": {
+						"height": 86441483,
+						"prev_hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+					},
+					"chunks": [],
+				}),
+			);
+		let metrics = RelayerMetrics::new();
+		let rpc = BlockchainConnector::with_rpc_url(server.url()).with_metrics(metrics.clone());
+		let checkpoint = rpc.get_light_client_block_view(checkpoint_hash()).unwrap();
+		let mut relayer = Relayer::new(
+			rpc,
+			VecSink::default(),
+			LightClientState::new_from_checkpoint(checkpoint),
+		)
+		.with_metrics(metrics.clone());
+		relayer
+			.enqueue_proof_request(ProofRequest::Transaction {
+				tx_hash: checkpoint_hash(),
+				sender_id: "relay.aurora".to_owned(),
+			})
+			.unwrap();
+
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Rejected(86455909));
+		relayer.update_metrics().unwrap();
+
+		let encoded = metrics.encode();
+		let endpoint = server.url();
+		for line in [
+			"near_lite_relayer_head_height 86441383".to_owned(),
+			"near_lite_relayer_final_height 86441483".to_owned(),
+			"near_lite_relayer_head_lag 100".to_owned(),
+			"near_lite_relayer_proof_queue_depth 1".to_owned(),
+			r#"near_lite_relayer_submissions_total{kind="header",sink="custom"} 1"#.to_owned(),
+			r#"near_lite_relayer_validation_failures_total{reason="invalid_signature"} 1"#
+				.to_owned(),
+			format!(
+				r#"near_lite_relayer_rpc_requests_total{{endpoint="{}",method="next_light_client_block"}} 3"#,
+				endpoint
+			),
+			format!(
+				r#"near_lite_relayer_rpc_requests_total{{endpoint="{}",method="block"}} 1"#,
+				endpoint
+			),
+		] {
+			assert!(encoded.lines().any(|encoded| encoded == line), "no {} in {}", line, encoded);
+		}
+		let epoch_id = relayer.state().epoch_id();
+		assert!(encoded
+			.contains(&format!(r#"near_lite_relayer_head_epoch{{epoch_id="{}"}} 1"#, epoch_id)));
+	}
+
+	#[test]
+	fn test_epoch_boundary_only() {
+		let server = mock_rpc_server();
+		let mut relayer = relayer(&server).with_policy(RelayPolicy::EpochBoundaryOnly);
+
+		// 86456070 is in the same epoch as 86455909
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86455909));
+		assert_eq!(relayer.step().unwrap(), Step::Skipped(86456070));
+		assert_eq!(relayer.step().unwrap(), Step::Skipped(86456070));
+		assert_eq!(relayer.state().head().inner_lite.height, 86455909);
+	}
+
+	#[test]
+	fn test_on_demand() {
+		let server = mock_rpc_server();
+		let mut relayer = relayer(&server).with_policy(RelayPolicy::OnDemand);
+
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86455909));
+		assert_eq!(relayer.step().unwrap(), Step::Skipped(86456070));
+
+		relayer
+			.enqueue_proof_request(ProofRequest::Transaction {
+				tx_hash: checkpoint_hash(),
+				sender_id: "relay.aurora".to_string(),
+			})
+			.unwrap();
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86456070));
+	}
+
+	#[test]
+	fn test_scan() {
+		let server = mock_rpc_server();
+		server
+			.respond("block", json!({ "finality": "final" }), block(86380522, json!([])))
+			.respond(
+				"block",
+				json!({ "block_id": 86380519 }),
+				block(
+					86380519,
+					json!([{
+						"chunk_hash": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8",
+						"height_included": 86380519,
+						"balance_burnt": "0",
+					}]),
+				),
+			)
+			.respond("block", json!({ "block_id": 86380520 }), block(86380520, json!([])))
+			.respond(
+				"chunk",
+				json!({ "chunk_id": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8" }),
+				json!({
+					"transactions": [
+						{ "hash": TX_HASH, "signer_id": "relay.aurora", "receiver_id": "aurora" },
+					],
+					"receipts": [],
+				}),
+			)
+			.respond_with_recording(
+				"EXPERIMENTAL_light_client_proof",
+				json!({
+					"type": "transaction",
+					"transaction_hash": TX_HASH,
+					"sender_id": "relay.aurora",
+					"light_client_head": FINAL_BLOCK_PREV_HASH,
+				}),
+				PROOF,
+			);
+
+		let mut relayer = relayer(&server);
+		assert_eq!(relayer.scan().unwrap(), 0);
+
+		let mut relayer = relayer.with_scanner(AccountScanner::new(["aurora"]));
+		assert_eq!(relayer.scan().unwrap(), 1);
+		assert_eq!(relayer.store().scanned_height().unwrap(), Some(86380520));
+		assert_eq!(
+			relayer.store().proof_requests().unwrap(),
+			vec![(
+				0,
+				ProofRequest::Transaction {
+					tx_hash: Base58CryptoHash::try_from(TX_HASH).unwrap(),
+					sender_id: "relay.aurora".to_string(),
+				}
+			)]
+		);
+		// up to date
+		assert_eq!(relayer.scan().unwrap(), 0);
+	}
+
+	#[test]
+	fn test_resume() {
+		let store_path =
+			env::temp_dir().join(format!("near-lite-relayer-store-{}", std::process::id()));
+		let _ = fs::remove_dir_all(&store_path);
+		let server = mock_rpc_server();
+		let resume_or_start = || {
+			Relayer::resume_or_start(
+				BlockchainConnector::with_rpc_url(server.url()),
+				VecSink::default(),
+				SledStore::open(&store_path, 8).unwrap(),
+				checkpoint_hash(),
+			)
+			.unwrap()
+		};
+
+		let mut first_run = resume_or_start();
+		assert_eq!(first_run.state().head().inner_lite.height, 86380519);
+		assert_eq!(first_run.step().unwrap(), Step::Submitted(86441383));
+		assert_eq!(first_run.step().unwrap(), Step::Submitted(86455909));
+		drop(first_run);
+
+		let mut second_run = resume_or_start();
+		assert_eq!(second_run.state().head().inner_lite.height, 86455909);
+		assert_eq!(second_run.step().unwrap(), Step::Submitted(86456070));
+		assert_eq!(heights(second_run.sink()), vec![86456070]);
+		let submitted_heights = second_run
+			.store()
+			.submissions()
+			.unwrap()
+			.iter()
+			.map(|submission| submission.height)
+			.collect::<Vec<_>>();
+		assert_eq!(submitted_heights, vec![86441383, 86455909, 86456070]);
+		drop(second_run);
+
+		fs::remove_dir_all(&store_path).unwrap();
+	}
+
+	#[test]
+	fn test_resume_from_sink() {
+		let server = mock_rpc_server();
+		let sink_head: NearLightClientBlockView = serde_json::from_value(
+			serde_json::from_str::<serde_json::Value>(BLOCKS[1].1).unwrap()["result"].clone(),
+		)
+		.unwrap();
+
+		let mut relayer = Relayer::resume_or_start(
+			BlockchainConnector::with_rpc_url(server.url()),
+			VecSink(vec![sink_head]),
+			MemoryStore::default(),
+			checkpoint_hash(),
+		)
+		.unwrap();
+		assert_eq!(relayer.state().head().inner_lite.height, 86441383);
+		assert_eq!(relayer.step().unwrap(), Step::Submitted(86455909));
+	}
+
+	#[test]
+	fn test_run_until_shutdown() {
+		let server = mock_rpc_server();
+		let mut relayer = relayer(&server);
+		let shutdown = Arc::new(AtomicBool::new(false));
+
+		let handle = {
+			let shutdown = shutdown.clone();
+			thread::spawn(move || {
+				relayer.run(Duration::from_millis(10), &shutdown);
+				relayer
+			})
+		};
+		thread::sleep(Duration::from_millis(500));
+		shutdown.store(true, Ordering::Relaxed);
+		let relayer = handle.join().unwrap();
+
+		assert_eq!(heights(relayer.sink()), vec![86441383, 86455909, 86456070]);
+	}
+}
@@ -0,0 +1,206 @@
+//! # Recording and replay of JSON-RPC exchanges
+//!
+//! A `BlockchainConnector` in record mode, see `BlockchainConnector::record_to`, writes every
+//! request and its response to a file of a directory:
+//!
+//! ```json
+//! { "method": "block", "params": { "finality": "final" }, "result": { ... } }
+//! ```
+//!
+//! A `ReplayConnector` serves them again without network access, so that tests relying on real
+//! node responses are hermetic.
+
+use std::{
+	ffi::OsStr,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{
+	blockchain_connector::JsonRpc,
+	error::{RelayerError, RpcError},
+};
+
+/// JSON-RPC request and the response of the node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+	pub method: String,
+	pub params: Value,
+	#[serde(flatten)]
+	pub outcome: RecordedOutcome,
+}
+
+/// `result` or `error` of a recorded response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordedOutcome {
+	Result(Value),
+	Error(RpcError),
+}
+
+impl Recording {
+	/// name of the file of the recording, the same for every recording of the same request
+	pub fn file_name(&self) -> String {
+		let params_hash = Sha256::digest(self.params.to_string().as_bytes());
+		let params_hash =
+			params_hash[..8].iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+		format!("{}_{}.json", self.method, params_hash)
+	}
+
+	/// writes the recording to `dir`, replacing a previous recording of the same request
+	pub fn save(&self, dir: impl AsRef<Path>) -> Result<PathBuf, RelayerError> {
+		fs::create_dir_all(&dir)?;
+		let path = dir.as_ref().join(self.file_name());
+		let mut recording = serde_json::to_vec_pretty(self)?;
+		recording.push(b'\n');
+		fs::write(&path, recording)?;
+		Ok(path)
+	}
+}
+
+/// Serves the recorded responses to the requests matching their `method` and `params`
+#[derive(Debug, Clone, Default)]
+pub struct ReplayConnector {
+	recordings: Vec<Recording>,
+}
+
+impl ReplayConnector {
+	pub fn new(recordings: Vec<Recording>) -> Self {
+		Self { recordings }
+	}
+
+	/// loads the recordings of `dir`, i.e. its `.json` files
+	pub fn open(dir: impl AsRef<Path>) -> Result<Self, RelayerError> {
+		let mut paths = fs::read_dir(dir)?
+			.map(|entry| Ok(entry?.path()))
+			.collect::<io::Result<Vec<_>>>()?;
+		paths.retain(|path| path.extension() == Some(OsStr::new("json")));
+		// the order of `read_dir` depends on the platform
+		paths.sort();
+
+		let recordings = paths
+			.iter()
+			.map(|path| {
+				serde_json::from_slice(&fs::read(path)?).map_err(|err| {
+					RelayerError::Decode(format!("invalid recording {}: {}", path.display(), err))
+				})
+			})
+			.collect::<Result<_, RelayerError>>()?;
+		Ok(Self::new(recordings))
+	}
+
+	pub fn recordings(&self) -> &[Recording] {
+		&self.recordings
+	}
+}
+
+impl JsonRpc for ReplayConnector {
+	fn call(&self, method: &str, params: Value) -> Result<Value, RelayerError> {
+		let recording = self
+			.recordings
+			.iter()
+			.find(|recording| recording.method == method && recording.params == params)
+			.ok_or_else(|| {
+				io::Error::new(
+					io::ErrorKind::NotFound,
+					format!("no recording of method={} params={}", method, params),
+				)
+			})?;
+		match &recording.outcome {
+			RecordedOutcome::Result(result) => Ok(result.clone()),
+			RecordedOutcome::Error(err) => Err(err.clone().into()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::env;
+
+	use near_sdk::json_types::Base58CryptoHash;
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		blockchain_connector::{BlockReference, BlockchainConnector, NearRpc, RpcEndpoint},
+		mock_rpc::MockRpcServer,
+		retry::RetryPolicy,
+	};
+
+	const CHECKPOINT: &str = include_str!("../../test-fixtures/light_client_block_86441383.json");
+	const CHECKPOINT_PREV_HASH: &str = "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr";
+
+	fn temp_dir(name: &str) -> PathBuf {
+		let path =
+			env::temp_dir().join(format!("near-lite-relayer-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		path
+	}
+
+	fn hash(encoded: &str) -> Base58CryptoHash {
+		Base58CryptoHash::try_from(encoded).unwrap()
+	}
+
+	#[test]
+	fn test_record_and_replay() {
+		let dir = temp_dir("recordings");
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond_with_recording(
+				"next_light_client_block",
+				json!([CHECKPOINT_PREV_HASH]),
+				CHECKPOINT,
+			)
+			.respond_with_error(
+				"block",
+				json!({ "block_id": 86697769 }),
+				json!({
+					"code": -32000,
+					"message": "Server error",
+					"name": "HANDLER_ERROR",
+					"cause": { "name": "UNKNOWN_BLOCK", "info": {} },
+				}),
+			);
+		let recorder = |url: &str| {
+			BlockchainConnector::from_endpoints(
+				vec![RpcEndpoint::new(url)],
+				RetryPolicy { max_retries: 0, ..Default::default() },
+			)
+			.record_to(&dir)
+		};
+		let unreachable_url = {
+			let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+			format!("http://{}", listener.local_addr().unwrap())
+		};
+
+		let checkpoint = recorder(server.url())
+			.get_light_client_block_view(hash(CHECKPOINT_PREV_HASH))
+			.unwrap();
+		// recorded again, in the same file
+		recorder(server.url())
+			.get_light_client_block_view(hash(CHECKPOINT_PREV_HASH))
+			.unwrap();
+		assert!(recorder(server.url()).block(BlockReference::Height(86697769)).is_err());
+		// nothing to record
+		assert!(recorder(&unreachable_url).block(BlockReference::Final).is_err());
+		assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+		let replay = ReplayConnector::open(&dir).unwrap();
+		assert_eq!(replay.recordings().len(), 2);
+		assert_eq!(
+			replay.get_light_client_block_view(hash(CHECKPOINT_PREV_HASH)).unwrap(),
+			checkpoint
+		);
+		match replay.block(BlockReference::Height(86697769)) {
+			Err(RelayerError::JsonRpc(err)) => assert_eq!(err.cause_name(), Some("UNKNOWN_BLOCK")),
+			result => panic!("unexpected {:?}", result),
+		}
+		assert!(matches!(replay.block(BlockReference::Final), Err(RelayerError::Io(_))));
+
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
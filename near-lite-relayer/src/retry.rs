@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{blockchain_connector::RpcEndpoint, error::RelayerError, metrics::RelayerMetrics};
+
+/// How often, and how long after, requests failing with a transient error are sent again.
+///
+/// Before every new round over the endpoints, the connector waits for an exponential backoff
+/// (`initial_backoff_ms * 2^(round - 1)`, capped to `max_backoff_ms`) of which the second half is
+/// randomized, so that relayers don't hammer a recovering node in lockstep.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+	/// rounds over the endpoints after the first one
+	pub max_retries: u32,
+	pub initial_backoff_ms: u64,
+	pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self { max_retries: 5, initial_backoff_ms: 250, max_backoff_ms: 10_000 }
+	}
+}
+
+impl RetryPolicy {
+	/// a single round over the endpoints
+	pub fn no_retry() -> Self {
+		Self { max_retries: 0, ..Default::default() }
+	}
+
+	/// time to wait before the `retry`-th retry, starting at 1
+	pub fn backoff(&self, retry: u32) -> Duration {
+		let exponential = self
+			.initial_backoff_ms
+			.saturating_mul(1u64.checked_shl(retry.saturating_sub(1)).unwrap_or(u64::MAX))
+			.min(self.max_backoff_ms);
+		let jitter = rand::thread_rng().gen_range(0..=exponential / 2);
+		Duration::from_millis(exponential - exponential / 2 + jitter)
+	}
+}
+
+/// Failover and retries of a JSON-RPC request over endpoints given in order of preference,
+/// following a `RetryPolicy`. The connectors only bring their transport: they wait for the backoff
+/// of every attempt, call its endpoint and report the result, until one of the results is final.
+pub(crate) struct Failover<'a> {
+	policy: &'a RetryPolicy,
+	endpoints: &'a [RpcEndpoint],
+	metrics: Option<&'a RelayerMetrics>,
+	method: &'a str,
+	/// rounds over the endpoints started so far
+	rounds: u32,
+	/// endpoint of the next attempt in the round
+	next_endpoint: usize,
+	/// whether an error of the round is worth another round
+	transient: bool,
+	last_error: Option<RelayerError>,
+}
+
+/// call of a request to an endpoint
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Attempt {
+	/// index of the endpoint
+	pub endpoint: usize,
+	/// time to wait before the call
+	pub backoff: Option<Duration>,
+}
+
+impl<'a> Failover<'a> {
+	pub(crate) fn new(
+		policy: &'a RetryPolicy,
+		endpoints: &'a [RpcEndpoint],
+		metrics: Option<&'a RelayerMetrics>,
+		method: &'a str,
+	) -> Self {
+		assert!(!endpoints.is_empty(), "at least one endpoint is required");
+		Self {
+			policy,
+			endpoints,
+			metrics,
+			method,
+			rounds: 0,
+			next_endpoint: 0,
+			transient: false,
+			last_error: None,
+		}
+	}
+
+	/// next call to make, `None` once the errors of a round are not transient or the retries are
+	/// exhausted
+	pub(crate) fn next_attempt(&mut self) -> Option<Attempt> {
+		if self.next_endpoint == self.endpoints.len() {
+			if !self.transient || self.rounds > self.policy.max_retries {
+				return None;
+			}
+			self.next_endpoint = 0;
+			self.transient = false;
+		}
+
+		let mut backoff = None;
+		if self.next_endpoint == 0 {
+			if self.rounds > 0 {
+				let retry_backoff = self.policy.backoff(self.rounds);
+				log::debug!("retrying method={} in {:?}", self.method, retry_backoff);
+				backoff = Some(retry_backoff);
+			}
+			self.rounds += 1;
+		}
+		let endpoint = self.next_endpoint;
+		self.next_endpoint += 1;
+		Some(Attempt { endpoint, backoff })
+	}
+
+	/// reports the result of the last attempt and how long its call took, returns it when it is
+	/// final, i.e. a success or an error that no other endpoint would avoid
+	pub(crate) fn report(
+		&mut self,
+		result: Result<Value, RelayerError>,
+		duration: Duration,
+	) -> Option<Result<Value, RelayerError>> {
+		let endpoint = &self.endpoints[self.next_endpoint - 1];
+		if let Some(metrics) = self.metrics {
+			metrics.observe_rpc_request(&endpoint.url, self.method, duration, result.is_ok());
+		}
+		match result {
+			Err(err) if err.is_endpoint_specific() => {
+				log::warn!("method={} failed on {}: {}", self.method, endpoint.url, err);
+				self.transient |= err.is_transient();
+				self.last_error = Some(err);
+				None
+			},
+			result => Some(result),
+		}
+	}
+
+	/// error of the last attempt, once there are no more
+	pub(crate) fn into_error(self) -> RelayerError {
+		self.last_error.expect("there is at least one endpoint")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	fn unknown_block() -> RelayerError {
+		RelayerError::JsonRpc(
+			serde_json::from_value(json!({
+				"code": -32000,
+				"message": "Server error",
+				"name": "HANDLER_ERROR",
+				"cause": { "name": "UNKNOWN_BLOCK" },
+			}))
+			.unwrap(),
+		)
+	}
+
+	#[test]
+	fn test_backoff() {
+		let policy =
+			RetryPolicy { max_retries: 10, initial_backoff_ms: 100, max_backoff_ms: 1_000 };
+		for (retry, expected_ms) in
+			[(1, 100), (2, 200), (3, 400), (4, 800), (5, 1_000), (64, 1_000)]
+		{
+			for _ in 0..10 {
+				let backoff = policy.backoff(retry);
+				assert!(backoff <= Duration::from_millis(expected_ms));
+				assert!(backoff >= Duration::from_millis(expected_ms / 2));
+			}
+		}
+	}
+
+	#[test]
+	fn test_failover() {
+		let policy = RetryPolicy { max_retries: 2, initial_backoff_ms: 1, max_backoff_ms: 1 };
+		let endpoints = [RpcEndpoint::new("http://a"), RpcEndpoint::new("http://b")];
+		let mut failover = Failover::new(&policy, &endpoints, None, "block");
+
+		assert_eq!(failover.next_attempt(), Some(Attempt { endpoint: 0, backoff: None }));
+		let transport_error = RelayerError::Transport("connection refused".to_string());
+		assert!(failover.report(Err(transport_error), Duration::ZERO).is_none());
+		assert_eq!(failover.next_attempt(), Some(Attempt { endpoint: 1, backoff: None }));
+		assert!(failover.report(Err(unknown_block()), Duration::ZERO).is_none());
+
+		// a transient error is worth another round
+		let attempt = failover.next_attempt().unwrap();
+		assert_eq!((attempt.endpoint, attempt.backoff.is_some()), (0, true));
+		assert!(failover.report(Err(unknown_block()), Duration::ZERO).is_none());
+		assert_eq!(failover.next_attempt(), Some(Attempt { endpoint: 1, backoff: None }));
+		let result = failover.report(Ok(json!({ "height": 1 })), Duration::ZERO);
+		assert_eq!(result.unwrap().unwrap(), json!({ "height": 1 }));
+	}
+
+	#[test]
+	fn test_failover_gives_up() {
+		let policy = RetryPolicy { max_retries: 2, initial_backoff_ms: 1, max_backoff_ms: 1 };
+		let endpoints = [RpcEndpoint::new("http://a"), RpcEndpoint::new("http://b")];
+
+		// the data is missing on every endpoint
+		let mut failover = Failover::new(&policy, &endpoints, None, "block");
+		while let Some(attempt) = failover.next_attempt() {
+			assert_eq!(attempt.backoff, None);
+			assert!(failover.report(Err(unknown_block()), Duration::ZERO).is_none());
+		}
+		assert!(matches!(failover.into_error(), RelayerError::JsonRpc(_)));
+
+		// every round fails
+		let mut failover = Failover::new(&policy, &endpoints, None, "block");
+		let mut attempts = 0;
+		while failover.next_attempt().is_some() {
+			let transport_error = RelayerError::Transport("connection refused".to_string());
+			assert!(failover.report(Err(transport_error), Duration::ZERO).is_none());
+			attempts += 1;
+		}
+		assert_eq!(attempts, 3 * endpoints.len());
+
+		// an error that another endpoint would answer as well
+		let mut failover = Failover::new(&policy, &endpoints, None, "block");
+		failover.next_attempt().unwrap();
+		let status_error = RelayerError::HttpStatus { status: 400, body: String::new() };
+		assert!(failover.report(Err(status_error), Duration::ZERO).unwrap().is_err());
+	}
+}
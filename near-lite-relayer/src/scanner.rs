@@ -0,0 +1,323 @@
+//! # Account scanner
+//!
+//! Walks the chunks of the blocks and collects the transactions and the receipts of a set of
+//! accounts, e.g. a bridge locker contract, with their outcomes and the proof requests for them.
+
+use std::collections::BTreeSet;
+
+use near_primitives::views::{ExecutionOutcomeWithIdView, ExecutionStatusView};
+use near_sdk::json_types::Base58CryptoHash;
+
+use crate::{
+	blockchain_connector::{BlockReference, NearRpc, ProofRequest},
+	error::RelayerError,
+};
+
+/// Transaction or receipt of a scanned account
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountActivity {
+	/// height of the block including the transaction or the receipt
+	pub block_height: u64,
+	pub request: ProofRequest,
+	/// outcome, as of the latest final block; `None` when it is not known yet, e.g. for a
+	/// delayed receipt
+	pub outcome: Option<ExecutionOutcomeWithIdView>,
+}
+
+impl AccountActivity {
+	/// whether the outcome is known and successful
+	pub fn is_success(&self) -> bool {
+		matches!(
+			self.outcome.as_ref().map(|outcome| &outcome.outcome.status),
+			Some(ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_))
+		)
+	}
+
+	/// logs of the outcome, if it is known
+	pub fn logs(&self) -> &[String] {
+		self.outcome.as_ref().map_or(&[], |outcome| &outcome.outcome.logs)
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountScanner {
+	accounts: BTreeSet<String>,
+}
+
+impl AccountScanner {
+	pub fn new(accounts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+		Self { accounts: accounts.into_iter().map(Into::into).collect() }
+	}
+
+	pub fn accounts(&self) -> &BTreeSet<String> {
+		&self.accounts
+	}
+
+	fn matches(&self, account_id: &str) -> bool {
+		self.accounts.contains(account_id)
+	}
+
+	/// Transactions signed by or sent to the accounts, and receipts sent by or to them, included
+	/// in the block at `height`. A height without a block, which was skipped, has none.
+	///
+	/// The outcomes are read from proofs against the latest final block, so `height` must be
+	/// before it.
+	pub fn scan_block(
+		&self,
+		rpc: &impl NearRpc,
+		height: u64,
+	) -> Result<Vec<AccountActivity>, RelayerError> {
+		let block = match rpc.block(BlockReference::Height(height)) {
+			Ok(block) => block,
+			Err(RelayerError::JsonRpc(err)) if err.cause_name() == Some("UNKNOWN_BLOCK") =>
+				return Ok(Vec::new()),
+			Err(err) => return Err(err),
+		};
+
+		let mut requests = Vec::new();
+		// chunks missing from the block are the previous chunks of their shards, already scanned
+		for chunk_header in block.chunks.iter().filter(|chunk| chunk.height_included == height) {
+			let chunk = rpc.chunk(chunk_header.chunk_hash)?;
+			for tx in chunk.transactions {
+				if !self.matches(&tx.signer_id) && !self.matches(&tx.receiver_id) {
+					continue;
+				}
+				// the receipt converted from a transaction to the same account is not in the
+				// receipts of any chunk
+				let local_receipt = tx.signer_id == tx.receiver_id && self.matches(&tx.receiver_id);
+				let request =
+					ProofRequest::Transaction { tx_hash: tx.hash, sender_id: tx.signer_id };
+				requests.push((request, local_receipt.then_some(tx.receiver_id)));
+			}
+			for receipt in chunk.receipts {
+				if self.matches(&receipt.predecessor_id) || self.matches(&receipt.receiver_id) {
+					let request = ProofRequest::Receipt {
+						receipt_id: receipt.receipt_id,
+						receiver_id: receipt.receiver_id,
+					};
+					requests.push((request, None));
+				}
+			}
+		}
+		if requests.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let (final_block_hash, _) = rpc.get_almost_latest_finalized_block_hash_and_height()?;
+		let hinted = requests.iter().map(|(request, _)| request).collect::<Vec<_>>();
+		rpc.prefetch_light_client_proofs(final_block_hash, &hinted);
+		let mut activities = Vec::new();
+		for (request, local_receiver_id) in requests {
+			let outcome = match rpc.light_client_proof(final_block_hash, &request) {
+				Ok(proof) => Some(proof.outcome_proof),
+				Err(err) => {
+					log::warn!("no outcome for {:?} yet: {}", request, err);
+					None
+				},
+			};
+			let local_receipt = local_receiver_id.zip(
+				outcome
+					.as_ref()
+					.and_then(|outcome| outcome.outcome.receipt_ids.first().copied()),
+			);
+			activities.push(AccountActivity { block_height: height, request, outcome });
+
+			if let Some((receiver_id, receipt_id)) = local_receipt {
+				let request = ProofRequest::Receipt {
+					receipt_id: Base58CryptoHash::from(receipt_id.0),
+					receiver_id,
+				};
+				let outcome = rpc
+					.light_client_proof(final_block_hash, &request)
+					.ok()
+					.map(|proof| proof.outcome_proof);
+				activities.push(AccountActivity { block_height: height, request, outcome });
+			}
+		}
+		Ok(activities)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+	use crate::{
+		blockchain_connector::BlockchainConnector,
+		mock_rpc::MockRpcServer,
+		test_utils::{FINAL_BLOCK_PREV_HASH, PROOF, TX_HASH},
+	};
+
+	const RECEIPT_ID: &str = "8hxkU4avDWFDCsZckig7oN2ypnYvLyb1qmZ3SA1t8iZK";
+
+	fn hash(encoded: &str) -> Base58CryptoHash {
+		Base58CryptoHash::try_from(encoded).unwrap()
+	}
+
+	/// the block 86697768 includes a new chunk, with a transaction of `relay.aurora` to `aurora`
+	/// and a receipt from `aurora` to `bridge.testnet`, and the previous chunk of another shard
+	fn mock_rpc_server() -> MockRpcServer {
+		let server = MockRpcServer::start().unwrap();
+		server
+			.respond(
+				"block",
+				json!({ "finality": "final" }),
+				json!({
+					"header": { "height": 86698268, "prev_hash": FINAL_BLOCK_PREV_HASH },
+					"chunks": [],
+				}),
+			)
+			.respond(
+				"block",
+				json!({ "block_id": 86697768 }),
+				json!({
+					"header": {
+						"height": 86697768,
+						"prev_hash": "3SZGvB3WTta5eMZuKqwmNst73jpAj9x77gc7BohTmiht",
+					},
+					"chunks": [
+						{
+							"chunk_hash": "Cx7camxqaUQMLBHmWUrVvbf8JCqq78vCTi4XgnX8gdDX",
+							"height_included": 86697767,
+							"balance_burnt": "0",
+						},
+						{
+							"chunk_hash": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8",
+							"height_included": 86697768,
+							"balance_burnt": "242839501800800000000",
+						},
+					],
+				}),
+			)
+			.respond_with_error(
+				"block",
+				json!({ "block_id": 86697769 }),
+				json!({
+					"code": -32000,
+					"message": "Server error",
+					"name": "HANDLER_ERROR",
+					"cause": { "name": "UNKNOWN_BLOCK", "info": {} },
+				}),
+			)
+			.respond(
+				"chunk",
+				json!({ "chunk_id": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8" }),
+				json!({
+					"transactions": [
+						{ "hash": TX_HASH, "signer_id": "relay.aurora", "receiver_id": "aurora" },
+						{
+							"hash": "FD128fQ4vBeCKqEnkfCGdbVWXZPcaCfrAY3MSpib1mDr",
+							"signer_id": "alice.testnet",
+							"receiver_id": "bob.testnet",
+						},
+					],
+					"receipts": [{
+						"receipt_id": RECEIPT_ID,
+						"predecessor_id": "aurora",
+						"receiver_id": "bridge.testnet",
+					}],
+				}),
+			)
+			.respond_with_recording(
+				"EXPERIMENTAL_light_client_proof",
+				json!({
+					"type": "transaction",
+					"transaction_hash": TX_HASH,
+					"sender_id": "relay.aurora",
+					"light_client_head": FINAL_BLOCK_PREV_HASH,
+				}),
+				PROOF,
+			);
+		server
+	}
+
+	#[test]
+	fn test_scan_block() {
+		let server = mock_rpc_server();
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+
+		let activities = AccountScanner::new(["aurora"]).scan_block(&rpc, 86697768).unwrap();
+		assert_eq!(activities.len(), 2);
+		assert_eq!(
+			activities[0].request,
+			ProofRequest::Transaction { tx_hash: hash(TX_HASH), sender_id: "relay.aurora".into() }
+		);
+		assert!(activities[0].is_success());
+		assert_eq!(
+			activities[0].outcome.as_ref().unwrap().outcome.executor_id.as_ref(),
+			"relay.aurora"
+		);
+		// the outcome of the receipt is not served
+		assert_eq!(
+			activities[1].request,
+			ProofRequest::Receipt {
+				receipt_id: hash(RECEIPT_ID),
+				receiver_id: "bridge.testnet".into()
+			}
+		);
+		assert_eq!(activities[1].outcome, None);
+		assert!(!activities[1].is_success());
+		assert!(activities.iter().all(|activity| activity.block_height == 86697768));
+
+		let activities = AccountScanner::new(["bob.testnet"]).scan_block(&rpc, 86697768).unwrap();
+		assert_eq!(activities.len(), 1);
+
+		let scanner = AccountScanner::new(["near"]);
+		assert!(scanner.scan_block(&rpc, 86697768).unwrap().is_empty());
+		// skipped
+		assert!(scanner.scan_block(&rpc, 86697769).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_scan_local_transaction() {
+		let server = mock_rpc_server();
+		server
+			.respond(
+				"block",
+				json!({ "block_id": 86697770 }),
+				json!({
+					"header": {
+						"height": 86697770,
+						"prev_hash": "3SZGvB3WTta5eMZuKqwmNst73jpAj9x77gc7BohTmiht",
+					},
+					"chunks": [{
+						"chunk_hash": "9NSyUcAfp7usW87j6dQiRfSdibsLrRtCcwgHyPRMZC3B",
+						"height_included": 86697770,
+						"balance_burnt": "0",
+					}],
+				}),
+			)
+			.respond(
+				"chunk",
+				json!({ "chunk_id": "9NSyUcAfp7usW87j6dQiRfSdibsLrRtCcwgHyPRMZC3B" }),
+				json!({
+					"transactions": [
+						{
+							"hash": TX_HASH,
+							"signer_id": "relay.aurora",
+							"receiver_id": "relay.aurora",
+						},
+					],
+					"receipts": [],
+				}),
+			);
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+
+		let activities = AccountScanner::new(["relay.aurora"]).scan_block(&rpc, 86697770).unwrap();
+		let requests = activities.into_iter().map(|activity| activity.request).collect::<Vec<_>>();
+		assert_eq!(
+			requests,
+			vec![
+				ProofRequest::Transaction {
+					tx_hash: hash(TX_HASH),
+					sender_id: "relay.aurora".into()
+				},
+				ProofRequest::Receipt {
+					receipt_id: hash(RECEIPT_ID),
+					receiver_id: "relay.aurora".into()
+				},
+			]
+		);
+	}
+}
@@ -0,0 +1,712 @@
+//! # Proof service
+//!
+//! HTTP server answering other services with headers and proofs, in the Borsh encoding of the
+//! `near-primitives-wasm` types validated by `near-lite-client`:
+//!
+//! - `GET /head`: the head of the relayer, from the store
+//! - `GET /blocks/{height}`: the header submitted at `height`, from the store, or, above the head,
+//!   the light client block the RPC serves after the block before `height`, validated. As in
+//!   `next_light_client_block`, it is the latest final block of the epoch of that block, or the
+//!   last final block of the next epoch, so it may be at a greater height than `height`
+//! - `POST /proofs/transaction`, with `{ "transaction_hash": "...", "sender_id": "..." }`, and
+//!   `POST /proofs/receipt`, with `{ "receipt_id": "...", "receiver_id": "..." }`: the proof of an
+//!   outcome against the head, as a `LiteClientProof`
+//! - `POST /proofs/state`, with `{ "account_id": "...", "key": "<base64>" }`: the value of a key of
+//!   the contract storage of the account as of the head, with its `LiteClientStateProof`
+//!
+//! The proofs are fetched from the RPC and validated against the head before being answered.
+//! Responses hold the JSON of the RPC next to the Borsh encoding, in hex or, with
+//! `?encoding=base64`, in base64. Errors are answered as `{ "error": "..." }`.
+
+use std::{
+	fmt, io,
+	net::{SocketAddr, TcpListener},
+	sync::Arc,
+	thread::{self, JoinHandle},
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_lite_client::{
+	validate_contract_state, validate_transaction, CommitmentProofBytes, IbcContractStorage,
+	LightClientBlockView as LiteClientBlockView, ShardStateRootProof,
+};
+use near_primitives::{
+	hash::hash, merkle::merklize, views::LightClientBlockView as NearLightClientBlockView,
+};
+use near_primitives_wasm::ConversionError;
+use near_sdk::json_types::Base58CryptoHash;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+use crate::{
+	blockchain_connector::{BlockReference, NearRpc, ProofRequest},
+	client_proof::LightClientBlockView,
+	error::RelayerError,
+	proof_queue::ExecutionProof,
+	state::{LightClientState, NativeHostFunctions},
+	store::RelayerStore,
+};
+
+/// Proof of the value of a key of a contract storage, validated by `validate_contract_state`
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct LiteClientStateProof {
+	pub shard_state_root_proof: ShardStateRootProof,
+	/// raw trie nodes from the state root of the shard, see `CommitmentProofBytes`
+	pub nodes: Vec<Vec<u8>>,
+}
+
+/// Encoding of the Borsh bytes in the responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+	Hex,
+	Base64,
+}
+
+impl Encoding {
+	/// encoding of the `encoding` parameter of `query`, hex by default
+	fn from_query(query: &str) -> Result<Self, ServiceError> {
+		match query.split('&').find_map(|param| param.strip_prefix("encoding=")) {
+			None | Some("hex") => Ok(Self::Hex),
+			Some("base64") => Ok(Self::Base64),
+			Some(other) => Err(ServiceError::BadRequest(format!("unknown encoding {}", other))),
+		}
+	}
+
+	fn encode(self, value: &impl BorshSerialize) -> String {
+		let bytes = value.try_to_vec().expect("serializing into a vec doesn't fail");
+		match self {
+			Self::Hex => hex::encode(bytes),
+			Self::Base64 => base64::encode(bytes),
+		}
+	}
+}
+
+#[derive(Debug)]
+enum ServiceError {
+	BadRequest(String),
+	NotFound(String),
+	/// the relayer has not submitted any header yet
+	NoHead,
+	/// the node served a header or a proof the light client rejects
+	Invalid(String),
+	Relayer(RelayerError),
+}
+
+impl ServiceError {
+	fn status(&self) -> u16 {
+		match self {
+			Self::BadRequest(_) => 400,
+			Self::NotFound(_) => 404,
+			Self::NoHead => 503,
+			Self::Invalid(_) => 502,
+			Self::Relayer(RelayerError::Io(_)) => 500,
+			Self::Relayer(_) => 502,
+		}
+	}
+}
+
+impl fmt::Display for ServiceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::BadRequest(err) => write!(f, "bad request: {}", err),
+			Self::NotFound(err) => write!(f, "not found: {}", err),
+			Self::NoHead => write!(f, "no header has been submitted yet"),
+			Self::Invalid(err) => write!(f, "rejected by the light client: {}", err),
+			Self::Relayer(err) => write!(f, "{}", err),
+		}
+	}
+}
+
+impl From<RelayerError> for ServiceError {
+	fn from(err: RelayerError) -> Self {
+		match err {
+			RelayerError::InvalidHeader { .. } => Self::Invalid(err.to_string()),
+			err => Self::Relayer(err),
+		}
+	}
+}
+
+impl From<ConversionError> for ServiceError {
+	fn from(err: ConversionError) -> Self {
+		Self::Relayer(err.into())
+	}
+}
+
+/// body of `POST /proofs/state`
+#[derive(Debug, Deserialize)]
+struct StateProofRequest {
+	account_id: String,
+	/// base64 encoded key of the contract storage
+	key: String,
+}
+
+/// Answers the requests of the proof service from the state saved in the store by a relayer, and
+/// from the RPC
+pub struct ProofService<R, T> {
+	rpc: R,
+	store: T,
+}
+
+impl<R: NearRpc, T: RelayerStore> ProofService<R, T> {
+	pub fn new(rpc: R, store: T) -> Self {
+		Self { rpc, store }
+	}
+
+	/// answers `method url` with `body`, as a status and a JSON body
+	pub fn handle(&self, method: &str, url: &str, body: &str) -> (u16, Value) {
+		match self.route(method, url, body) {
+			Ok(response) => (200, response),
+			Err(err) => {
+				log::debug!("{} {} failed: {}", method, url, err);
+				(err.status(), json!({ "error": err.to_string() }))
+			},
+		}
+	}
+
+	fn route(&self, method: &str, url: &str, body: &str) -> Result<Value, ServiceError> {
+		let (path, query) = url.split_once('?').unwrap_or((url, ""));
+		let encoding = Encoding::from_query(query)?;
+		let segments = path.trim_matches('/').split('/').collect::<Vec<_>>();
+		match (method, segments.as_slice()) {
+			("GET", ["head"]) => block_response(self.state()?.head(), encoding),
+			("GET", ["blocks", height]) => {
+				let height = height
+					.parse()
+					.map_err(|_| ServiceError::BadRequest(format!("invalid height {}", height)))?;
+				self.block(height, encoding)
+			},
+			("POST", ["proofs", kind @ ("transaction" | "receipt")]) =>
+				self.execution_proof(proof_request(kind, body)?, encoding),
+			("POST", ["proofs", "state"]) => {
+				let request = serde_json::from_str(body)
+					.map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+				self.state_proof(request, encoding)
+			},
+			_ => Err(ServiceError::NotFound(format!("no route for {} {}", method, path))),
+		}
+	}
+
+	fn state(&self) -> Result<LightClientState, ServiceError> {
+		self.store.load_state()?.ok_or(ServiceError::NoHead)
+	}
+
+	fn block(&self, height: u64, encoding: Encoding) -> Result<Value, ServiceError> {
+		if let Some(block_view) = self.store.submitted_block(height)? {
+			return block_response(&block_view, encoding);
+		}
+		let mut state = self.state()?;
+		if height <= state.head_height() {
+			return Err(ServiceError::NotFound(format!("no header submitted at height {}", height)));
+		}
+		let prev_hash = self.rpc.get_block_hash_from_block_number(height)?;
+		let block_view = self.rpc.next_light_client_block(prev_hash)?.ok_or_else(|| {
+			ServiceError::NotFound(format!("no final light client block from height {}", height))
+		})?;
+		state.validate_head(&block_view)?;
+		block_response(&block_view, encoding)
+	}
+
+	fn execution_proof(
+		&self,
+		request: ProofRequest,
+		encoding: Encoding,
+	) -> Result<Value, ServiceError> {
+		let state = self.state()?;
+		let head_hash = state.head_hash();
+		let response =
+			self.rpc.light_client_proof(Base58CryptoHash::from(head_hash.0), &request)?;
+		let proof = ExecutionProof { request, light_client_head: head_hash, response };
+		// the block merkle tree of the head only covers the blocks before it
+		if proof.block_height() >= state.head_height() {
+			return Err(ServiceError::NotFound(format!(
+				"the block height={} of the outcome is not covered by the head",
+				proof.block_height()
+			)));
+		}
+		let block_merkle_root = proof.block_merkle_root();
+		if block_merkle_root != state.head().inner_lite.block_merkle_root {
+			return Err(ServiceError::Invalid(format!(
+				"invalid block proof: it leads to {} instead of the head's block merkle root",
+				block_merkle_root
+			)));
+		}
+
+		let lite_client_proof = proof.to_lite_client();
+		validate_transaction::<NativeHostFunctions>(
+			&lite_client_proof.outcome_proof,
+			lite_client_proof.outcome_root_proof.clone(),
+			lite_client_proof.block_outcome_root,
+		)
+		.map_err(|err| ServiceError::Invalid(format!("invalid proof: {:?}", err)))?;
+		Ok(json!({
+			"light_client_head": head_hash,
+			"block_height": proof.block_height(),
+			"json": proof.response,
+			"borsh": encoding.encode(&lite_client_proof),
+		}))
+	}
+
+	fn state_proof(
+		&self,
+		request: StateProofRequest,
+		encoding: Encoding,
+	) -> Result<Value, ServiceError> {
+		let key = base64::decode(&request.key)
+			.map_err(|err| ServiceError::BadRequest(format!("invalid key: {}", err)))?;
+		let state = self.state()?;
+		let head = state.head();
+		// the `prev_state_root` of the head is the state following its previous block
+		let response = self.rpc.view_state(
+			Base58CryptoHash::from(head.prev_block_hash.0),
+			&request.account_id,
+			&key,
+		)?;
+		let value = response.values.into_iter().find(|item| item.key == key).map(|item| item.value);
+
+		// the first node is the root of the state trie of the shard of the account
+		let state_root = match response.proof.first() {
+			Some(root_node) => hash(root_node),
+			None => return Err(ServiceError::Invalid("the state proof is empty".to_string())),
+		};
+		let state_roots = self
+			.rpc
+			.block(BlockReference::Height(head.inner_lite.height))?
+			.chunks
+			.into_iter()
+			.map(|chunk| {
+				chunk.prev_state_root.ok_or_else(|| {
+					RelayerError::Decode("chunk header without prev_state_root".to_string())
+				})
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		let shard = state_roots.iter().position(|root| *root == state_root).ok_or_else(|| {
+			ServiceError::Invalid(
+				"no shard of the head has the state root of the proof".to_string(),
+			)
+		})?;
+		let (_, mut paths) = merklize(&state_roots);
+		let path = paths.swap_remove(shard);
+
+		let proof = LiteClientStateProof {
+			shard_state_root_proof: ShardStateRootProof {
+				state_root: state_root.into(),
+				path: path.iter().cloned().map(Into::into).collect(),
+			},
+			nodes: response.proof,
+		};
+		validate_contract_state::<NativeHostFunctions>(
+			&LiteClientBlockView::from(head.clone()),
+			&IbcContractStorage {
+				account_id: request.account_id.clone(),
+				storage_prefix: Vec::new(),
+			},
+			&key,
+			&proof.shard_state_root_proof,
+			&CommitmentProofBytes::from(proof.nodes.clone()),
+			value.clone(),
+		)
+		.map_err(|err| ServiceError::Invalid(format!("invalid state proof: {:?}", err)))?;
+		Ok(json!({
+			"light_client_head": state.head_hash(),
+			"block_height": head.inner_lite.height,
+			"account_id": request.account_id,
+			"key": request.key,
+			"value": value.map(base64::encode),
+			"json": {
+				"state_root": state_root,
+				"state_root_path": path,
+				"nodes": proof.nodes.iter().map(base64::encode).collect::<Vec<_>>(),
+			},
+			"borsh": encoding.encode(&proof),
+		}))
+	}
+}
+
+/// `ProofRequest` of the body of `POST /proofs/{kind}`
+fn proof_request(kind: &str, body: &str) -> Result<ProofRequest, ServiceError> {
+	let mut request: Value =
+		serde_json::from_str(body).map_err(|err| ServiceError::BadRequest(err.to_string()))?;
+	if let Some(request) = request.as_object_mut() {
+		request.insert("type".to_string(), kind.into());
+	}
+	serde_json::from_value(request).map_err(|err| ServiceError::BadRequest(err.to_string()))
+}
+
+fn block_response(
+	block_view: &NearLightClientBlockView,
+	encoding: Encoding,
+) -> Result<Value, ServiceError> {
+	let lite_client_block = LiteClientBlockView::from(block_view.clone());
+	Ok(json!({
+		"height": block_view.inner_lite.height,
+		"hash": LightClientBlockView::from(block_view.clone()).current_block_hash(),
+		"epoch_id": block_view.inner_lite.epoch_id,
+		"json": block_view,
+		"borsh": encoding.encode(&lite_client_block),
+	}))
+}
+
+/// HTTP server answering with a `ProofService`, until dropped
+pub struct ProofServer {
+	addr: SocketAddr,
+	server: Arc<Server>,
+	handle: Option<JoinHandle<()>>,
+}
+
+impl ProofServer {
+	/// starts the server on `addr`; a port 0 picks a free port, see `addr`
+	pub fn start<R, T>(addr: SocketAddr, service: ProofService<R, T>) -> io::Result<Self>
+	where
+		R: NearRpc + Send + 'static,
+		T: RelayerStore + Send + 'static,
+	{
+		let listener = TcpListener::bind(addr)?;
+		let addr = listener.local_addr()?;
+		let server = Arc::new(
+			Server::from_listener(listener, None)
+				.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?,
+		);
+		let handle = {
+			let server = server.clone();
+			thread::spawn(move || serve(&server, &service))
+		};
+		log::info!("serving the proofs on http://{}", addr);
+		Ok(Self { addr, server, handle: Some(handle) })
+	}
+
+	pub fn addr(&self) -> SocketAddr {
+		self.addr
+	}
+}
+
+impl Drop for ProofServer {
+	fn drop(&mut self) {
+		self.server.unblock();
+		if let Some(handle) = self.handle.take() {
+			let _ = handle.join();
+		}
+	}
+}
+
+fn serve<R: NearRpc, T: RelayerStore>(server: &Server, service: &ProofService<R, T>) {
+	for mut request in server.incoming_requests() {
+		let mut body = String::new();
+		let (status, response) = match request.as_reader().read_to_string(&mut body) {
+			Ok(_) => service.handle(request.method().as_str(), request.url(), &body),
+			Err(err) => (400, json!({ "error": format!("bad request: {}", err) })),
+		};
+		let header = Header::from_bytes("Content-Type", "application/json")
+			.expect("the content type is a valid header");
+		let response = Response::from_string(response.to_string())
+			.with_status_code(status)
+			.with_header(header);
+		let _ = request.respond(response);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use near_chain_simulator::{ChainSimulator, Tampering, Validator};
+	use near_lite_client::CryptoHash as LiteClientCryptoHash;
+	use near_primitives::hash::CryptoHash;
+
+	use super::*;
+	use crate::{
+		blockchain_connector::BlockchainConnector,
+		mock_rpc::MockRpcServer,
+		proof_queue::LiteClientProof,
+		store::{MemoryStore, Submission},
+		test_utils::{head, proof_head, single_leaf_trie, ACCOUNT_ID, PROOF, TX_HASH},
+	};
+
+	/// store holding `state` as the state following the submission of its head
+	fn store(state: &LightClientState) -> MemoryStore {
+		let mut store = MemoryStore::default();
+		let submission = Submission::new(state.head_height(), state.head_hash(), state.epoch_id());
+		store.save_submission(state, submission).unwrap();
+		store
+	}
+
+	fn block(
+		chain: &ChainSimulator,
+		height: u64,
+		tampering: Tampering,
+	) -> NearLightClientBlockView {
+		chain
+			.tampered_light_client_block(height, &tampering)
+			.unwrap()
+			.try_into()
+			.unwrap()
+	}
+
+	/// Serves the block at `height` and, as the RPC does, the light client block following the
+	/// block before it: the latest final block when it is in the same epoch, else the last block of
+	/// the next epoch. Only the block before the head of `chain` is final.
+	fn serve_next_block(
+		server: &MockRpcServer,
+		chain: &ChainSimulator,
+		height: u64,
+		tampering: Tampering,
+	) -> Option<NearLightClientBlockView> {
+		let last_known_height = height - 1;
+		let final_height = chain.head_height().unwrap() - 1;
+		let epoch = chain.epoch_of(last_known_height);
+		let next_height = if epoch == chain.epoch_of(final_height) {
+			final_height
+		} else {
+			chain.last_block_of_epoch(epoch + 1).min(final_height)
+		};
+		let next_block =
+			(next_height > last_known_height).then(|| block(chain, next_height, tampering));
+
+		let prev_hash = CryptoHash(chain.block_hash(last_known_height).unwrap().0);
+		server
+			.respond(
+				"block",
+				json!({ "block_id": height }),
+				json!({ "header": { "height": height, "prev_hash": prev_hash }, "chunks": [] }),
+			)
+			.respond(
+				"next_light_client_block",
+				json!([prev_hash]),
+				next_block
+					.as_ref()
+					.map_or(json!({}), |block| serde_json::to_value(block).unwrap()),
+			);
+		next_block
+	}
+
+	fn decode_borsh<D: BorshDeserialize>(response: &Value) -> D {
+		D::try_from_slice(&hex::decode(response["borsh"].as_str().unwrap()).unwrap()).unwrap()
+	}
+
+	#[test]
+	fn test_blocks() {
+		let mut chain = ChainSimulator::new(vec![
+			Validator::new("alice.near", 400),
+			Validator::new("bob.near", 300),
+			Validator::new("carol.near", 200),
+		]);
+		// up to the first block of the epoch 3, the epoch 2 being final
+		chain.produce_blocks(3 * chain.epoch_length() + 1);
+		let height = chain.last_block_of_epoch(0);
+		let mut state =
+			LightClientState::new_from_checkpoint(block(&chain, height, Tampering::default()));
+		state.validate_head(&block(&chain, height + 1, Tampering::default())).unwrap();
+
+		let server = MockRpcServer::start().unwrap();
+		let next_block =
+			serve_next_block(&server, &chain, height + 2, Tampering::default()).unwrap();
+		let forged = Tampering { forged_approvals: [0].into(), ..Default::default() };
+		serve_next_block(&server, &chain, height + 3, forged).unwrap();
+		let final_height = chain.head_height().unwrap() - 1;
+		let no_block = serve_next_block(&server, &chain, final_height + 1, Tampering::default());
+		assert!(no_block.is_none());
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+
+		let service = ProofService::new(rpc, MemoryStore::default());
+		assert_eq!(service.handle("GET", "/head", "").0, 503);
+		let service = ProofService::new(service.rpc, store(&state));
+
+		let (status, head) = service.handle("GET", "/head", "");
+		assert_eq!(status, 200);
+		assert_eq!(head["height"], height + 1);
+		assert_eq!(head["hash"], state.head_hash().to_string());
+		assert_eq!(head["json"], serde_json::to_value(state.head()).unwrap());
+		let head_block: LiteClientBlockView = decode_borsh(&head);
+		assert_eq!(head_block.inner_lite.height, height + 1);
+
+		let (status, submitted) =
+			service.handle("GET", &format!("/blocks/{}?encoding=base64", height + 1), "");
+		assert_eq!(status, 200);
+		let borsh = base64::decode(submitted["borsh"].as_str().unwrap()).unwrap();
+		assert_eq!(borsh, hex::decode(head["borsh"].as_str().unwrap()).unwrap());
+
+		// the last block of the next epoch, not the block at the requested height
+		let (status, fetched) = service.handle("GET", &format!("/blocks/{}", height + 2), "");
+		assert_eq!(status, 200, "{}", fetched);
+		assert_eq!(fetched["height"], chain.last_block_of_epoch(2));
+		assert_eq!(fetched["json"], serde_json::to_value(&next_block).unwrap());
+
+		for (url, expected_status) in [
+			(format!("/blocks/{}", height + 3), 502),
+			(format!("/blocks/{}", final_height + 1), 404),
+			(format!("/blocks/{}", height), 404),
+			("/blocks/head".to_string(), 400),
+			("/head?encoding=base58".to_string(), 400),
+			("/blocks".to_string(), 404),
+		] {
+			let (status, response) = service.handle("GET", &url, "");
+			assert_eq!(status, expected_status, "{}: {}", url, response);
+			assert!(response["error"].is_string());
+		}
+	}
+
+	#[test]
+	fn test_transaction_proof() {
+		// the outcome is in the block 86697768
+		let server = MockRpcServer::start().unwrap();
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let request = json!({ "transaction_hash": TX_HASH, "sender_id": "relay.aurora" });
+		for head in [proof_head(86697768), proof_head(86697769)] {
+			let head_hash = LightClientBlockView::from(head.clone()).current_block_hash();
+			let mut params = request.clone();
+			params["type"] = json!("transaction");
+			params["light_client_head"] = json!(head_hash);
+			server.respond_with_recording("EXPERIMENTAL_light_client_proof", params, PROOF);
+		}
+
+		let state = LightClientState::new_from_checkpoint(proof_head(86697768));
+		let service = ProofService::new(rpc, store(&state));
+		let (status, _) = service.handle("POST", "/proofs/transaction", &request.to_string());
+		assert_eq!(status, 404);
+
+		let state = LightClientState::new_from_checkpoint(proof_head(86697769));
+		let service = ProofService::new(service.rpc, store(&state));
+		let (status, proof) = service.handle("POST", "/proofs/transaction", &request.to_string());
+		assert_eq!(status, 200, "{}", proof);
+		assert_eq!(proof["light_client_head"], state.head_hash().to_string());
+		assert_eq!(proof["block_height"], 86697768);
+		assert_eq!(proof["json"]["outcome_proof"]["id"], TX_HASH);
+		let lite_client_proof: LiteClientProof = decode_borsh(&proof);
+		assert_eq!(
+			lite_client_proof.light_client_head,
+			LiteClientCryptoHash::from(state.head_hash())
+		);
+		validate_transaction::<NativeHostFunctions>(
+			&lite_client_proof.outcome_proof,
+			lite_client_proof.outcome_root_proof,
+			lite_client_proof.block_outcome_root,
+		)
+		.unwrap();
+
+		// a receipt unknown to the node, and a request missing its sender
+		let receipt = json!({ "receipt_id": TX_HASH, "receiver_id": "aurora" });
+		assert_eq!(service.handle("POST", "/proofs/receipt", &receipt.to_string()).0, 502);
+		let request = json!({ "transaction_hash": TX_HASH });
+		assert_eq!(service.handle("POST", "/proofs/transaction", &request.to_string()).0, 400);
+		assert_eq!(service.handle("GET", "/proofs/transaction", "").0, 404);
+	}
+
+	#[test]
+	fn test_transaction_proof_with_tampered_block_proof() {
+		let server = MockRpcServer::start().unwrap();
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let head = proof_head(86697769);
+		let request = json!({ "transaction_hash": TX_HASH, "sender_id": "relay.aurora" });
+		let mut params = request.clone();
+		params["type"] = json!("transaction");
+		params["light_client_head"] =
+			json!(LightClientBlockView::from(head.clone()).current_block_hash());
+		let mut rpc_response: Value = serde_json::from_str(PROOF).unwrap();
+		rpc_response["result"]["block_proof"][0]["hash"] =
+			json!(CryptoHash::hash_bytes(b"other block"));
+		server.respond("EXPERIMENTAL_light_client_proof", params, rpc_response["result"].take());
+
+		let state = LightClientState::new_from_checkpoint(head);
+		let service = ProofService::new(rpc, store(&state));
+		let (status, response) =
+			service.handle("POST", "/proofs/transaction", &request.to_string());
+		assert_eq!(status, 502, "{}", response);
+		assert!(response["error"].as_str().unwrap().contains("block proof"), "{}", response);
+	}
+
+	#[test]
+	fn test_state_proof() {
+		let (state_root, node) = single_leaf_trie(b"counter", b"42");
+		let other_state_root = CryptoHash::hash_bytes(b"other shard");
+		let mut head = head(86697769);
+		head.inner_lite.prev_state_root = merklize(&[other_state_root, state_root]).0;
+
+		let server = MockRpcServer::start().unwrap();
+		let chunk = |state_root: CryptoHash| {
+			json!({
+				"chunk_hash": "3dpE917UNXmFqqzn8eBuqx28LE4GxzimzQ5NubR3xuQ8",
+				"height_included": 86697769,
+				"balance_burnt": "0",
+				"prev_state_root": state_root,
+			})
+		};
+		server.respond(
+			"block",
+			json!({ "block_id": 86697769 }),
+			json!({
+				"header": { "height": 86697769, "prev_hash": head.prev_block_hash },
+				"chunks": [chunk(other_state_root), chunk(state_root)],
+			}),
+		);
+		// the node knows `counter`, but lies about `answer`
+		for (key, value) in [("counter", Some("42")), ("missing", None), ("answer", Some("41"))] {
+			let values = value
+				.map(|value| {
+					vec![json!({ "key": base64::encode(key), "value": base64::encode(value) })]
+				})
+				.unwrap_or_default();
+			server.respond(
+				"query",
+				json!({
+					"request_type": "view_state",
+					"block_id": head.prev_block_hash,
+					"account_id": ACCOUNT_ID,
+					"prefix_base64": base64::encode(key),
+					"include_proof": true,
+				}),
+				json!({
+					"values": values,
+					"proof": [base64::encode(&node)],
+					"block_height": 86697768,
+					"block_hash": head.prev_block_hash,
+				}),
+			);
+		}
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let service = ProofService::new(rpc, store(&LightClientState::new_from_checkpoint(head)));
+		let state_proof = |key: &str| {
+			let request = json!({ "account_id": ACCOUNT_ID, "key": base64::encode(key) });
+			service.handle("POST", "/proofs/state", &request.to_string())
+		};
+
+		let (status, proof) = state_proof("counter");
+		assert_eq!(status, 200, "{}", proof);
+		assert_eq!(proof["value"], base64::encode("42"));
+		assert_eq!(proof["json"]["state_root"], state_root.to_string());
+		let state_proof_borsh: LiteClientStateProof = decode_borsh(&proof);
+		assert_eq!(state_proof_borsh.nodes, vec![node]);
+		assert_eq!(
+			state_proof_borsh.shard_state_root_proof.state_root,
+			LiteClientCryptoHash::from(state_root)
+		);
+
+		let (status, proof) = state_proof("missing");
+		assert_eq!(status, 200, "{}", proof);
+		assert_eq!(proof["value"], Value::Null);
+
+		assert_eq!(state_proof("answer").0, 502);
+		let request = json!({ "account_id": ACCOUNT_ID, "key": "not base64!" });
+		assert_eq!(service.handle("POST", "/proofs/state", &request.to_string()).0, 400);
+	}
+
+	#[test]
+	fn test_server() {
+		let server = MockRpcServer::start().unwrap();
+		let rpc = BlockchainConnector::with_rpc_url(server.url());
+		let state = LightClientState::new_from_checkpoint(head(86697769));
+		let proof_server = ProofServer::start(
+			"127.0.0.1:0".parse().unwrap(),
+			ProofService::new(rpc, store(&state)),
+		)
+		.unwrap();
+
+		let head: Value = ureq::get(&format!("http://{}/head", proof_server.addr()))
+			.call()
+			.unwrap()
+			.into_json()
+			.unwrap();
+		assert_eq!(head["height"], 86697769);
+
+		let bad_request =
+			ureq::post(&format!("http://{}/proofs/state", proof_server.addr())).send_string("{}");
+		assert!(matches!(bad_request, Err(ureq::Error::Status(400, _))));
+	}
+}
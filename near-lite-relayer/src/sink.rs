@@ -0,0 +1,362 @@
+//! Destinations of the relayer outputs
+//!
+//! The relayer doesn't care where the headers and the proofs end up: a light client on another
+//! chain, a file or stdout all implement `HeaderSink` and `ProofSink`.
+
+use std::{
+	fs::{File, OpenOptions},
+	io::{self, BufRead, BufReader, Write},
+	path::{Path, PathBuf},
+};
+
+use near_lite_client::validate_transaction;
+use near_primitives::views::LightClientBlockView as NearLightClientBlockView;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	error::RelayerError,
+	proof_queue::ExecutionProof,
+	state::{LightClientState, NativeHostFunctions},
+};
+
+/// Destination of the headers accepted by the relayer, e.g. a light client on another chain
+pub trait HeaderSink {
+	/// latest header known by the destination, from which relaying can resume
+	fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError>;
+
+	fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError>;
+
+	/// name of the destination in the metrics
+	fn name(&self) -> &str {
+		"custom"
+	}
+}
+
+/// Destination of the proofs of transactions and receipts, relative to a header submitted to the
+/// same destination
+pub trait ProofSink {
+	fn submit_proof(&mut self, proof: ExecutionProof) -> Result<(), RelayerError>;
+}
+
+/// Only logs the accepted headers and proofs
+#[derive(Debug, Default)]
+pub struct LogSink;
+
+impl HeaderSink for LogSink {
+	fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		Ok(None)
+	}
+
+	fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError> {
+		log::info!(
+			"accepted header height={} on epoch={}",
+			block_view.inner_lite.height,
+			block_view.inner_lite.epoch_id
+		);
+		Ok(())
+	}
+
+	fn name(&self) -> &str {
+		"log"
+	}
+}
+
+impl ProofSink for LogSink {
+	fn submit_proof(&mut self, proof: ExecutionProof) -> Result<(), RelayerError> {
+		log::info!(
+			"accepted proof of outcome={} in block height={} against head={}",
+			proof.response.outcome_proof.id,
+			proof.block_height(),
+			proof.light_client_head
+		);
+		Ok(())
+	}
+}
+
+/// Line of the JSONL outputs
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Record {
+	Header(Box<NearLightClientBlockView>),
+	Proof(Box<ExecutionProof>),
+}
+
+fn write_record(writer: &mut impl Write, record: &Record) -> Result<(), RelayerError> {
+	let mut line = serde_json::to_vec(record)?;
+	line.push(b'\n');
+	// a single write, so that a crash can only truncate the last line
+	writer.write_all(&line)?;
+	writer.flush()?;
+	Ok(())
+}
+
+/// Appends the headers and the proofs to a file, one JSON object per line: `{"header": {...}}`
+/// or `{"proof": {...}}`
+#[derive(Debug)]
+pub struct JsonlSink {
+	path: PathBuf,
+	file: File,
+}
+
+impl JsonlSink {
+	/// opens the file at `path`, creating it if needed, and appends to it
+	pub fn open(path: impl Into<PathBuf>) -> Result<Self, RelayerError> {
+		let path = path.into();
+		let file = OpenOptions::new().create(true).append(true).open(&path)?;
+		Ok(Self { path, file })
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl HeaderSink for JsonlSink {
+	/// the last header of the file. A truncated last line, left by a crash, is ignored.
+	fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		let mut latest_known_head = None;
+		for line in BufReader::new(File::open(&self.path)?).lines() {
+			match serde_json::from_str(&line?) {
+				Ok(Record::Header(block_view)) => latest_known_head = Some(*block_view),
+				Ok(Record::Proof(_)) => {},
+				Err(err) => log::warn!("skipping invalid line of {}: {}", self.path.display(), err),
+			}
+		}
+		Ok(latest_known_head)
+	}
+
+	fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError> {
+		write_record(&mut self.file, &Record::Header(Box::new(block_view)))
+	}
+
+	fn name(&self) -> &str {
+		"jsonl"
+	}
+}
+
+impl ProofSink for JsonlSink {
+	fn submit_proof(&mut self, proof: ExecutionProof) -> Result<(), RelayerError> {
+		write_record(&mut self.file, &Record::Proof(Box::new(proof)))
+	}
+}
+
+/// Prints the headers and the proofs to stdout, in the format of `JsonlSink`
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl HeaderSink for StdoutSink {
+	fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		Ok(None)
+	}
+
+	fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError> {
+		write_record(&mut io::stdout().lock(), &Record::Header(Box::new(block_view)))
+	}
+
+	fn name(&self) -> &str {
+		"stdout"
+	}
+}
+
+impl ProofSink for StdoutSink {
+	fn submit_proof(&mut self, proof: ExecutionProof) -> Result<(), RelayerError> {
+		write_record(&mut io::stdout().lock(), &Record::Proof(Box::new(proof)))
+	}
+}
+
+/// In-memory light client: validates the headers and the proofs with `near-lite-client` before
+/// accepting them, as a light client on another chain would. Meant to test end-to-end flows
+/// locally.
+#[derive(Debug)]
+pub struct LiteClientSink {
+	state: LightClientState,
+	headers: Vec<NearLightClientBlockView>,
+	proofs: Vec<ExecutionProof>,
+}
+
+impl LiteClientSink {
+	/// starts from a trusted checkpoint, which must carry the block producers of the next epoch
+	pub fn new(checkpoint: NearLightClientBlockView) -> Result<Self, RelayerError> {
+		if checkpoint.next_bps.is_none() {
+			return Err(RelayerError::Sink("the checkpoint has no next_bps".to_string()));
+		}
+		Ok(Self {
+			state: LightClientState::new_from_checkpoint(checkpoint),
+			headers: Vec::new(),
+			proofs: Vec::new(),
+		})
+	}
+
+	/// headers accepted since the checkpoint
+	pub fn headers(&self) -> &[NearLightClientBlockView] {
+		&self.headers
+	}
+
+	/// proofs accepted since the checkpoint
+	pub fn proofs(&self) -> &[ExecutionProof] {
+		&self.proofs
+	}
+}
+
+impl HeaderSink for LiteClientSink {
+	fn latest_known_head(&self) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		Ok(Some(self.state.head().clone()))
+	}
+
+	fn submit(&mut self, block_view: NearLightClientBlockView) -> Result<(), RelayerError> {
+		self.state
+			.validate_head(&block_view)
+			.map_err(|err| RelayerError::Sink(err.to_string()))?;
+		self.headers.push(block_view);
+		Ok(())
+	}
+
+	fn name(&self) -> &str {
+		"lite_client"
+	}
+}
+
+impl ProofSink for LiteClientSink {
+	fn submit_proof(&mut self, proof: ExecutionProof) -> Result<(), RelayerError> {
+		// the block merkle tree of the head only covers the blocks before it
+		let block_height = proof.block_height();
+		if block_height >= self.state.head_height() {
+			return Err(RelayerError::Sink(format!(
+				"the block height={} of the proof is not covered by the head",
+				block_height
+			)));
+		}
+		let block_merkle_root = proof.block_merkle_root();
+		if block_merkle_root != self.state.head().inner_lite.block_merkle_root {
+			return Err(RelayerError::Sink(format!(
+				"invalid block proof: it leads to {} instead of the head's block merkle root",
+				block_merkle_root
+			)));
+		}
+
+		let lite_client_proof = proof.to_lite_client();
+		validate_transaction::<NativeHostFunctions>(
+			&lite_client_proof.outcome_proof,
+			lite_client_proof.outcome_root_proof,
+			lite_client_proof.block_outcome_root,
+		)
+		.map_err(|err| RelayerError::Sink(format!("invalid proof: {:?}", err)))?;
+
+		self.proofs.push(proof);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs};
+
+	use near_primitives::hash::CryptoHash;
+
+	use super::*;
+	use crate::test_utils::{proof, proof_head};
+
+	fn block_view(rpc_response: &str) -> NearLightClientBlockView {
+		let rpc_response: serde_json::Value = serde_json::from_str(rpc_response).unwrap();
+		serde_json::from_value(rpc_response["result"].clone()).unwrap()
+	}
+
+	fn blocks() -> Vec<NearLightClientBlockView> {
+		[
+			include_str!("../../test-fixtures/light_client_block_86380519.json"),
+			include_str!("../../test-fixtures/light_client_block_86441383.json"),
+			include_str!("../../test-fixtures/light_client_block_86455909.json"),
+			include_str!("../../test-fixtures/light_client_block_86456070.json"),
+		]
+		.into_iter()
+		.map(block_view)
+		.collect()
+	}
+
+	#[test]
+	fn test_jsonl_sink() {
+		let path =
+			env::temp_dir().join(format!("near-lite-relayer-sink-{}.jsonl", std::process::id()));
+		let _ = fs::remove_file(&path);
+		let blocks = blocks();
+
+		let mut sink = JsonlSink::open(&path).unwrap();
+		assert_eq!(sink.latest_known_head().unwrap(), None);
+		sink.submit(blocks[1].clone()).unwrap();
+		sink.submit(blocks[2].clone()).unwrap();
+		drop(sink);
+
+		// a crash while writing the last line
+		let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+		file.write_all(b"{\"header\": {\"prev_bl").unwrap();
+
+		let sink = JsonlSink::open(&path).unwrap();
+		assert_eq!(sink.latest_known_head().unwrap(), Some(blocks[2].clone()));
+		assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn test_lite_client_sink() {
+		let blocks = blocks();
+		let mut sink = LiteClientSink::new(blocks[0].clone()).unwrap();
+		assert_eq!(sink.latest_known_head().unwrap(), Some(blocks[0].clone()));
+
+		for block_view in &blocks[1..] {
+			sink.submit(block_view.clone()).unwrap();
+		}
+		assert_eq!(sink.headers(), &blocks[1..]);
+		assert_eq!(sink.latest_known_head().unwrap(), Some(blocks[3].clone()));
+
+		// already known
+		assert!(matches!(sink.submit(blocks[2].clone()), Err(RelayerError::Sink(_))));
+	}
+
+	#[test]
+	fn test_lite_client_sink_rejects_invalid_header() {
+		let blocks = blocks();
+		let mut sink = LiteClientSink::new(blocks[0].clone()).unwrap();
+		let mut tampered_block = blocks[1].clone();
+		tampered_block.inner_lite.prev_state_root = Default::default();
+
+		assert!(matches!(sink.submit(tampered_block), Err(RelayerError::Sink(_))));
+		assert!(sink.headers().is_empty());
+		// the block producers of the epoch of 86455909 are only known after 86441383
+		assert!(matches!(sink.submit(blocks[2].clone()), Err(RelayerError::Sink(_))));
+	}
+
+	#[test]
+	fn test_lite_client_sink_proof() {
+		let proof = proof();
+		let mut sink = LiteClientSink::new(proof_head(proof.block_height() - 1)).unwrap();
+		assert!(matches!(sink.submit_proof(proof.clone()), Err(RelayerError::Sink(_))));
+
+		// the checkpoint is trusted as is, and the fixtures end before the block of the proof
+		let mut sink = LiteClientSink::new(proof_head(proof.block_height())).unwrap();
+		assert!(matches!(sink.submit_proof(proof.clone()), Err(RelayerError::Sink(_))));
+		let mut sink = LiteClientSink::new(proof_head(proof.block_height() + 1)).unwrap();
+
+		let mut tampered_proof = proof.clone();
+		tampered_proof.response.outcome_proof.outcome.gas_burnt += 1;
+		assert!(matches!(sink.submit_proof(tampered_proof), Err(RelayerError::Sink(_))));
+		sink.submit_proof(proof).unwrap();
+		assert_eq!(sink.proofs().len(), 1);
+	}
+
+	#[test]
+	fn test_lite_client_sink_rejects_proof_of_another_block() {
+		let proof = proof();
+		let mut sink = LiteClientSink::new(proof_head(proof.block_height() + 1)).unwrap();
+
+		let mut tampered_proof = proof.clone();
+		tampered_proof.response.block_proof[0].hash = CryptoHash::hash_bytes(b"other block");
+		let err = sink.submit_proof(tampered_proof).unwrap_err();
+		assert!(err.to_string().contains("block proof"), "{}", err);
+		// any field of the header changes the hash of the block, not only its outcome root
+		let mut tampered_proof = proof;
+		tampered_proof.response.block_header_lite.inner_lite.timestamp += 1;
+		assert!(matches!(sink.submit_proof(tampered_proof), Err(RelayerError::Sink(_))));
+		assert!(sink.proofs().is_empty());
+	}
+}
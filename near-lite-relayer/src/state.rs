@@ -1,18 +1,38 @@
-use std::collections::HashMap;
+//! # Light client state of the relayer
+//!
+//! The relayer pre-validates the headers with `near-lite-client`, the same rules as the light
+//! client they are submitted to, so that it only submits what the destination accepts.
 
-use borsh::BorshSerialize;
-use near_crypto::{PublicKey, Signature};
+use std::collections::{BTreeMap, HashMap};
+
+use near_lite_client::{
+	validate_head, CryptoHash as LiteClientCryptoHash, LightClientBlockView as LiteClientBlockView,
+	NearLiteClientError, ValidatorStakeView as LiteClientValidatorStakeView,
+};
 use near_primitives::{
 	hash::CryptoHash,
 	views::{
 		validator_stake_view::ValidatorStakeView, LightClientBlockView as NearLightClientBlockView,
 	},
 };
-use sha2::{Digest, Sha256};
+use near_primitives_wasm::HostFunctions;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	client_proof::LightClientBlockView,
+	error::{HeaderRejection, RelayerError},
+};
+
+pub(crate) struct NativeHostFunctions;
 
-use crate::client_proof::{reconstruct_light_client_block_view_fields, LightClientBlockView};
+impl HostFunctions for NativeHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		use sha2::Digest;
+		sha2::Sha256::digest(data).into()
+	}
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LightClientState {
 	head: NearLightClientBlockView,
 
@@ -45,121 +65,113 @@ impl LightClientState {
 			head: checkpoint,
 		}
 	}
-	// TODO: not sure how Polkadot works, but the return should be a `Result` instead
-
-	//The light client updates its head with the information from LightClientBlockView iff:
-
-	// 1. The height of the block is higher than the height of the current head;
-	// 2. The epoch of the block is equal to the epoch_id or next_epoch_id known for the current
-	// head; 3. If the epoch of the block is equal to the next_epoch_id of the head, then next_bps
-	// is not None; 4. approvals_after_next contain valid signatures on approval_message from the
-	// block producers of the corresponding epoch
-	// 5. The signatures present in approvals_after_next correspond to more than 2/3 of the total
-	// stake (see next section). 6. If next_bps is not none, sha256(borsh(next_bps)) corresponds to
-	// the next_bp_hash in inner_lite.
 
-	// QUESTION: do we also want to pass the block hash received from the RPC?
-	// it's not on the spec, but it's an extra validation
-	pub fn validate_head(&mut self, block_view: &LightClientBlockView) -> bool {
+	/// Validates `block_view` against the head with `near-lite-client`, and makes it the new head
+	/// if it is valid. An invalid header is a `RelayerError::InvalidHeader`, and leaves the state
+	/// unchanged.
+	pub fn validate_head(
+		&mut self,
+		block_view: &NearLightClientBlockView,
+	) -> Result<(), RelayerError> {
 		log::trace!(
 			"Validating block view for height={} on epoch={}",
 			block_view.inner_lite.height,
 			block_view.inner_lite.epoch_id
 		);
-		let head = &mut self.head;
-		let (_current_block_hash, _next_block_hash, approval_message) =
-			reconstruct_light_client_block_view_fields(block_view);
-
-		// (1)
-		if block_view.inner_lite.height <= head.inner_lite.height {
-			return false
-		}
-
-		// (2)
-		if ![head.inner_lite.epoch_id, head.inner_lite.next_epoch_id]
-			.contains(&block_view.inner_lite.epoch_id)
-		{
-			return false
+		// tells unknown epochs, e.g. the checkpoint's own epoch, apart from invalid headers
+		if !self.epoch_block_producers.contains_key(&block_view.inner_lite.epoch_id) {
+			return Err(RelayerError::InvalidHeader {
+				reason: HeaderRejection::UnknownEpoch,
+				message: format!(
+					"unknown block producers of the epoch {}",
+					block_view.inner_lite.epoch_id
+				),
+			})
 		}
+		let head = LiteClientBlockView::from(self.head.clone());
+		let lite_block_view = LiteClientBlockView::from(block_view.clone());
+		let epoch_block_producers = self
+			.epoch_block_producers
+			.iter()
+			.map(|(epoch_id, block_producers)| {
+				let block_producers = block_producers
+					.iter()
+					.cloned()
+					.map(LiteClientValidatorStakeView::from)
+					.collect();
+				(LiteClientCryptoHash::from(*epoch_id), block_producers)
+			})
+			.collect::<BTreeMap<_, _>>();
+		validate_head::<NativeHostFunctions>(&head, &lite_block_view, &epoch_block_producers)
+			.map_err(|err| RelayerError::InvalidHeader {
+				reason: match err {
+					NearLiteClientError::InvalidLiteBlock(_) => HeaderRejection::InvalidBlock,
+					NearLiteClientError::SignatureVerification(_) =>
+						HeaderRejection::InvalidSignature,
+					_ => HeaderRejection::Other,
+				},
+				message: format!("{:?}", err),
+			})?;
 
-		// (3)
-		if block_view.inner_lite.epoch_id == head.inner_lite.next_epoch_id &&
-			block_view.next_bps.is_none()
-		{
-			return false
+		if let Some(next_bps) = &block_view.next_bps {
+			self.epoch_block_producers
+				.insert(block_view.inner_lite.next_epoch_id, next_bps.clone());
 		}
-
-		//  (4) and (5)
-		let mut total_stake = 0;
-		let mut approved_stake = 0;
-
-		let epoch_block_producers = &self.epoch_block_producers[&block_view.inner_lite.epoch_id];
-
-		for (maybe_signature, block_producer) in
-			block_view.approvals_after_next.iter().zip(epoch_block_producers.iter())
-		{
-			let validator_stake = block_producer.clone().into_validator_stake();
-			let bp_stake = validator_stake.stake();
-			total_stake += bp_stake;
-
-			if maybe_signature.is_none() {
-				continue
-			}
-
-			approved_stake += bp_stake;
-
-			if !verify_signature(validator_stake.public_key(), maybe_signature, &approval_message) {
-				return false
-			}
-		}
-
-		let threshold = total_stake * 2 / 3;
-		if approved_stake <= threshold {
-			return false
-		}
-
-		// # (6)
-		if block_view.next_bps.is_some() {
-			if Sha256::digest(block_view.next_bps.as_deref().unwrap().try_to_vec().unwrap())
-				.as_slice() != block_view.inner_lite.next_bp_hash.as_ref()
-			{
-				return false
-			}
-		}
-
-		self.epoch_block_producers.insert(
-			block_view.inner_lite.next_epoch_id,
-			block_view.next_bps.as_ref().unwrap().clone(),
-		);
-		*head = NearLightClientBlockView::from(block_view);
+		self.head = block_view.clone();
+		// only the block producers of the epoch of the head, or of the next one, sign the blocks
+		// following the head
+		let (epoch_id, next_epoch_id) = (self.epoch_id(), self.head.inner_lite.next_epoch_id);
+		self.epoch_block_producers
+			.retain(|id, _| *id == epoch_id || *id == next_epoch_id);
 		log::trace!(
 			"Succesfully updated the head for height={} on epoch={}",
 			block_view.inner_lite.height,
 			block_view.inner_lite.epoch_id
 		);
-		true
+		Ok(())
+	}
+
+	pub fn head(&self) -> &NearLightClientBlockView {
+		&self.head
 	}
 
-	pub fn current_block_hash(&self) -> u64 {
+	pub fn head_hash(&self) -> CryptoHash {
+		LightClientBlockView::from(self.head.clone()).current_block_hash()
+	}
+
+	pub fn head_height(&self) -> u64 {
 		self.head.inner_lite.height
 	}
-}
 
-fn verify_signature(
-	public_key: &PublicKey,
-	maybe_signature: &Option<Signature>,
-	approval_message: impl AsRef<[u8]>,
-) -> bool {
-	maybe_signature
-		.as_ref()
-		.map(|s| s.verify(approval_message.as_ref(), public_key))
-		.unwrap_or(false)
+	/// epoch of the head
+	pub fn epoch_id(&self) -> CryptoHash {
+		self.head.inner_lite.epoch_id
+	}
+
+	/// block producers of the epoch of the head; unknown for the epoch of a checkpoint, whose
+	/// block producers of the next epoch only are trusted
+	pub fn validators(&self) -> Option<&[ValidatorStakeView]> {
+		self.epoch_block_producers.get(&self.epoch_id()).map(Vec::as_slice)
+	}
+
+	/// block producers of the epoch of the head and of the next one
+	pub fn epoch_block_producers(&self) -> &HashMap<CryptoHash, Vec<ValidatorStakeView>> {
+		&self.epoch_block_producers
+	}
+
+	/// restores a state from its parts, e.g. as saved by a `RelayerStore`
+	pub fn from_parts(
+		head: NearLightClientBlockView,
+		epoch_block_producers: HashMap<CryptoHash, Vec<ValidatorStakeView>>,
+	) -> Self {
+		Self { head, epoch_block_producers }
+	}
 }
 
 #[cfg(test)]
 mod tests {
-	use near_crypto::ED25519PublicKey;
-	use near_sdk::{json_types::Base58CryptoHash, CryptoHash as JSONCryptoHash};
+	use borsh::BorshSerialize;
+	use near_primitives_wasm::{PublicKey, Signature};
 
 	use crate::{
 		client_block::get_client_block_view,
@@ -1674,55 +1686,77 @@ mod tests {
         "id": "idontcare"
     }
     "#;
+	fn block_view(client_block_response: &str) -> NearLightClientBlockView {
+		NearLightClientBlockView::from(&get_client_block_view(client_block_response).unwrap())
+	}
+
 	#[test]
 	fn test_validate_head_valid_block_next_epoch() {
-		let client_block_view_checkpoint = NearLightClientBlockView::from(
-			&get_client_block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH).unwrap(),
-		);
-		let client_block_view = get_client_block_view(CLIENT_BLOCK_RESPONSE).unwrap();
+		let client_block_view_checkpoint = block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH);
+		let client_block_view = block_view(CLIENT_BLOCK_RESPONSE);
 		let mut light_client_state =
-			LightClientState::new_from_checkpoint(client_block_view_checkpoint.into());
+			LightClientState::new_from_checkpoint(client_block_view_checkpoint.clone());
 
 		let next_epoch_id = light_client_state.head.inner_lite.next_epoch_id;
 		assert_eq!(light_client_state.epoch_block_producers[&next_epoch_id].len(), 70);
+		// the block producers of the epoch of the checkpoint are not trusted
+		assert_eq!(light_client_state.validators(), None);
 
-		assert!(light_client_state.validate_head(&client_block_view));
+		light_client_state.validate_head(&client_block_view).unwrap();
+		assert_eq!(light_client_state.head_height(), client_block_view.inner_lite.height);
+		assert_eq!(
+			light_client_state.head_hash(),
+			LightClientBlockView::from(client_block_view.clone()).current_block_hash()
+		);
+		assert_eq!(light_client_state.epoch_id(), next_epoch_id);
+		assert_eq!(light_client_state.validators().unwrap().len(), 70);
 	}
 
 	#[test]
 	fn test_validate_head_valid_block_next_epoch_and_then_next_height() {
-		let client_block_view_checkpoint = NearLightClientBlockView::from(
-			&get_client_block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH).unwrap(),
-		);
-		let client_block_view = get_client_block_view(CLIENT_BLOCK_RESPONSE).unwrap();
+		let client_block_view_checkpoint = block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH);
+		let client_block_view = block_view(CLIENT_BLOCK_RESPONSE);
 		let mut light_client_state =
-			LightClientState::new_from_checkpoint(client_block_view_checkpoint.into());
+			LightClientState::new_from_checkpoint(client_block_view_checkpoint);
 
 		let next_epoch_id = light_client_state.head.inner_lite.next_epoch_id;
 		assert_eq!(light_client_state.epoch_block_producers[&next_epoch_id].len(), 70);
 
-		assert!(light_client_state.validate_head(&client_block_view));
-		let client_block_view_next_height =
-			get_client_block_view(CLIENT_BLOCK_RESPONSE_NEXT_BLOCK).unwrap();
-		assert!(light_client_state.validate_head(&client_block_view_next_height));
+		light_client_state.validate_head(&client_block_view).unwrap();
+		let client_block_view_next_height = block_view(CLIENT_BLOCK_RESPONSE_NEXT_BLOCK);
+		light_client_state.validate_head(&client_block_view_next_height).unwrap();
+		assert_eq!(light_client_state.head(), &client_block_view_next_height);
 	}
 
 	#[test]
-	#[should_panic]
 	fn test_validate_head_invalid_block() {
-		let client_block_view_checkpoint = NearLightClientBlockView::from(
-			&get_client_block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH).unwrap(),
-		);
-		let client_block_view = get_client_block_view(CLIENT_BLOCK_RESPONSE).unwrap();
+		let client_block_view_checkpoint = block_view(CLIENT_RESPONSE_PREVIOUS_EPOCH);
+		let client_block_view = block_view(CLIENT_BLOCK_RESPONSE);
 		let mut light_client_state =
-			LightClientState::new_from_checkpoint(client_block_view_checkpoint.into());
+			LightClientState::new_from_checkpoint(client_block_view_checkpoint.clone());
+
+		let client_block_view_next_height = block_view(CLIENT_BLOCK_RESPONSE_NEXT_BLOCK);
+		let mut tampered_block_view = client_block_view_next_height.clone();
+		tampered_block_view.inner_lite.prev_state_root = Default::default();
+		// the approvals don't sign the tampered header
+		assert!(matches!(
+			light_client_state.validate_head(&tampered_block_view),
+			Err(RelayerError::InvalidHeader { reason: HeaderRejection::InvalidSignature, .. })
+		));
+		assert_eq!(light_client_state.head(), &client_block_view_checkpoint);
 
-		let client_block_view_next_height =
-			get_client_block_view(CLIENT_BLOCK_RESPONSE_NEXT_BLOCK).unwrap();
-		assert!(light_client_state.validate_head(&client_block_view_next_height));
-		assert!(light_client_state.validate_head(&client_block_view));
+		light_client_state.validate_head(&client_block_view_next_height).unwrap();
+		// not ahead of the head
+		assert!(matches!(
+			light_client_state.validate_head(&client_block_view),
+			Err(RelayerError::InvalidHeader { reason: HeaderRejection::InvalidBlock, .. })
+		));
+		assert_eq!(light_client_state.head(), &client_block_view_next_height);
 	}
 
+	const APPROVAL_SIGNATURE: &'static str =
+		"5X6Fq8PeNtc6sv84QeKPd5MG4La9K3rBMDYbKtkJ8VZcC6k1ehFd9NP3PuBqwL5gMqoqj7nkzSQZzJzKDJLPJRCA";
+
 	#[test]
 	fn test_verify_signature() {
 		let client_block_view = get_client_block_view(CLIENT_BLOCK_RESPONSE).unwrap();
@@ -1730,19 +1764,21 @@ mod tests {
 			client_block_view.next_block_inner_hash,
 			client_block_view.current_block_hash(),
 		);
-		let signature_hash = bs58::decode("5X6Fq8PeNtc6sv84QeKPd5MG4La9K3rBMDYbKtkJ8VZcC6k1ehFd9NP3PuBqwL5gMqoqj7nkzSQZzJzKDJLPJRCA").into_vec().unwrap();
-		let pubkey_hash = JSONCryptoHash::from(
-			Base58CryptoHash::try_from("Fy6quR4nBhrEnDyEuPWoAdBP5tzNbuEZsEd91Q5pQnXB").unwrap(),
+		let signature = Signature::from_raw(&bs58::decode(APPROVAL_SIGNATURE).into_vec().unwrap());
+		let public_key = PublicKey::from_raw(
+			&bs58::decode("Fy6quR4nBhrEnDyEuPWoAdBP5tzNbuEZsEd91Q5pQnXB").into_vec().unwrap(),
 		);
-		let public_key = PublicKey::ED25519(ED25519PublicKey::try_from(pubkey_hash).unwrap());
-		let signature =
-			Signature::from_parts(near_crypto::KeyType::ED25519, signature_hash.as_ref()).unwrap();
 
 		let approval_message = [
 			ApprovalInner::Endorsement(next_block_hash).try_to_vec().unwrap(),
 			(client_block_view.inner_lite.height + 2).to_le().try_to_vec().unwrap(),
 		]
 		.concat();
-		assert!(verify_signature(&public_key, &Some(signature), approval_message));
+		assert!(NativeHostFunctions::verify(
+			signature.clone(),
+			&approval_message,
+			public_key.clone()
+		));
+		assert!(!NativeHostFunctions::verify(signature, &approval_message[1..], public_key));
 	}
 }
@@ -0,0 +1,469 @@
+//! # Relayer store
+//!
+//! Persists what the relayer needs to resume after a restart or a crash: the validated head, the
+//! block producers of the epochs, the history of the submissions and their headers, the queued
+//! proof requests and the last block scanned for account activity.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use near_primitives::{
+	hash::CryptoHash,
+	views::{
+		validator_stake_view::ValidatorStakeView, LightClientBlockView as NearLightClientBlockView,
+	},
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sled::{transaction::ConflictableTransactionError, Transactional};
+
+use crate::{blockchain_connector::ProofRequest, error::RelayerError, state::LightClientState};
+
+const HEAD_KEY: &[u8] = b"head";
+const SCANNED_HEIGHT_KEY: &[u8] = b"scanned_height";
+
+/// Header submitted to the sink
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Submission {
+	pub height: u64,
+	pub block_hash: CryptoHash,
+	pub epoch_id: CryptoHash,
+	/// seconds since the unix epoch
+	pub submitted_at: u64,
+}
+
+impl Submission {
+	pub fn new(height: u64, block_hash: CryptoHash, epoch_id: CryptoHash) -> Self {
+		let submitted_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |elapsed| elapsed.as_secs());
+		Self { height, block_hash, epoch_id, submitted_at }
+	}
+}
+
+/// Block producers of an epoch, as known by the store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredEpoch {
+	epoch_id: CryptoHash,
+	/// height of the head when the block producers were first stored, which orders the epochs
+	since_height: u64,
+	block_producers: Vec<ValidatorStakeView>,
+}
+
+/// Storage backend of the relayer
+pub trait RelayerStore {
+	/// the state saved by the last `save_submission`, if any
+	fn load_state(&self) -> Result<Option<LightClientState>, RelayerError>;
+
+	/// saves `submission` and the state following it, atomically
+	fn save_submission(
+		&mut self,
+		state: &LightClientState,
+		submission: Submission,
+	) -> Result<(), RelayerError>;
+
+	/// submissions of the epochs still stored, by increasing height
+	fn submissions(&self) -> Result<Vec<Submission>, RelayerError>;
+
+	/// header of the submission at `height`, if it is still stored
+	fn submitted_block(
+		&self,
+		height: u64,
+	) -> Result<Option<NearLightClientBlockView>, RelayerError>;
+
+	/// queues a proof request, returning its id
+	fn enqueue_proof_request(&mut self, request: ProofRequest) -> Result<u64, RelayerError>;
+
+	/// queued proof requests, in the order they were queued
+	fn proof_requests(&self) -> Result<Vec<(u64, ProofRequest)>, RelayerError>;
+
+	fn remove_proof_request(&mut self, id: u64) -> Result<(), RelayerError>;
+
+	/// height of the last block scanned for account activity, if any
+	fn scanned_height(&self) -> Result<Option<u64>, RelayerError>;
+
+	fn save_scanned_height(&mut self, height: u64) -> Result<(), RelayerError>;
+}
+
+/// Keeps everything in memory and never prunes the submissions, e.g. for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+	state: Option<LightClientState>,
+	submissions: BTreeMap<u64, Submission>,
+	blocks: BTreeMap<u64, NearLightClientBlockView>,
+	proof_requests: BTreeMap<u64, ProofRequest>,
+	next_proof_request_id: u64,
+	scanned_height: Option<u64>,
+}
+
+impl RelayerStore for MemoryStore {
+	fn load_state(&self) -> Result<Option<LightClientState>, RelayerError> {
+		Ok(self.state.clone())
+	}
+
+	fn save_submission(
+		&mut self,
+		state: &LightClientState,
+		submission: Submission,
+	) -> Result<(), RelayerError> {
+		self.state = Some(state.clone());
+		self.blocks.insert(submission.height, state.head().clone());
+		self.submissions.insert(submission.height, submission);
+		Ok(())
+	}
+
+	fn submissions(&self) -> Result<Vec<Submission>, RelayerError> {
+		Ok(self.submissions.values().cloned().collect())
+	}
+
+	fn submitted_block(
+		&self,
+		height: u64,
+	) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		Ok(self.blocks.get(&height).cloned())
+	}
+
+	fn enqueue_proof_request(&mut self, request: ProofRequest) -> Result<u64, RelayerError> {
+		let id = self.next_proof_request_id;
+		self.next_proof_request_id += 1;
+		self.proof_requests.insert(id, request);
+		Ok(id)
+	}
+
+	fn proof_requests(&self) -> Result<Vec<(u64, ProofRequest)>, RelayerError> {
+		Ok(self.proof_requests.iter().map(|(id, request)| (*id, request.clone())).collect())
+	}
+
+	fn remove_proof_request(&mut self, id: u64) -> Result<(), RelayerError> {
+		self.proof_requests.remove(&id);
+		Ok(())
+	}
+
+	fn scanned_height(&self) -> Result<Option<u64>, RelayerError> {
+		Ok(self.scanned_height)
+	}
+
+	fn save_scanned_height(&mut self, height: u64) -> Result<(), RelayerError> {
+		self.scanned_height = Some(height);
+		Ok(())
+	}
+}
+
+/// Embedded on-disk store, backed by sled. Every write is flushed to disk before returning.
+///
+/// The block producers of the `epoch_retention` most recent epochs, and the submissions of those
+/// epochs, are kept; older ones are pruned. Clones share the same database, e.g. to read it from
+/// another thread.
+#[derive(Clone)]
+pub struct SledStore {
+	db: sled::Db,
+	/// head of the state and scanned height
+	meta: sled::Tree,
+	/// `StoredEpoch`s by epoch id
+	epochs: sled::Tree,
+	/// `Submission`s by big endian height
+	submissions: sled::Tree,
+	/// submitted headers by big endian height
+	blocks: sled::Tree,
+	/// `ProofRequest`s by big endian id
+	proof_requests: sled::Tree,
+	epoch_retention: usize,
+}
+
+impl SledStore {
+	/// the minimum `epoch_retention`: the epoch of the head and the next one
+	pub const MIN_EPOCH_RETENTION: usize = 2;
+
+	pub fn open(path: impl AsRef<Path>, epoch_retention: usize) -> Result<Self, RelayerError> {
+		let db = sled::open(path)?;
+		Ok(Self {
+			meta: db.open_tree("meta")?,
+			epochs: db.open_tree("epochs")?,
+			submissions: db.open_tree("submissions")?,
+			blocks: db.open_tree("blocks")?,
+			proof_requests: db.open_tree("proof_requests")?,
+			db,
+			epoch_retention: epoch_retention.max(Self::MIN_EPOCH_RETENTION),
+		})
+	}
+
+	fn stored_epochs(&self) -> Result<Vec<StoredEpoch>, RelayerError> {
+		self.epochs.iter().values().map(|value| decode(&value?)).collect()
+	}
+
+	/// removes the epochs beyond the retention, and their submissions. Pruning is not part of the
+	/// transaction of `save_submission`: after a crash, it is caught up by the next one.
+	fn prune(&self, state: &LightClientState) -> Result<(), RelayerError> {
+		// the epochs of the state are always retained, then the most recent other ones
+		let (mut retained, mut stored_epochs): (Vec<_>, Vec<_>) = self
+			.stored_epochs()?
+			.into_iter()
+			.partition(|epoch| state.epoch_block_producers().contains_key(&epoch.epoch_id));
+		stored_epochs.sort_by_key(|epoch| std::cmp::Reverse(epoch.since_height));
+		for epoch in stored_epochs {
+			if retained.len() < self.epoch_retention {
+				retained.push(epoch);
+				continue;
+			}
+			log::debug!("pruning epoch={}", epoch.epoch_id);
+			self.epochs.remove(epoch.epoch_id.as_ref())?;
+			for value in self.submissions.iter().values() {
+				let submission: Submission = decode(&value?)?;
+				if submission.epoch_id == epoch.epoch_id {
+					self.submissions.remove(submission.height.to_be_bytes())?;
+					self.blocks.remove(submission.height.to_be_bytes())?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+
+impl RelayerStore for SledStore {
+	fn load_state(&self) -> Result<Option<LightClientState>, RelayerError> {
+		let head = match self.meta.get(HEAD_KEY)? {
+			Some(head) => decode::<NearLightClientBlockView>(&head)?,
+			None => return Ok(None),
+		};
+		let mut epoch_block_producers = HashMap::new();
+		for epoch_id in [head.inner_lite.epoch_id, head.inner_lite.next_epoch_id] {
+			if let Some(epoch) = self.epochs.get(epoch_id.as_ref())? {
+				let epoch: StoredEpoch = decode(&epoch)?;
+				epoch_block_producers.insert(epoch_id, epoch.block_producers);
+			}
+		}
+		Ok(Some(LightClientState::from_parts(head, epoch_block_producers)))
+	}
+
+	fn save_submission(
+		&mut self,
+		state: &LightClientState,
+		submission: Submission,
+	) -> Result<(), RelayerError> {
+		let head = encode(state.head())?;
+		let mut new_epochs = Vec::new();
+		for (epoch_id, block_producers) in state.epoch_block_producers() {
+			if !self.epochs.contains_key(epoch_id.as_ref())? {
+				let epoch = StoredEpoch {
+					epoch_id: *epoch_id,
+					since_height: submission.height,
+					block_producers: block_producers.clone(),
+				};
+				new_epochs.push((*epoch_id, encode(&epoch)?));
+			}
+		}
+		let height = submission.height.to_be_bytes();
+		let submission = encode(&submission)?;
+
+		(&self.meta, &self.epochs, &self.submissions, &self.blocks).transaction(
+			|(meta, epochs, submissions, blocks)| {
+				meta.insert(HEAD_KEY, head.as_slice())?;
+				for (epoch_id, epoch) in &new_epochs {
+					epochs.insert(epoch_id.as_ref(), epoch.as_slice())?;
+				}
+				submissions.insert(&height[..], submission.as_slice())?;
+				blocks.insert(&height[..], head.as_slice())?;
+				Ok::<_, ConflictableTransactionError>(())
+			},
+		)?;
+		self.prune(state)?;
+		self.db.flush()?;
+		Ok(())
+	}
+
+	fn submissions(&self) -> Result<Vec<Submission>, RelayerError> {
+		self.submissions.iter().values().map(|value| decode(&value?)).collect()
+	}
+
+	fn submitted_block(
+		&self,
+		height: u64,
+	) -> Result<Option<NearLightClientBlockView>, RelayerError> {
+		self.blocks.get(height.to_be_bytes())?.map(|block| decode(&block)).transpose()
+	}
+
+	fn enqueue_proof_request(&mut self, request: ProofRequest) -> Result<u64, RelayerError> {
+		let id = self.db.generate_id()?;
+		self.proof_requests.insert(id.to_be_bytes(), encode(&request)?)?;
+		self.db.flush()?;
+		Ok(id)
+	}
+
+	fn proof_requests(&self) -> Result<Vec<(u64, ProofRequest)>, RelayerError> {
+		self.proof_requests
+			.iter()
+			.map(|entry| {
+				let (id, request) = entry?;
+				let id =
+					u64::from_be_bytes(id.as_ref().try_into().map_err(|_| {
+						RelayerError::Decode("invalid proof request id".to_string())
+					})?);
+				Ok((id, decode(&request)?))
+			})
+			.collect()
+	}
+
+	fn remove_proof_request(&mut self, id: u64) -> Result<(), RelayerError> {
+		self.proof_requests.remove(id.to_be_bytes())?;
+		self.db.flush()?;
+		Ok(())
+	}
+
+	fn scanned_height(&self) -> Result<Option<u64>, RelayerError> {
+		self.meta.get(SCANNED_HEIGHT_KEY)?.map(|height| decode(&height)).transpose()
+	}
+
+	fn save_scanned_height(&mut self, height: u64) -> Result<(), RelayerError> {
+		self.meta.insert(SCANNED_HEIGHT_KEY, encode(&height)?)?;
+		self.db.flush()?;
+		Ok(())
+	}
+}
+
+fn encode(value: &impl Serialize) -> Result<Vec<u8>, RelayerError> {
+	Ok(serde_json::to_vec(value)?)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, RelayerError> {
+	Ok(serde_json::from_slice(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs};
+
+	use near_sdk::json_types::Base58CryptoHash;
+
+	use super::*;
+	use crate::test_utils::TX_HASH;
+
+	fn blocks() -> Vec<NearLightClientBlockView> {
+		[
+			include_str!("../../test-fixtures/light_client_block_86380519.json"),
+			include_str!("../../test-fixtures/light_client_block_86441383.json"),
+			include_str!("../../test-fixtures/light_client_block_86455909.json"),
+			include_str!("../../test-fixtures/light_client_block_86456070.json"),
+		]
+		.into_iter()
+		.map(|rpc_response| {
+			let rpc_response: serde_json::Value = serde_json::from_str(rpc_response).unwrap();
+			serde_json::from_value(rpc_response["result"].clone()).unwrap()
+		})
+		.collect()
+	}
+
+	/// validates the blocks following the checkpoint, saving them to `store`
+	fn follow(
+		store: &mut impl RelayerStore,
+		state: &mut LightClientState,
+		blocks: &[NearLightClientBlockView],
+	) {
+		for block_view in blocks {
+			state.validate_head(block_view).unwrap();
+			let submission =
+				Submission::new(state.head_height(), state.head_hash(), state.epoch_id());
+			store.save_submission(state, submission).unwrap();
+		}
+	}
+
+	fn heights(submissions: Vec<Submission>) -> Vec<u64> {
+		submissions.iter().map(|submission| submission.height).collect()
+	}
+
+	fn temp_dir(name: &str) -> std::path::PathBuf {
+		let path =
+			env::temp_dir().join(format!("near-lite-relayer-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		path
+	}
+
+	#[test]
+	fn test_sled_store_resume() {
+		let path = temp_dir("store-resume");
+		let blocks = blocks();
+		let mut state = LightClientState::new_from_checkpoint(blocks[0].clone());
+
+		let mut store = SledStore::open(&path, 8).unwrap();
+		assert!(store.load_state().unwrap().is_none());
+		follow(&mut store, &mut state, &blocks[1..3]);
+		drop(store);
+
+		let mut store = SledStore::open(&path, 8).unwrap();
+		let mut resumed_state = store.load_state().unwrap().unwrap();
+		assert_eq!(resumed_state.head(), state.head());
+		assert_eq!(resumed_state.epoch_block_producers(), state.epoch_block_producers());
+		assert_eq!(heights(store.submissions().unwrap()), vec![86441383, 86455909]);
+
+		follow(&mut store, &mut resumed_state, &blocks[3..]);
+		assert_eq!(heights(store.submissions().unwrap()), vec![86441383, 86455909, 86456070]);
+		assert_eq!(store.submitted_block(86455909).unwrap().as_ref(), Some(&blocks[2]));
+		assert_eq!(store.submitted_block(86455910).unwrap(), None);
+
+		fs::remove_dir_all(&path).unwrap();
+	}
+
+	#[test]
+	fn test_sled_store_prune() {
+		let path = temp_dir("store-prune");
+		let blocks = blocks();
+		let mut state = LightClientState::new_from_checkpoint(blocks[0].clone());
+
+		// the blocks are in the epochs 5iyA, GHmq and GHmq
+		let mut store = SledStore::open(&path, 0).unwrap();
+		follow(&mut store, &mut state, &blocks[1..]);
+		assert_eq!(state.epoch_block_producers().len(), 2);
+		assert_eq!(store.stored_epochs().unwrap().len(), 2);
+		assert_eq!(heights(store.submissions().unwrap()), vec![86455909, 86456070]);
+		assert_eq!(store.submitted_block(86441383).unwrap(), None);
+		assert_eq!(store.submitted_block(86456070).unwrap().as_ref(), Some(&blocks[3]));
+
+		fs::remove_dir_all(&path).unwrap();
+	}
+
+	#[test]
+	fn test_proof_requests() {
+		let path = temp_dir("store-proof-requests");
+		let request = |sender_id: &str| ProofRequest::Transaction {
+			tx_hash: Base58CryptoHash::try_from(TX_HASH).unwrap(),
+			sender_id: sender_id.to_string(),
+		};
+
+		let mut sled_store = SledStore::open(&path, 8).unwrap();
+		let mut memory_store = MemoryStore::default();
+		let stores: [&mut dyn RelayerStore; 2] = [&mut sled_store, &mut memory_store];
+		for store in stores {
+			let first = store.enqueue_proof_request(request("alice.testnet")).unwrap();
+			let second = store.enqueue_proof_request(request("bob.testnet")).unwrap();
+			store.remove_proof_request(first).unwrap();
+			assert_eq!(store.proof_requests().unwrap(), vec![(second, request("bob.testnet"))]);
+		}
+		drop(sled_store);
+
+		let sled_store = SledStore::open(&path, 8).unwrap();
+		assert_eq!(sled_store.proof_requests().unwrap().len(), 1);
+
+		fs::remove_dir_all(&path).unwrap();
+	}
+
+	#[test]
+	fn test_scanned_height() {
+		let path = temp_dir("store-scanned-height");
+		let mut sled_store = SledStore::open(&path, 8).unwrap();
+		let mut memory_store = MemoryStore::default();
+		let stores: [&mut dyn RelayerStore; 2] = [&mut sled_store, &mut memory_store];
+		for store in stores {
+			assert_eq!(store.scanned_height().unwrap(), None);
+			store.save_scanned_height(86697768).unwrap();
+			store.save_scanned_height(86697769).unwrap();
+			assert_eq!(store.scanned_height().unwrap(), Some(86697769));
+		}
+		drop(sled_store);
+
+		let sled_store = SledStore::open(&path, 8).unwrap();
+		assert_eq!(sled_store.scanned_height().unwrap(), Some(86697769));
+
+		fs::remove_dir_all(&path).unwrap();
+	}
+}
@@ -0,0 +1,57 @@
+//! Fixtures shared by the tests of the relayer
+
+use near_primitives::{hash::CryptoHash, views::LightClientBlockView as NearLightClientBlockView};
+use near_sdk::json_types::Base58CryptoHash;
+
+use crate::{blockchain_connector::ProofRequest, proof_queue::ExecutionProof};
+
+/// `EXPERIMENTAL_light_client_proof` response recorded on testnet for `TX_HASH`
+pub(crate) const PROOF: &str = include_str!(
+	"../../test-fixtures/light_client_proof_8HoqDvJGYrSjaejXpv2PsK8c5NUvqhU3EcUFkgq18jx9.json"
+);
+/// transaction of `relay.aurora`, its outcome is in the block 86697768
+pub(crate) const TX_HASH: &str = "8HoqDvJGYrSjaejXpv2PsK8c5NUvqhU3EcUFkgq18jx9";
+/// light client head the proof of `PROOF` has been requested for
+pub(crate) const FINAL_BLOCK_PREV_HASH: &str = "4E2VN7cUVSb8ek761H4cRo57ERTWBKbcB9uEBDS2cWhD";
+/// contract whose state is proven by the state proof tests
+pub(crate) const ACCOUNT_ID: &str = "ibc.testnet";
+
+/// header at `height`, in the epoch of the block of the proof
+pub(crate) fn head(height: u64) -> NearLightClientBlockView {
+	let rpc_response: serde_json::Value =
+		serde_json::from_str(include_str!("../../test-fixtures/light_client_block_86456070.json"))
+			.unwrap();
+	let mut head: NearLightClientBlockView =
+		serde_json::from_value(rpc_response["result"].clone()).unwrap();
+	head.inner_lite.height = height;
+	head
+}
+
+/// proof of `PROOF`, whose block is 86697768
+pub(crate) fn proof() -> ExecutionProof {
+	let rpc_response: serde_json::Value = serde_json::from_str(PROOF).unwrap();
+	ExecutionProof {
+		request: ProofRequest::Transaction {
+			tx_hash: Base58CryptoHash::try_from(TX_HASH).unwrap(),
+			sender_id: "relay.aurora".to_string(),
+		},
+		light_client_head: Default::default(),
+		response: serde_json::from_value(rpc_response["result"].clone()).unwrap(),
+	}
+}
+
+/// header at `height`, whose block merkle tree holds the block of the outcome of `PROOF`
+pub(crate) fn proof_head(height: u64) -> NearLightClientBlockView {
+	let mut head = head(height);
+	head.inner_lite.block_merkle_root = proof().block_merkle_root();
+	head
+}
+
+/// trie holding the single entry `key` of the contract state of `ACCOUNT_ID`, as its root and
+/// its only node
+pub(crate) fn single_leaf_trie(key: &[u8], value: &[u8]) -> (CryptoHash, Vec<u8>) {
+	// `ContractData` trie key of the account
+	let trie_key = [&[9][..], ACCOUNT_ID.as_bytes(), b",", key].concat();
+	let (state_root, node) = near_lite_client::test_utils::single_leaf_trie(&trie_key, value);
+	(CryptoHash(state_root.0), node)
+}
@@ -0,0 +1,216 @@
+//! # Commitment verification
+//!
+//! NEAR's state trie does not fit into an ICS-23 `ProofSpec`: leaves only carry the
+//! remaining nibbles of the key and every node is hashed together with its
+//! `memory_usage`. Instead of converting the proofs, this module exposes
+//! `verify_membership` and `verify_non_membership` with the same shape as the ones used by
+//! ibc-rs's client states, so that they can be plugged into a generic IBC handler.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives_wasm::{CryptoHash, HostFunctions};
+use no_std_compat as std;
+use std::{string::String, vec::Vec};
+
+use crate::state_proof::{verify_state_proof, RawTrieNodeWithSize};
+
+/// Key prefix under which the commitments are stored in the NEAR state trie
+/// (e.g. the `ContractData` prefix of the IBC contract).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitmentPrefix(pub Vec<u8>);
+
+/// State root the proofs are verified against (i.e: `prev_state_root` of a validated block).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitmentRoot(pub CryptoHash);
+
+/// Borsh encoded list of raw trie nodes, as returned by the `proof` field of the
+/// `view_state` RPC query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitmentProofBytes(pub Vec<u8>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommitmentError {
+	EmptyProof,
+	DecodingError(String),
+	MembershipVerificationFailed,
+	NonMembershipVerificationFailed,
+}
+
+impl CommitmentPrefix {
+	/// Full trie key of `path` under this prefix
+	pub fn apply(&self, path: &[u8]) -> Vec<u8> {
+		[self.0.as_slice(), path].concat()
+	}
+}
+
+impl CommitmentProofBytes {
+	/// Decodes the proof into the trie nodes expected by `verify_state_proof`
+	pub fn decode(&self) -> Result<Vec<RawTrieNodeWithSize>, CommitmentError> {
+		let raw_nodes = Vec::<Vec<u8>>::try_from_slice(&self.0)
+			.map_err(|_| CommitmentError::DecodingError("proof is not a list of nodes".into()))?;
+		if raw_nodes.is_empty() {
+			return Err(CommitmentError::EmptyProof);
+		}
+		raw_nodes
+			.iter()
+			.map(|node| RawTrieNodeWithSize::decode(node).map_err(CommitmentError::DecodingError))
+			.collect()
+	}
+}
+
+impl From<Vec<Vec<u8>>> for CommitmentProofBytes {
+	fn from(raw_nodes: Vec<Vec<u8>>) -> Self {
+		Self(raw_nodes.try_to_vec().expect("serializing into a vector never fails; qed"))
+	}
+}
+
+/// Verifies that `value` is stored under `prefix ++ path` in the trie with root `root`.
+pub fn verify_membership<H: HostFunctions>(
+	prefix: &CommitmentPrefix,
+	proof: &CommitmentProofBytes,
+	root: &CommitmentRoot,
+	path: &[u8],
+	value: Vec<u8>,
+) -> Result<(), CommitmentError> {
+	let levels = proof.decode()?;
+	if !verify_state_proof::<H>(&prefix.apply(path), &levels, Some(&value), root.0) {
+		return Err(CommitmentError::MembershipVerificationFailed);
+	}
+	Ok(())
+}
+
+/// Verifies that nothing is stored under `prefix ++ path` in the trie with root `root`.
+pub fn verify_non_membership<H: HostFunctions>(
+	prefix: &CommitmentPrefix,
+	proof: &CommitmentProofBytes,
+	root: &CommitmentRoot,
+	path: &[u8],
+) -> Result<(), CommitmentError> {
+	let levels = proof.decode()?;
+	if !verify_state_proof::<H>(&prefix.apply(path), &levels, None, root.0) {
+		return Err(CommitmentError::NonMembershipVerificationFailed);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use core::str::FromStr;
+
+	use near_primitives::hash::CryptoHash as NearCryptoHash;
+
+	use super::*;
+
+	struct MockedHostFunctions;
+	impl HostFunctions for MockedHostFunctions {
+		fn sha256(data: &[u8]) -> [u8; 32] {
+			use sha2::Digest;
+			sha2::Sha256::digest(data).into()
+		}
+	}
+
+	// same proof used in `state_proof`, taken from NEAR CORE
+	fn doge_proof() -> Vec<Vec<u8>> {
+		[
+			"0301000000165a1e73ea8e3686db1c5938a8d22912c2e2936fe475d933c310c452cbfb4306ff5303000000000000",
+			"011001aa33bd98698cbe05e77f027fc9bd32783d433a9a9f6213e80a523dd82e7fab108d0c9da6ee86e6957cd608f5a9bac9b9069ceaf555431744a4b09311ad62898e1f03000000000000",
+			"0302000000006f7240cc9614e62849c6e37a9b74e3e46707c42e517061622d45a1c62849d744d31002000000000000",
+			"02040000006806f86a17f0ae04d4ad43168fcdd0651c0fe99108b992f5ac398fd6bf235331400044a09c32d7c008aa4cd3f44adbd62bfe01a7811f21591aef4df91634d4530d1dda01000000000000",
+			"01880095581dfffd1f4f734b2c69c960e63193c18fae785d0c20d9a1c52c7c1374b5efe5fe9244cc68ea29ca00d2207f0033bb007be8aed9a00e27e23f1c84456bf02f7201000000000000",
+			"02050000006588ef4db6a357d6d9ca7d0c9feb69bd8e2f236ab88459da5c193b7fa95031874000e2919fa19c4fe63ae7741fc7e42ab169792efc46b715749822df86bb74c977afd300000000000000",
+			"00010000003504000000b3a1984ba0b1d8ad7f9dc881dfd9c9dc78c76c647a7692fbbfd6fcdcb9d9a1216a00000000000000"
+		].into_iter().map(|p| hex::decode(p).unwrap()).collect()
+	}
+
+	fn root() -> CommitmentRoot {
+		CommitmentRoot(CryptoHash(
+			NearCryptoHash::from_str("hvKZryexWm5CPgcvB3VKxKp1uRQWZnSDALLNa9raXJV")
+				.unwrap()
+				.into(),
+		))
+	}
+
+	#[test]
+	fn test_verify_membership() {
+		let prefix = CommitmentPrefix(b"do".to_vec());
+		let proof = CommitmentProofBytes::from(doge_proof());
+
+		assert_eq!(
+			verify_membership::<MockedHostFunctions>(
+				&prefix,
+				&proof,
+				&root(),
+				b"ge",
+				b"coin".to_vec()
+			),
+			Ok(())
+		);
+		assert_eq!(
+			verify_membership::<MockedHostFunctions>(
+				&prefix,
+				&proof,
+				&root(),
+				b"ge",
+				b"coin_not_present".to_vec()
+			),
+			Err(CommitmentError::MembershipVerificationFailed)
+		);
+		// the key is the prefix plus the path, the path alone is not in the trie
+		assert_eq!(
+			verify_membership::<MockedHostFunctions>(
+				&CommitmentPrefix::default(),
+				&proof,
+				&root(),
+				b"ge",
+				b"coin".to_vec()
+			),
+			Err(CommitmentError::MembershipVerificationFailed)
+		);
+	}
+
+	#[test]
+	fn test_verify_non_membership() {
+		let proof = CommitmentProofBytes::from(doge_proof()[..1].to_vec());
+
+		assert_eq!(
+			verify_non_membership::<MockedHostFunctions>(
+				&CommitmentPrefix(b"white_".to_vec()),
+				&proof,
+				&root(),
+				b"horse",
+			),
+			Ok(())
+		);
+		assert_eq!(
+			verify_non_membership::<MockedHostFunctions>(
+				&CommitmentPrefix(b"do".to_vec()),
+				&CommitmentProofBytes::from(doge_proof()),
+				&root(),
+				b"ge",
+			),
+			Err(CommitmentError::NonMembershipVerificationFailed)
+		);
+	}
+
+	#[test]
+	fn test_malformed_proofs() {
+		assert_eq!(
+			verify_non_membership::<MockedHostFunctions>(
+				&CommitmentPrefix::default(),
+				&CommitmentProofBytes::from(Vec::new()),
+				&root(),
+				b"doge",
+			),
+			Err(CommitmentError::EmptyProof)
+		);
+		assert!(matches!(
+			verify_membership::<MockedHostFunctions>(
+				&CommitmentPrefix::default(),
+				&CommitmentProofBytes([1, 2, 3].to_vec()),
+				&root(),
+				b"doge",
+				b"coin".to_vec(),
+			),
+			Err(CommitmentError::DecodingError(_))
+		));
+	}
+}
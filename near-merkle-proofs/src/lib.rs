@@ -7,6 +7,7 @@
 
 extern crate no_std_compat as std;
 
+pub mod commitment;
 mod nibble;
 pub mod state_proof;
 
@@ -65,9 +66,8 @@ impl CachedNodes {
 				if let Some(cached_node) = self.inner.get(&(*level, *index)) {
 					match hash {
 						// given that the hash is cached, only check for a potential error
-						Some(hash) if hash != cached_node => {
-							return Err("cached_node != hash".into())
-						},
+						Some(hash) if hash != cached_node =>
+							return Err("cached_node != hash".into()),
 						_ => return Ok(()),
 					}
 				}
@@ -142,14 +142,12 @@ impl<HF: HostFunctions> ProofBatchVerifier<HF> {
 				match cached_value {
 					None => {
 						match merkle_path_item.direction {
-							Direction::Left => {
+							Direction::Left =>
 								hash =
-									Ok(hash_borsh::<_, HF>(&(merkle_path_item.hash, hash.unwrap())))
-							},
-							Direction::Right => {
+									Ok(hash_borsh::<_, HF>(&(merkle_path_item.hash, hash.unwrap()))),
+							Direction::Right =>
 								hash =
-									Ok(hash_borsh::<_, HF>(&(hash.unwrap(), merkle_path_item.hash)))
-							},
+									Ok(hash_borsh::<_, HF>(&(hash.unwrap(), merkle_path_item.hash))),
 						};
 						// update the cache
 						self.cached_nodes.inner.insert((*level, *index), hash.clone().unwrap());
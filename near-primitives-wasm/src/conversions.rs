@@ -0,0 +1,508 @@
+//! # Conversions from and into the `near-primitives` views
+//!
+//! Every view of this crate converts from and into its `near-primitives` counterpart field by
+//! field, rather than through their borsh encodings, which only match by chance. The conversions
+//! are lossless, but for:
+//! - ed25519 signatures that aren't canonical for `near-crypto`, which don't convert
+//! - account ids, which are only validated by `near-primitives`
+//! - the `metadata` of the outcomes, which is not part of their hash and not kept here; it is the
+//!   default one in the other direction
+
+use borsh::{
+	maybestd::string::{String, ToString},
+	BorshDeserialize, BorshSerialize,
+};
+use near_crypto::{
+	ED25519PublicKey, KeyType as NearKeyType, PublicKey as NearPublicKey,
+	Signature as NearSignature,
+};
+use near_primitives::{
+	hash::CryptoHash as NearCryptoHash,
+	merkle::{Direction as NearDirection, MerklePathItem as NearMerklePathItem},
+	types::AccountId as NearAccountId,
+	views::{
+		validator_stake_view::{
+			ValidatorStakeView as NearValidatorStakeView,
+			ValidatorStakeViewV1 as NearValidatorStakeViewV1,
+		},
+		BlockHeaderInnerLiteView as NearBlockHeaderInnerLiteView, ExecutionMetadataView,
+		ExecutionOutcomeView as NearExecutionOutcomeView, ExecutionOutcomeWithIdView,
+		ExecutionStatusView, LightClientBlockLiteView as NearLightClientBlockLiteView,
+		LightClientBlockView as NearLightClientBlockView,
+	},
+};
+use sp_core::ed25519::Signature as Ed25519Signature;
+use sp_std::prelude::*;
+
+use crate::{
+	BlockHeaderInnerLiteView, ConversionError, CryptoHash, Direction, ExecutionOutcomeView,
+	KeyType, LightClientBlockLiteView, LightClientBlockView, MerklePathItem, OutcomeProof,
+	PublicKey, Signature, ValidatorStakeView, ValidatorStakeViewV1,
+};
+
+impl From<NearCryptoHash> for CryptoHash {
+	fn from(hash: NearCryptoHash) -> Self {
+		Self(hash.0)
+	}
+}
+
+impl From<CryptoHash> for NearCryptoHash {
+	fn from(hash: CryptoHash) -> Self {
+		Self(hash.0)
+	}
+}
+
+impl From<NearPublicKey> for PublicKey {
+	fn from(public_key: NearPublicKey) -> Self {
+		match public_key {
+			NearPublicKey::ED25519(public_key) => Self::Ed25519(public_key.0),
+			NearPublicKey::SECP256K1(public_key) => Self::Secp256k1(public_key.into()),
+		}
+	}
+}
+
+impl From<PublicKey> for NearPublicKey {
+	fn from(public_key: PublicKey) -> Self {
+		match public_key {
+			PublicKey::Ed25519(public_key) => Self::ED25519(ED25519PublicKey(public_key)),
+			PublicKey::Secp256k1(public_key) => Self::SECP256K1(public_key.into()),
+		}
+	}
+}
+
+impl From<NearSignature> for Signature {
+	fn from(signature: NearSignature) -> Self {
+		match signature {
+			NearSignature::ED25519(signature) =>
+				Self::Ed25519(Ed25519Signature::from_raw(signature.to_bytes())),
+			NearSignature::SECP256K1(signature) => Self::Secp256k1(signature.into()),
+		}
+	}
+}
+
+impl TryFrom<Signature> for NearSignature {
+	type Error = ConversionError;
+	fn try_from(signature: Signature) -> Result<Self, Self::Error> {
+		let key_type = match signature.key_type() {
+			KeyType::Ed25519 => NearKeyType::ED25519,
+			KeyType::Secp256k1 => NearKeyType::SECP256K1,
+		};
+		NearSignature::from_parts(key_type, signature.as_bytes())
+			.map_err(|err| ConversionError(err.to_string()))
+	}
+}
+
+impl From<NearValidatorStakeView> for ValidatorStakeView {
+	fn from(view: NearValidatorStakeView) -> Self {
+		let NearValidatorStakeView::V1(view) = view;
+		Self::V1(ValidatorStakeViewV1 {
+			account_id: view.account_id.into(),
+			public_key: view.public_key.into(),
+			stake: view.stake,
+		})
+	}
+}
+
+impl TryFrom<ValidatorStakeView> for NearValidatorStakeView {
+	type Error = ConversionError;
+	fn try_from(view: ValidatorStakeView) -> Result<Self, Self::Error> {
+		let ValidatorStakeView::V1(view) = view;
+		Ok(Self::V1(NearValidatorStakeViewV1 {
+			account_id: account_id(view.account_id)?,
+			public_key: view.public_key.into(),
+			stake: view.stake,
+		}))
+	}
+}
+
+impl From<NearBlockHeaderInnerLiteView> for BlockHeaderInnerLiteView {
+	fn from(view: NearBlockHeaderInnerLiteView) -> Self {
+		Self {
+			height: view.height,
+			epoch_id: view.epoch_id.into(),
+			next_epoch_id: view.next_epoch_id.into(),
+			prev_state_root: view.prev_state_root.into(),
+			outcome_root: view.outcome_root.into(),
+			timestamp: view.timestamp,
+			timestamp_nanosec: view.timestamp_nanosec,
+			next_bp_hash: view.next_bp_hash.into(),
+			block_merkle_root: view.block_merkle_root.into(),
+		}
+	}
+}
+
+impl From<BlockHeaderInnerLiteView> for NearBlockHeaderInnerLiteView {
+	fn from(view: BlockHeaderInnerLiteView) -> Self {
+		Self {
+			height: view.height,
+			epoch_id: view.epoch_id.into(),
+			next_epoch_id: view.next_epoch_id.into(),
+			prev_state_root: view.prev_state_root.into(),
+			outcome_root: view.outcome_root.into(),
+			timestamp: view.timestamp,
+			timestamp_nanosec: view.timestamp_nanosec,
+			next_bp_hash: view.next_bp_hash.into(),
+			block_merkle_root: view.block_merkle_root.into(),
+		}
+	}
+}
+
+impl From<NearLightClientBlockLiteView> for LightClientBlockLiteView {
+	fn from(view: NearLightClientBlockLiteView) -> Self {
+		Self {
+			prev_block_hash: view.prev_block_hash.into(),
+			inner_rest_hash: view.inner_rest_hash.into(),
+			inner_lite: view.inner_lite.into(),
+		}
+	}
+}
+
+impl From<LightClientBlockLiteView> for NearLightClientBlockLiteView {
+	fn from(view: LightClientBlockLiteView) -> Self {
+		Self {
+			prev_block_hash: view.prev_block_hash.into(),
+			inner_rest_hash: view.inner_rest_hash.into(),
+			inner_lite: view.inner_lite.into(),
+		}
+	}
+}
+
+impl From<NearLightClientBlockView> for LightClientBlockView {
+	fn from(view: NearLightClientBlockView) -> Self {
+		Self {
+			prev_block_hash: view.prev_block_hash.into(),
+			next_block_inner_hash: view.next_block_inner_hash.into(),
+			inner_lite: view.inner_lite.into(),
+			inner_rest_hash: view.inner_rest_hash.into(),
+			next_bps: view.next_bps.map(|next_bps| next_bps.into_iter().map(Into::into).collect()),
+			approvals_after_next: view
+				.approvals_after_next
+				.into_iter()
+				.map(|approval| approval.map(Into::into))
+				.collect(),
+		}
+	}
+}
+
+impl TryFrom<LightClientBlockView> for NearLightClientBlockView {
+	type Error = ConversionError;
+	fn try_from(view: LightClientBlockView) -> Result<Self, Self::Error> {
+		Ok(Self {
+			prev_block_hash: view.prev_block_hash.into(),
+			next_block_inner_hash: view.next_block_inner_hash.into(),
+			inner_lite: view.inner_lite.into(),
+			inner_rest_hash: view.inner_rest_hash.into(),
+			next_bps: view
+				.next_bps
+				.map(|next_bps| next_bps.into_iter().map(TryInto::try_into).collect())
+				.transpose()?,
+			approvals_after_next: view
+				.approvals_after_next
+				.into_iter()
+				.map(|approval| approval.map(TryInto::try_into).transpose())
+				.collect::<Result<_, _>>()?,
+		})
+	}
+}
+
+impl From<NearDirection> for Direction {
+	fn from(direction: NearDirection) -> Self {
+		match direction {
+			NearDirection::Left => Self::Left,
+			NearDirection::Right => Self::Right,
+		}
+	}
+}
+
+impl From<Direction> for NearDirection {
+	fn from(direction: Direction) -> Self {
+		match direction {
+			Direction::Left => Self::Left,
+			Direction::Right => Self::Right,
+		}
+	}
+}
+
+impl From<NearMerklePathItem> for MerklePathItem {
+	fn from(item: NearMerklePathItem) -> Self {
+		Self { hash: item.hash.into(), direction: item.direction.into() }
+	}
+}
+
+impl From<MerklePathItem> for NearMerklePathItem {
+	fn from(item: MerklePathItem) -> Self {
+		Self { hash: item.hash.into(), direction: item.direction.into() }
+	}
+}
+
+/// `status` is the borsh encoding of the `ExecutionStatusView`, as hashed in the outcome
+impl From<NearExecutionOutcomeView> for ExecutionOutcomeView {
+	fn from(view: NearExecutionOutcomeView) -> Self {
+		Self {
+			logs: view.logs,
+			receipt_ids: view.receipt_ids.into_iter().map(Into::into).collect(),
+			gas_burnt: view.gas_burnt,
+			tokens_burnt: view.tokens_burnt,
+			executor_id: view.executor_id.into(),
+			status: view.status.try_to_vec().expect("borsh encoding into a vec doesn't fail"),
+		}
+	}
+}
+
+impl TryFrom<ExecutionOutcomeView> for NearExecutionOutcomeView {
+	type Error = ConversionError;
+	fn try_from(view: ExecutionOutcomeView) -> Result<Self, Self::Error> {
+		Ok(Self {
+			logs: view.logs,
+			receipt_ids: view.receipt_ids.into_iter().map(Into::into).collect(),
+			gas_burnt: view.gas_burnt,
+			tokens_burnt: view.tokens_burnt,
+			executor_id: account_id(view.executor_id)?,
+			status: ExecutionStatusView::try_from_slice(&view.status)
+				.map_err(|err| ConversionError(err.to_string()))?,
+			metadata: ExecutionMetadataView::default(),
+		})
+	}
+}
+
+impl From<ExecutionOutcomeWithIdView> for OutcomeProof {
+	fn from(view: ExecutionOutcomeWithIdView) -> Self {
+		Self {
+			proof: view.proof.into_iter().map(Into::into).collect(),
+			block_hash: view.block_hash.into(),
+			id: view.id.into(),
+			outcome: view.outcome.into(),
+		}
+	}
+}
+
+impl TryFrom<OutcomeProof> for ExecutionOutcomeWithIdView {
+	type Error = ConversionError;
+	fn try_from(proof: OutcomeProof) -> Result<Self, Self::Error> {
+		Ok(Self {
+			proof: proof.proof.into_iter().map(Into::into).collect(),
+			block_hash: proof.block_hash.into(),
+			id: proof.id.into(),
+			outcome: proof.outcome.try_into()?,
+		})
+	}
+}
+
+fn account_id(account_id: String) -> Result<NearAccountId, ConversionError> {
+	account_id.parse().map_err(|err| ConversionError(format!("{}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+	use near_crypto::SecretKey;
+	use proptest::{collection::vec, option, prelude::*};
+
+	use super::*;
+
+	fn near_hash() -> impl Strategy<Value = NearCryptoHash> {
+		any::<[u8; 32]>().prop_map(NearCryptoHash)
+	}
+
+	fn near_account_id() -> impl Strategy<Value = NearAccountId> {
+		"[a-z0-9]{2,16}(\\.[a-z0-9]{2,16}){0,2}".prop_map(|account_id| account_id.parse().unwrap())
+	}
+
+	fn near_public_key() -> impl Strategy<Value = NearPublicKey> {
+		prop_oneof![
+			any::<[u8; 32]>().prop_map(|key| NearPublicKey::ED25519(ED25519PublicKey(key))),
+			any::<u64>().prop_map(|seed| {
+				SecretKey::from_seed(NearKeyType::SECP256K1, &seed.to_string()).public_key()
+			}),
+		]
+	}
+
+	/// signatures from `near-crypto`, as arbitrary bytes are not all valid signatures for it
+	fn near_signature() -> impl Strategy<Value = NearSignature> {
+		prop_oneof![
+			(any::<u64>(), vec(any::<u8>(), 0..64)).prop_map(|(seed, message)| {
+				SecretKey::from_seed(NearKeyType::ED25519, &seed.to_string()).sign(&message)
+			}),
+			// secp256k1 signatures are of 32 bytes hashes
+			(any::<u64>(), any::<[u8; 32]>()).prop_map(|(seed, message)| {
+				SecretKey::from_seed(NearKeyType::SECP256K1, &seed.to_string()).sign(&message)
+			}),
+		]
+	}
+
+	fn near_validator_stake_view() -> impl Strategy<Value = NearValidatorStakeView> {
+		(near_account_id(), near_public_key(), any::<u128>()).prop_map(
+			|(account_id, public_key, stake)| {
+				NearValidatorStakeView::V1(NearValidatorStakeViewV1 {
+					account_id,
+					public_key,
+					stake,
+				})
+			},
+		)
+	}
+
+	fn near_inner_lite() -> impl Strategy<Value = NearBlockHeaderInnerLiteView> {
+		(
+			any::<(u64, u64, u64)>(),
+			[near_hash(), near_hash(), near_hash(), near_hash(), near_hash(), near_hash()],
+		)
+			.prop_map(|((height, timestamp, timestamp_nanosec), hashes)| {
+				let [epoch_id, next_epoch_id, prev_state_root, outcome_root, next_bp_hash, block_merkle_root] =
+					hashes;
+				NearBlockHeaderInnerLiteView {
+					height,
+					epoch_id,
+					next_epoch_id,
+					prev_state_root,
+					outcome_root,
+					timestamp,
+					timestamp_nanosec,
+					next_bp_hash,
+					block_merkle_root,
+				}
+			})
+	}
+
+	fn near_light_client_block_view() -> impl Strategy<Value = NearLightClientBlockView> {
+		(
+			[near_hash(), near_hash(), near_hash()],
+			near_inner_lite(),
+			option::of(vec(near_validator_stake_view(), 0..4)),
+			vec(option::of(near_signature()), 0..4),
+		)
+			.prop_map(|(hashes, inner_lite, next_bps, approvals_after_next)| {
+				let [prev_block_hash, next_block_inner_hash, inner_rest_hash] = hashes;
+				NearLightClientBlockView {
+					prev_block_hash,
+					next_block_inner_hash,
+					inner_lite,
+					inner_rest_hash,
+					next_bps,
+					approvals_after_next,
+				}
+			})
+	}
+
+	fn near_merkle_path() -> impl Strategy<Value = Vec<NearMerklePathItem>> {
+		vec(
+			(near_hash(), any::<bool>()).prop_map(|(hash, left)| NearMerklePathItem {
+				hash,
+				direction: if left { NearDirection::Left } else { NearDirection::Right },
+			}),
+			0..8,
+		)
+	}
+
+	fn near_execution_status() -> impl Strategy<Value = ExecutionStatusView> {
+		prop_oneof![
+			Just(ExecutionStatusView::Unknown),
+			".*".prop_map(ExecutionStatusView::SuccessValue),
+			near_hash().prop_map(ExecutionStatusView::SuccessReceiptId),
+		]
+	}
+
+	fn near_outcome_with_id() -> impl Strategy<Value = ExecutionOutcomeWithIdView> {
+		(
+			(near_merkle_path(), near_hash(), near_hash()),
+			vec(".*", 0..4),
+			vec(near_hash(), 0..4),
+			any::<(u64, u128)>(),
+			near_account_id(),
+			near_execution_status(),
+		)
+			.prop_map(
+				|(
+					(proof, block_hash, id),
+					logs,
+					receipt_ids,
+					(gas_burnt, tokens_burnt),
+					executor_id,
+					status,
+				)| ExecutionOutcomeWithIdView {
+					proof,
+					block_hash,
+					id,
+					outcome: NearExecutionOutcomeView {
+						logs,
+						receipt_ids,
+						gas_burnt,
+						tokens_burnt,
+						executor_id,
+						status,
+						metadata: ExecutionMetadataView::default(),
+					},
+				},
+			)
+	}
+
+	proptest! {
+		#[test]
+		fn test_light_client_block_view_round_trip(view in near_light_client_block_view()) {
+			let converted = LightClientBlockView::from(view.clone());
+			// the same hash as the one of `near-primitives`
+			prop_assert_eq!(
+				converted.inner_lite.timestamp_nanosec,
+				view.inner_lite.timestamp_nanosec
+			);
+			prop_assert_eq!(NearLightClientBlockView::try_from(converted).unwrap(), view);
+		}
+
+		#[test]
+		fn test_light_client_block_lite_view_round_trip(
+			prev_block_hash in near_hash(),
+			inner_rest_hash in near_hash(),
+			inner_lite in near_inner_lite(),
+		) {
+			let view = NearLightClientBlockLiteView { prev_block_hash, inner_rest_hash, inner_lite };
+			let converted = LightClientBlockLiteView::from(view.clone());
+			// no `PartialEq` for the `near-primitives` view
+			prop_assert_eq!(
+				NearLightClientBlockLiteView::from(converted).try_to_vec().unwrap(),
+				view.try_to_vec().unwrap()
+			);
+		}
+
+		#[test]
+		fn test_outcome_proof_round_trip(view in near_outcome_with_id()) {
+			let converted = OutcomeProof::from(view.clone());
+			prop_assert_eq!(&converted.outcome.status, &view.outcome.status.try_to_vec().unwrap());
+			prop_assert_eq!(ExecutionOutcomeWithIdView::try_from(converted).unwrap(), view);
+		}
+
+		/// the conversions agree with the borsh encodings where they are the same
+		#[test]
+		fn test_borsh_compatibility(
+			view in near_light_client_block_view(),
+			path in near_merkle_path(),
+		) {
+			let converted = LightClientBlockView::from(view.clone());
+			prop_assert_eq!(converted.try_to_vec().unwrap(), view.try_to_vec().unwrap());
+			let converted = path.iter().cloned().map(MerklePathItem::from).collect::<Vec<_>>();
+			prop_assert_eq!(converted.try_to_vec().unwrap(), path.try_to_vec().unwrap());
+		}
+	}
+
+	#[test]
+	fn test_secp256k1_keys() {
+		let secret_key = SecretKey::from_seed(NearKeyType::SECP256K1, "secp256k1");
+		let public_key = PublicKey::from(secret_key.public_key());
+		assert_eq!(public_key.key_type(), KeyType::Secp256k1);
+		assert_eq!(NearPublicKey::from(public_key), secret_key.public_key());
+		let signature = Signature::from(secret_key.sign(&[0; 32]));
+		assert_eq!(signature.key_type(), KeyType::Secp256k1);
+		assert_eq!(NearSignature::try_from(signature).unwrap(), secret_key.sign(&[0; 32]));
+	}
+
+	#[test]
+	fn test_invalid_account_id() {
+		let view = ExecutionOutcomeView {
+			logs: Vec::new(),
+			receipt_ids: Vec::new(),
+			gas_burnt: 0,
+			tokens_burnt: 0,
+			executor_id: "Invalid Account".into(),
+			status: vec![0],
+		};
+		assert!(NearExecutionOutcomeView::try_from(view).is_err());
+	}
+}
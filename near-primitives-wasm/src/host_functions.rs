@@ -1,9 +1,57 @@
+use sp_core::ed25519::{Public as Ed25519Public, Signature as Ed25519Signature};
+
 use crate::{PublicKey, Signature};
 
+/// order of the secp256k1 curve divided by two: the `s` of a signature is at most this, as
+/// `near-crypto` only verifies signatures with the lower `s`
+const SECP256K1_HALF_ORDER: [u8; 32] = [
+	0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
 pub trait HostFunctions {
 	fn sha256(data: &[u8]) -> [u8; 32];
 
+	/// Verifies `signature` of `data` by `public_key` like `near-crypto`: a signature never
+	/// verifies with a key of another type, and the `data` of a secp256k1 signature is the 32
+	/// bytes hash that was signed.
 	fn verify(signature: Signature, data: impl AsRef<[u8]>, public_key: PublicKey) -> bool {
-		signature.verify(data, public_key)
+		match (signature, public_key) {
+			(Signature::Ed25519(signature), PublicKey::Ed25519(public_key)) =>
+				Self::ed25519_verify(&signature.0, data.as_ref(), &public_key),
+			(Signature::Secp256k1(signature), PublicKey::Secp256k1(public_key)) => {
+				let message: &[u8; 32] = match data.as_ref().try_into() {
+					Ok(message) => message,
+					Err(_) => return false,
+				};
+				signature[64] <= 3 &&
+					signature[32..64] <= SECP256K1_HALF_ORDER[..] &&
+					Self::secp256k1_ecdsa_recover(&signature, message) == Some(public_key)
+			},
+			_ => false,
+		}
+	}
+
+	fn ed25519_verify(signature: &[u8; 64], data: &[u8], public_key: &[u8; 32]) -> bool {
+		sp_io::crypto::ed25519_verify(
+			&Ed25519Signature(*signature),
+			data,
+			&Ed25519Public(*public_key),
+		)
+	}
+
+	/// the public key, uncompressed and without its `0x04` prefix, of the secp256k1 `signature`
+	/// of `message`
+	fn secp256k1_ecdsa_recover(signature: &[u8; 65], message: &[u8; 32]) -> Option<[u8; 64]> {
+		sp_io::crypto::secp256k1_ecdsa_recover(signature, message).ok()
+	}
+}
+
+/// The host functions of substrate, for `Signature::verify`
+pub(crate) struct SubstrateHostFunctions;
+
+impl HostFunctions for SubstrateHostFunctions {
+	fn sha256(data: &[u8]) -> [u8; 32] {
+		sp_io::hashing::sha2_256(data)
 	}
 }
@@ -1,24 +1,59 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "near-primitives")]
+mod conversions;
 pub mod host_functions;
 pub use host_functions::HostFunctions;
 
-use sp_io::crypto::ed25519_verify;
 use sp_std::prelude::*;
 
-use borsh::maybestd::{io::Write, string::String};
+use borsh::maybestd::{
+	io::{Error as IoError, ErrorKind, Write},
+	string::String,
+};
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use sp_core::ed25519::{Public as Ed25519Public, Signature as Ed25519Signature};
+use sp_core::ed25519::Signature as Ed25519Signature;
 
 #[derive(Debug)]
 pub struct ConversionError(String);
-#[derive(Debug, Clone)]
-pub struct PublicKey(pub [u8; 32]);
+
+impl sp_std::fmt::Display for ConversionError {
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter<'_>) -> sp_std::fmt::Result {
+		write!(f, "conversion error: {}", self.0)
+	}
+}
+
+/// Type of a key or a signature, its tag in their borsh encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+	Ed25519 = 0,
+	Secp256k1 = 1,
+}
+
+impl TryFrom<u8> for KeyType {
+	type Error = IoError;
+	fn try_from(tag: u8) -> Result<Self, Self::Error> {
+		match tag {
+			0 => Ok(Self::Ed25519),
+			1 => Ok(Self::Secp256k1),
+			_ => Err(IoError::new(ErrorKind::InvalidData, "unknown key type")),
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+	Ed25519([u8; 32]),
+	/// uncompressed, without its `0x04` prefix
+	Secp256k1([u8; 64]),
+}
 
 #[derive(Debug, Clone)]
 pub enum Signature {
 	Ed25519(Ed25519Signature),
+	/// `r`, `s` and the recovery id `v`
+	Secp256k1([u8; 65]),
 }
 
 #[derive(
@@ -37,32 +72,55 @@ pub enum Signature {
 pub struct CryptoHash(pub [u8; 32]);
 
 impl Signature {
-	const LEN: usize = 64;
+	const ED25519_LEN: usize = 64;
+	const SECP256K1_LEN: usize = 65;
 
+	/// an ed25519 signature
 	pub fn from_raw(raw: &[u8]) -> Self {
 		Self::Ed25519(Ed25519Signature::from_raw(raw.try_into().unwrap()))
 	}
 
+	pub fn key_type(&self) -> KeyType {
+		match self {
+			Self::Ed25519(_) => KeyType::Ed25519,
+			Self::Secp256k1(_) => KeyType::Secp256k1,
+		}
+	}
+
 	pub fn as_bytes(&self) -> &[u8] {
 		match self {
 			Self::Ed25519(inner) => &inner.0,
+			Self::Secp256k1(inner) => inner,
 		}
 	}
 
+	/// verifies the signature with the host functions of substrate, see `HostFunctions::verify`
 	pub fn verify(&self, data: impl AsRef<[u8]>, public_key: PublicKey) -> bool {
-		match self {
-			Self::Ed25519(signature) => {
-				ed25519_verify(signature, data.as_ref(), &Ed25519Public::from(&public_key))
-			},
-		}
+		host_functions::SubstrateHostFunctions::verify(self.clone(), data, public_key)
 	}
 }
 
 impl PublicKey {
-	const LEN: usize = 32;
+	const ED25519_LEN: usize = 32;
+	const SECP256K1_LEN: usize = 64;
 
+	/// an ed25519 public key
 	pub fn from_raw(raw: &[u8]) -> Self {
-		Self(raw.try_into().unwrap())
+		Self::Ed25519(raw.try_into().unwrap())
+	}
+
+	pub fn key_type(&self) -> KeyType {
+		match self {
+			Self::Ed25519(_) => KeyType::Ed25519,
+			Self::Secp256k1(_) => KeyType::Secp256k1,
+		}
+	}
+
+	pub fn as_bytes(&self) -> &[u8] {
+		match self {
+			Self::Ed25519(inner) => inner,
+			Self::Secp256k1(inner) => inner,
+		}
 	}
 }
 
@@ -104,20 +162,15 @@ impl AsRef<[u8]> for CryptoHash {
 	}
 }
 
-impl From<&PublicKey> for Ed25519Public {
-	fn from(pubkey: &PublicKey) -> Ed25519Public {
-		Ed25519Public(pubkey.0)
-	}
-}
-
+/// an ed25519 key from its 32 bytes, or a secp256k1 one from its 64 bytes
 impl TryFrom<&[u8]> for PublicKey {
 	type Error = ConversionError;
 	fn try_from(v: &[u8]) -> Result<Self, Self::Error> {
-		if v.len() != 32 {
-			return Err(ConversionError("wrong size".into()));
+		match v.len() {
+			Self::ED25519_LEN => Ok(Self::Ed25519(v.try_into().unwrap())),
+			Self::SECP256K1_LEN => Ok(Self::Secp256k1(v.try_into().unwrap())),
+			_ => Err(ConversionError("wrong size".into())),
 		}
-		let inner: [u8; 32] = v.try_into().unwrap();
-		Ok(PublicKey(inner))
 	}
 }
 
@@ -191,7 +244,7 @@ pub struct ValidatorStakeViewV1 {
 	pub stake: Balance,
 }
 
-#[derive(Debug, Clone, BorshDeserialize)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct ExecutionOutcomeView {
 	/// Logs from this transaction or receipt.
 	pub logs: Vec<String>,
@@ -211,7 +264,7 @@ pub struct ExecutionOutcomeView {
 	                      * having to define too many unnecessary structs) */
 }
 
-#[derive(Clone, Debug, BorshDeserialize)]
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct OutcomeProof {
 	pub proof: Vec<MerklePathItem>,
 	pub block_hash: CryptoHash,
@@ -333,36 +386,41 @@ impl From<BlockHeaderInnerLiteView> for BlockHeaderInnerLiteViewFinal {
 
 impl BorshDeserialize for Signature {
 	fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
-		let _key_type: [u8; 1] = BorshDeserialize::deserialize(buf)?;
-		let array: [u8; Self::LEN] = BorshDeserialize::deserialize(buf)?;
-		Ok(Signature::Ed25519(Ed25519Signature::from_raw(array)))
+		let key_type = KeyType::try_from(u8::deserialize(buf)?)?;
+		Ok(match key_type {
+			KeyType::Ed25519 => {
+				let array: [u8; Self::ED25519_LEN] = BorshDeserialize::deserialize(buf)?;
+				Signature::Ed25519(Ed25519Signature::from_raw(array))
+			},
+			KeyType::Secp256k1 => {
+				let array: [u8; Self::SECP256K1_LEN] = BorshDeserialize::deserialize(buf)?;
+				Signature::Secp256k1(array)
+			},
+		})
 	}
 }
 
 impl BorshSerialize for Signature {
 	fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), borsh::maybestd::io::Error> {
-		match self {
-			Signature::Ed25519(signature) => {
-				BorshSerialize::serialize(&0u8, writer)?;
-				writer.write_all(&signature.0)?;
-			},
-		}
-		Ok(())
+		BorshSerialize::serialize(&(self.key_type() as u8), writer)?;
+		writer.write_all(self.as_bytes())
 	}
 }
 
 impl BorshSerialize for PublicKey {
 	fn serialize<W: Write>(&self, writer: &mut W) -> Result<(), borsh::maybestd::io::Error> {
-		BorshSerialize::serialize(&0u8, writer)?;
-		writer.write_all(&self.0)?;
-		Ok(())
+		BorshSerialize::serialize(&(self.key_type() as u8), writer)?;
+		writer.write_all(self.as_bytes())
 	}
 }
 
 impl BorshDeserialize for PublicKey {
 	fn deserialize(buf: &mut &[u8]) -> Result<Self, borsh::maybestd::io::Error> {
-		let _key_type: [u8; 1] = BorshDeserialize::deserialize(buf)?;
-		Ok(Self(BorshDeserialize::deserialize(buf)?))
+		let key_type = KeyType::try_from(u8::deserialize(buf)?)?;
+		Ok(match key_type {
+			KeyType::Ed25519 => Self::Ed25519(BorshDeserialize::deserialize(buf)?),
+			KeyType::Secp256k1 => Self::Secp256k1(BorshDeserialize::deserialize(buf)?),
+		})
 	}
 }
 #[cfg(test)]
@@ -412,8 +470,8 @@ mod tests {
 		let pubkey_decoded =
 			bs58::decode("D6Gq2RpUoDUojmE2vLpqQzuZwYmFPW6rMcXPrwRYhqN8").into_vec().unwrap();
 		let public_key = PublicKey::from_raw(pubkey_decoded.as_ref());
-		let mut near_public_key_buffer = vec![0; PublicKey::LEN];
-		let mut public_key_buffer = vec![0; PublicKey::LEN];
+		let mut near_public_key_buffer = vec![0; PublicKey::ED25519_LEN];
+		let mut public_key_buffer = vec![0; PublicKey::ED25519_LEN];
 		near_public_key.serialize(&mut near_public_key_buffer).unwrap();
 		public_key.serialize(&mut public_key_buffer).unwrap();
 
@@ -954,7 +1012,7 @@ mod tests {
 			NearPublicKey::from_str("811gesxXYdYeThry96ZiWn8chgWYNyreiScMkmxg4U9u").unwrap();
 		let near_public_key_serialized = near_publickey.try_to_vec().unwrap();
 		let public_key = PublicKey::try_from_slice(&near_public_key_serialized).unwrap();
-		let inner_near_public_key = ED25519PublicKey::try_from(public_key.0).unwrap();
+		let inner_near_public_key = ED25519PublicKey::try_from(public_key.as_bytes()).unwrap();
 		assert_eq!(NearPublicKey::from(inner_near_public_key), near_publickey);
 
 		let public_key_encoded =
@@ -967,4 +1025,76 @@ mod tests {
 			NearPublicKey::from_str("ydgzeXHJ5Xyt7M1gXLxqLBW1Ejx6scNV5Nx2pxFM8su").unwrap()
 		);
 	}
+
+	#[test]
+	fn test_secp256k1_keys_and_signatures() {
+		use near_crypto::{
+			KeyType as NearKeyType, PublicKey as NearPublicKey, SecretKey,
+			Signature as NearSignature,
+		};
+		let secret_key = SecretKey::from_seed(NearKeyType::SECP256K1, "secp256k1");
+		let near_public_key = secret_key.public_key();
+		let message = CryptoHash::hash_bytes(b"message");
+		let near_signature = secret_key.sign(message.as_ref());
+
+		let public_key = PublicKey::try_from_slice(&near_public_key.try_to_vec().unwrap()).unwrap();
+		let signature = Signature::try_from_slice(&near_signature.try_to_vec().unwrap()).unwrap();
+		assert_eq!(public_key.key_type(), KeyType::Secp256k1);
+		assert_eq!(signature.key_type(), KeyType::Secp256k1);
+		assert_eq!(public_key.try_to_vec().unwrap(), near_public_key.try_to_vec().unwrap());
+		assert_eq!(signature.try_to_vec().unwrap(), near_signature.try_to_vec().unwrap());
+		assert_eq!(
+			NearPublicKey::try_from_slice(&public_key.try_to_vec().unwrap()).unwrap(),
+			near_public_key
+		);
+
+		assert!(signature.verify(message, public_key.clone()));
+		assert!(!signature.verify(CryptoHash::hash_bytes(b"another message"), public_key.clone()));
+		// the hash that was signed, not the message
+		assert!(!signature.verify(b"message", public_key.clone()));
+		let ed25519_key = SecretKey::from_seed(NearKeyType::ED25519, "ed25519").public_key();
+		let ed25519_key = PublicKey::try_from_slice(&ed25519_key.try_to_vec().unwrap()).unwrap();
+		assert!(!signature.verify(message, ed25519_key));
+
+		// the same signature with the higher `s`, which `near-crypto` doesn't verify either
+		const SECP256K1_ORDER: [u8; 32] = [
+			0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+			0xff, 0xfe, 0xba, 0xae, 0xdc, 0xe6, 0xaf, 0x48, 0xa0, 0x3b, 0xbf, 0xd2, 0x5e, 0x8c,
+			0xd0, 0x36, 0x41, 0x41,
+		];
+		let mut high_s = <[u8; 65]>::try_from(signature.as_bytes()).unwrap();
+		let mut borrow = 0;
+		for i in (0..32).rev() {
+			let difference = SECP256K1_ORDER[i] as i16 - high_s[32 + i] as i16 - borrow;
+			high_s[32 + i] = difference.rem_euclid(256) as u8;
+			borrow = (difference < 0) as i16;
+		}
+		high_s[64] ^= 1;
+		let near_high_s = NearSignature::from_parts(NearKeyType::SECP256K1, &high_s);
+		assert!(!near_high_s.unwrap().verify(message.as_ref(), &near_public_key));
+		assert!(!Signature::Secp256k1(high_s).verify(message, public_key));
+	}
+
+	#[test]
+	fn test_unknown_key_type() {
+		let key = [&[2][..], &[7; 32]].concat();
+		assert!(PublicKey::try_from_slice(&key).is_err());
+		let signature = [&[2][..], &[7; 64]].concat();
+		assert!(Signature::try_from_slice(&signature).is_err());
+		// a secp256k1 tag followed by an ed25519 key
+		let key = [&[1][..], &[7; 32]].concat();
+		assert!(PublicKey::try_from_slice(&key).is_err());
+
+		let view = ValidatorStakeView::V1(ValidatorStakeViewV1 {
+			account_id: "alice.near".into(),
+			public_key: PublicKey::Secp256k1([7; 64]),
+			stake: 100,
+		});
+		let mut encoded = view.try_to_vec().unwrap();
+		let decoded = ValidatorStakeView::try_from_slice(&encoded).unwrap();
+		assert_eq!(decoded.try_to_vec().unwrap(), encoded);
+		let tag = 1 + 4 + "alice.near".len();
+		encoded[tag] = 2;
+		assert!(ValidatorStakeView::try_from_slice(&encoded).is_err());
+	}
 }